@@ -0,0 +1,108 @@
+//! Parses lines typed by the player into requests to the server.
+//!
+//! Lines starting with `/` are commands, anything else is sent as a chat message:
+//!
+//! * `/vote <name>`: vote for a player, or nominate them for trial.
+//! * `/skip`: skip voting this cycle.
+//! * `/guilty`, `/innocent`: cast a verdict on the player on trial.
+//! * `/start`, `/end`: start or end a game in the current room, only for the room's host.
+//! * `/host <name>`: make another player the room's host.
+//! * `/sub <player> <spectator>`: replace a player in the game with a spectator.
+//! * `/spectate`, `/play`: sit out or play in the next game.
+//! * `/ready`, `/unready`: say whether you are ready for the next game to start.
+//! * `/rooms`: list the rooms on the server.
+//! * `/create <room>`, `/join <room>`: create or join a room.
+//! * `/leave`: go back to the lobby.
+//! * `/quit`: disconnect from the server.
+
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::Request;
+use mafia_game_lib::ServerInfo;
+use mafia_game_lib::Verdict;
+
+pub const HELP: &str = "commands: /vote <name>, /skip, /guilty, /innocent, /start, /end, \
+    /host <name>, /sub <player> <spectator>, \
+    /spectate, /play, /ready, /unready, \
+    /rooms, /create <room>, /join <room>, /leave, \
+    /quit, anything else is chat";
+
+/// Parses `input` into a request. Player names are resolved against `server_info`.
+pub fn parse_input(input: &str, server_info: &ServerInfo) -> Result<Request, String> {
+    let input = input.trim();
+
+    let Some(command) = input.strip_prefix('/') else {
+        return Ok(Request::SendMessage {
+            contents: Box::from(input),
+        });
+    };
+
+    let (command, args) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(command, args)| (command, args.trim()));
+
+    match (command, args) {
+        ("vote", "") => Err("usage: /vote <name>".to_string()),
+        ("vote", name) => {
+            let target = server_info
+                .client_id(name)
+                .ok_or_else(|| format!("no player named '{}'", name))?;
+
+            Ok(Request::CastVote {
+                target: Some(target),
+            })
+        }
+        ("skip", "") => Ok(Request::CastVote { target: None }),
+        ("guilty", "") => Ok(Request::CastVerdict {
+            verdict: Verdict::Guilty,
+        }),
+        ("innocent", "") => Ok(Request::CastVerdict {
+            verdict: Verdict::Innocent,
+        }),
+        ("start", "") => Ok(Request::StartGame),
+        ("end", "") => Ok(Request::EndGame),
+        ("host", "") => Err("usage: /host <name>".to_string()),
+        ("host", name) => {
+            let to = server_info
+                .client_id(name)
+                .ok_or_else(|| format!("no player named '{}'", name))?;
+
+            Ok(Request::TransferHost { to })
+        }
+        ("spectate", "") => Ok(Request::SetLobbyStatus {
+            status: LobbyStatus::Spectator,
+        }),
+        ("play", "") => Ok(Request::SetLobbyStatus {
+            status: LobbyStatus::Player,
+        }),
+        ("ready", "") => Ok(Request::SetReady { ready: true }),
+        ("unready", "") => Ok(Request::SetReady { ready: false }),
+        ("sub", args) => {
+            let Some((player, replacement)) = args.split_once(char::is_whitespace) else {
+                return Err("usage: /sub <player> <spectator>".to_string());
+            };
+            let player = server_info
+                .client_id(player)
+                .ok_or_else(|| format!("no player named '{}'", player))?;
+            let replacement = server_info
+                .client_id(replacement.trim())
+                .ok_or_else(|| format!("no player named '{}'", replacement.trim()))?;
+
+            Ok(Request::SubstitutePlayer {
+                player,
+                replacement,
+            })
+        }
+        ("rooms", "") => Ok(Request::ListRooms),
+        ("create", "") => Err("usage: /create <room>".to_string()),
+        ("create", name) => Ok(Request::CreateRoom {
+            name: Box::from(name),
+        }),
+        ("join", "") => Err("usage: /join <room>".to_string()),
+        ("join", name) => Ok(Request::JoinRoom {
+            name: Box::from(name),
+        }),
+        ("leave", "") => Ok(Request::LeaveRoom),
+        ("quit", "") => Ok(Request::Disconnect),
+        _ => Err(format!("invalid command '{}', {}", input, HELP)),
+    }
+}
@@ -0,0 +1,292 @@
+//! Describes game events as system messages for the chat.
+
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+use mafia_game_lib::Cycle;
+use mafia_game_lib::DeathCause;
+use mafia_game_lib::DrawReason;
+use mafia_game_lib::Entity;
+use mafia_game_lib::Event;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::Message;
+use mafia_game_lib::RoleReveal;
+use mafia_game_lib::ServerInfo;
+use mafia_game_lib::Verdict;
+use mafia_game_lib::Winners;
+
+/// Returns the name of `client_id`, or a placeholder if they are not connected.
+pub(crate) fn name(server_info: &ServerInfo, client_id: ClientId) -> &str {
+    server_info.client_name(client_id).unwrap_or("<unknown>")
+}
+
+/// Returns the names of the winning factions and players, sorted.
+pub(crate) fn winner_names(server_info: &ServerInfo, winners: &Winners) -> Vec<String> {
+    let mut names = winners
+        .factions
+        .iter()
+        .map(|faction| faction.to_string())
+        .chain(
+            winners
+                .players
+                .iter()
+                .map(|&player| name(server_info, player).to_string()),
+        )
+        .collect::<Vec<_>>();
+    names.sort();
+
+    names
+}
+
+fn sided_with(allegiance: Allegiance) -> String {
+    match allegiance {
+        Allegiance::Neutral => "sided with no one".to_string(),
+        _ => format!("sided with the {}", allegiance),
+    }
+}
+
+fn system_message(channel: EventChannel, contents: String) -> Message {
+    Message {
+        channel,
+        contents: contents.into_boxed_str(),
+        from: Entity::System,
+    }
+}
+
+/// Returns a chat message describing `event`, or `None` if the event is not shown in the chat.
+///
+/// `server_info` must be the state from before the event is applied, so that clients that just
+/// disconnected can still be named.
+pub fn describe_event(server_info: &ServerInfo, event: &Event) -> Option<Message> {
+    let message = match event {
+        Event::SetServerInfo(new_info) => {
+            if new_info.room == server_info.room {
+                return None;
+            }
+
+            system_message(
+                EventChannel::Public,
+                format!("You joined the room '{}'.", new_info.room),
+            )
+        }
+        Event::MessageReceived(_) => return None,
+        Event::SetGame(_) => {
+            system_message(EventChannel::Public, "A new game has started!".to_string())
+        }
+        Event::EndGame => system_message(EventChannel::Public, "The game was ended.".to_string()),
+        Event::ClientConnected(client_info) => system_message(
+            EventChannel::Public,
+            format!("{} connected.", client_info.name),
+        ),
+        Event::ClientDisconnected(client_id) => system_message(
+            EventChannel::Public,
+            format!("{} disconnected.", name(server_info, *client_id)),
+        ),
+        Event::HostChanged(client_id) => system_message(
+            EventChannel::Public,
+            format!("{} is now the host.", name(server_info, *client_id)),
+        ),
+        Event::LobbyStatusChanged { client, status } => system_message(
+            EventChannel::Public,
+            match status {
+                LobbyStatus::Player => {
+                    format!("{} will play in the next game.", name(server_info, *client))
+                }
+                LobbyStatus::Spectator => format!("{} is spectating.", name(server_info, *client)),
+            },
+        ),
+        Event::ReadyChanged { client, ready } => system_message(
+            EventChannel::Public,
+            if *ready {
+                format!("{} is ready.", name(server_info, *client))
+            } else {
+                format!("{} is not ready.", name(server_info, *client))
+            },
+        ),
+        Event::CountdownStarted {
+            start_time_unix_ts_secs: _,
+            duration_secs,
+        } => system_message(
+            EventChannel::Public,
+            format!(
+                "Enough players are ready, the game starts in {}s.",
+                duration_secs
+            ),
+        ),
+        Event::PlayerReplaced {
+            player,
+            replacement,
+        } => system_message(
+            EventChannel::Public,
+            format!(
+                "{} replaced {} in the game.",
+                name(server_info, *replacement),
+                name(server_info, *player)
+            ),
+        ),
+        Event::CountdownCancelled => system_message(
+            EventChannel::Public,
+            "Not enough players are ready, the countdown was cancelled.".to_string(),
+        ),
+        Event::VoteIssued {
+            voter,
+            target,
+            channel,
+        } => system_message(
+            *channel,
+            match target {
+                Some(target) => format!(
+                    "{} voted for {}.",
+                    name(server_info, *voter),
+                    name(server_info, *target)
+                ),
+                None => format!("{} voted to skip.", name(server_info, *voter)),
+            },
+        ),
+        Event::PlayerNominated { voter, accused } => system_message(
+            EventChannel::Public,
+            match accused {
+                Some(accused) => format!(
+                    "{} nominated {} for trial.",
+                    name(server_info, *voter),
+                    name(server_info, *accused)
+                ),
+                None => format!(
+                    "{} voted to not put anyone on trial.",
+                    name(server_info, *voter)
+                ),
+            },
+        ),
+        Event::TrialStarted { accused } => system_message(
+            EventChannel::Public,
+            format!("{} is put on trial.", name(server_info, *accused)),
+        ),
+        Event::TrialVerdict {
+            accused,
+            verdict,
+            votes,
+        } => {
+            let num_guilty = votes.values().filter(|v| **v == Verdict::Guilty).count();
+
+            system_message(
+                EventChannel::Public,
+                format!(
+                    "{} was found {} ({} guilty, {} innocent).",
+                    name(server_info, *accused),
+                    match verdict {
+                        Verdict::Guilty => "guilty",
+                        Verdict::Innocent => "innocent",
+                    },
+                    num_guilty,
+                    votes.len() - num_guilty
+                ),
+            )
+        }
+        Event::FailedVote { cycle, channel } => system_message(
+            *channel,
+            match (cycle, channel) {
+                (Cycle::Night, EventChannel::Mafia) => {
+                    "The Mafia could not agree on who to kill.".to_string()
+                }
+                (Cycle::Night, _) => "A killer did not pick anyone to kill.".to_string(),
+                _ => "The town did not agree on anyone to lynch.".to_string(),
+            },
+        ),
+        Event::SetCycle {
+            start_time_unix_ts_secs: _,
+            duration_secs,
+            cycle,
+            day_num,
+        } => system_message(
+            EventChannel::Public,
+            match cycle {
+                Cycle::Day => format!("Day {} begins, you have {}s.", day_num, duration_secs),
+                Cycle::Defense => format!("The accused has {}s for their defense.", duration_secs),
+                Cycle::Verdict => {
+                    format!("You have {}s to vote /guilty or /innocent.", duration_secs)
+                }
+                Cycle::Night => format!("Night {} falls, you have {}s.", day_num, duration_secs),
+            },
+        ),
+        Event::PlayerKilled {
+            player,
+            cycle,
+            death_message,
+            reveal,
+            cause,
+        } => {
+            let mut contents = match cause {
+                DeathCause::Killed => format!(
+                    "{} {} {}.",
+                    name(server_info, *player),
+                    death_message,
+                    if *cycle == Cycle::Night {
+                        "the next morning"
+                    } else {
+                        "that day"
+                    }
+                ),
+                DeathCause::Modkilled => {
+                    format!("{} {}.", name(server_info, *player), death_message)
+                }
+            };
+
+            match reveal {
+                Some(RoleReveal::Role {
+                    allegiance: _,
+                    role: Some(role),
+                }) => contents.push_str(&format!(" They were the {}.", role)),
+                Some(RoleReveal::Role {
+                    allegiance: _,
+                    role: None,
+                }) => contents.push_str(" They were a Villager."),
+                Some(RoleReveal::Allegiance(allegiance)) => {
+                    contents.push_str(&format!(" They {}.", sided_with(*allegiance)))
+                }
+                None => {}
+            }
+
+            system_message(EventChannel::Public, contents)
+        }
+        Event::PlayerInvestigated {
+            actor,
+            target,
+            allegiance,
+        } => system_message(
+            // Only the investigator and spectators are sent the result.
+            EventChannel::Spectator,
+            format!(
+                "{} investigated {} and found they {}.",
+                name(server_info, *actor),
+                name(server_info, *target),
+                sided_with(*allegiance)
+            ),
+        ),
+        Event::GameWon {
+            player_to_role: _,
+            winners,
+        } => system_message(
+            EventChannel::Public,
+            format!(
+                "The game is over, {} won!",
+                winner_names(server_info, winners).join(" and ")
+            ),
+        ),
+        Event::GameDrawn {
+            player_to_role: _,
+            reason,
+        } => system_message(
+            EventChannel::Public,
+            match reason {
+                DrawReason::MaxDays => {
+                    "The game ended in a draw, the day limit was reached.".to_string()
+                }
+                DrawReason::Stalemate => {
+                    "The game ended in a draw, no one can be killed anymore.".to_string()
+                }
+            },
+        ),
+    };
+
+    Some(message)
+}
@@ -7,10 +7,15 @@ use mafia_game_lib::Allegiance;
 use mafia_game_lib::ClientId;
 use mafia_game_lib::Message;
 use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::RoleReveal;
 use mafia_game_lib::ServerInfo;
 use mafia_game_lib::SessionToken;
 use mafia_game_lib::SpecialRole;
 
+pub mod command;
+pub mod describe;
+pub mod ui;
+
 pub const MAX_MESSAGES_HISTORY: usize = 200;
 
 /// Identity information for the client connection.
@@ -24,6 +29,17 @@ pub struct MafiaClientInner {
     pub messages: VecDeque<Message>,
 }
 
+impl MafiaClientInner {
+    /// Adds a message to the history, dropping the oldest message if the history is full.
+    pub fn push_message(&mut self, message: Message) {
+        if self.messages.len() >= MAX_MESSAGES_HISTORY {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back(message);
+    }
+}
+
 /// Maintains client-side state about a mafia game and handles drawing to the terminal.
 pub struct MafiaClient {
     ident: MafiaClientIdent,
@@ -52,7 +68,8 @@ impl MafiaClient {
     pub fn apply_event(&self, event: mafia_game_lib::Event) {
         let mut lock = self.inner.lock().unwrap();
 
-        // TODO(emersonford): translate these into messages
+        // Described before applying, so names of disconnecting clients can still be resolved.
+        let description = describe::describe_event(&lock.server_info, &event);
 
         match event {
             mafia_game_lib::Event::SetServerInfo(new_info) => {
@@ -72,12 +89,55 @@ impl MafiaClient {
             mafia_game_lib::Event::ClientDisconnected(client_id) => {
                 lock.server_info.connected_clients.remove(&client_id);
             }
-            mafia_game_lib::Event::MessageReceived(message) => {
-                if lock.messages.len() >= MAX_MESSAGES_HISTORY {
-                    lock.messages.pop_front();
+            mafia_game_lib::Event::HostChanged(client_id) => {
+                lock.server_info.host = Some(client_id);
+            }
+            mafia_game_lib::Event::LobbyStatusChanged { client, status } => {
+                if let Some(info) = lock.server_info.connected_clients.get_mut(&client) {
+                    info.lobby_status = status;
+                }
+            }
+            mafia_game_lib::Event::ReadyChanged { client, ready } => {
+                if let Some(info) = lock.server_info.connected_clients.get_mut(&client) {
+                    info.ready = ready;
                 }
+            }
+            mafia_game_lib::Event::CountdownStarted {
+                start_time_unix_ts_secs: _,
+                duration_secs: _,
+            } => {}
+            mafia_game_lib::Event::CountdownCancelled => {}
+            mafia_game_lib::Event::PlayerReplaced {
+                player,
+                replacement,
+            } => {
+                if let Some(game) = &mut lock.server_info.active_game {
+                    let swap = |client_id| {
+                        if client_id == player {
+                            replacement
+                        } else {
+                            client_id
+                        }
+                    };
 
-                lock.messages.push_back(message);
+                    if let Some(status) = game.player_status.remove(&player) {
+                        game.player_status.insert(replacement, status);
+                    }
+                    if let Some(role) = game.player_to_role.remove(&player) {
+                        game.player_to_role.insert(replacement, role);
+                    }
+                    if let Some(reveal) = game.death_reveals.remove(&player) {
+                        game.death_reveals.insert(replacement, reveal);
+                    }
+                    game.votes = std::mem::take(&mut game.votes)
+                        .into_iter()
+                        .map(|(voter, target)| (swap(voter), target.map(swap)))
+                        .collect();
+                    game.accused = game.accused.map(swap);
+                }
+            }
+            mafia_game_lib::Event::MessageReceived(message) => {
+                lock.push_message(message);
             }
             mafia_game_lib::Event::VoteIssued {
                 voter,
@@ -88,6 +148,25 @@ impl MafiaClient {
                     game.votes.insert(voter, target);
                 }
             }
+            mafia_game_lib::Event::PlayerNominated { voter, accused } => {
+                if let Some(game) = &mut lock.server_info.active_game {
+                    game.votes.insert(voter, accused);
+                }
+            }
+            mafia_game_lib::Event::TrialStarted { accused } => {
+                if let Some(game) = &mut lock.server_info.active_game {
+                    game.accused = Some(accused);
+                }
+            }
+            mafia_game_lib::Event::TrialVerdict {
+                accused: _,
+                verdict: _,
+                votes: _,
+            } => {
+                if let Some(game) = &mut lock.server_info.active_game {
+                    game.accused = None;
+                }
+            }
             mafia_game_lib::Event::FailedVote {
                 cycle: _,
                 channel: _,
@@ -110,11 +189,25 @@ impl MafiaClient {
                 player,
                 cycle: _,
                 death_message: _,
+                reveal,
+                cause: _,
             } => {
                 if let Some(game) = &mut lock.server_info.active_game {
                     game.player_status.entry(player).and_modify(|e| {
                         *e = PlayerStatus::Dead;
                     });
+
+                    if let Some(reveal) = reveal {
+                        if let RoleReveal::Role {
+                            allegiance: _,
+                            role: Some(role),
+                        } = &reveal
+                        {
+                            game.player_to_role.insert(player, role.clone());
+                        }
+
+                        game.death_reveals.insert(player, reveal);
+                    }
                 }
             }
             mafia_game_lib::Event::PlayerInvestigated {
@@ -122,21 +215,41 @@ impl MafiaClient {
                 target,
                 allegiance,
             } => {
-                if let Some(game) = &mut lock.server_info.active_game {
-                    if allegiance == Allegiance::Mafia {
-                        game.player_to_role.insert(target, SpecialRole::Mafia);
-                    }
+                if let Some(game) = &mut lock.server_info.active_game
+                    && allegiance == Allegiance::Mafia
+                {
+                    game.player_to_role.insert(target, SpecialRole::Mafia);
                 }
             }
             mafia_game_lib::Event::GameWon {
                 player_to_role,
-                side,
+                winners,
             } => {
                 if let Some(game) = &mut lock.server_info.active_game {
                     game.player_to_role = player_to_role;
-                    game.winner = Some(side);
+                    game.winner = Some(winners);
                 }
             }
+            mafia_game_lib::Event::GameDrawn {
+                player_to_role,
+                reason,
+            } => {
+                if let Some(game) = &mut lock.server_info.active_game {
+                    game.player_to_role = player_to_role;
+                    game.draw = Some(reason);
+                }
+            }
+        }
+
+        if let Some(description) = description {
+            lock.push_message(description);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    mod command;
+    mod describe;
+    mod ui;
+}
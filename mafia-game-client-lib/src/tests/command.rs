@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mafia_game_lib::ClientId;
+use mafia_game_lib::ClientInfo;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::Request;
+use mafia_game_lib::ServerInfo;
+use mafia_game_lib::Verdict;
+
+use crate::command::parse_input;
+
+#[test]
+fn test_parse_input() {
+    let server_info = ServerInfo {
+        room: Arc::from("lobby"),
+        host: Some(ClientId(0)),
+        connected_clients: HashMap::from_iter([(
+            ClientId(3),
+            ClientInfo {
+                name: Arc::from("pearl"),
+                id: ClientId(3),
+                lobby_status: LobbyStatus::Player,
+                ready: false,
+            },
+        )]),
+        active_game: None,
+    };
+
+    assert_eq!(
+        parse_input("hello world ", &server_info),
+        Ok(Request::SendMessage {
+            contents: Box::from("hello world")
+        })
+    );
+    assert_eq!(
+        parse_input("/vote pearl", &server_info),
+        Ok(Request::CastVote {
+            target: Some(ClientId(3))
+        })
+    );
+    assert_eq!(
+        parse_input("/skip", &server_info),
+        Ok(Request::CastVote { target: None })
+    );
+    assert_eq!(
+        parse_input("/guilty", &server_info),
+        Ok(Request::CastVerdict {
+            verdict: Verdict::Guilty
+        })
+    );
+    assert_eq!(
+        parse_input("/innocent", &server_info),
+        Ok(Request::CastVerdict {
+            verdict: Verdict::Innocent
+        })
+    );
+    assert_eq!(parse_input("/start", &server_info), Ok(Request::StartGame));
+    assert_eq!(parse_input("/end", &server_info), Ok(Request::EndGame));
+    assert_eq!(
+        parse_input("/host pearl", &server_info),
+        Ok(Request::TransferHost { to: ClientId(3) })
+    );
+    assert_eq!(
+        parse_input("/spectate", &server_info),
+        Ok(Request::SetLobbyStatus {
+            status: LobbyStatus::Spectator
+        })
+    );
+    assert_eq!(
+        parse_input("/sub pearl  pearl", &server_info),
+        Ok(Request::SubstitutePlayer {
+            player: ClientId(3),
+            replacement: ClientId(3)
+        })
+    );
+    assert_eq!(
+        parse_input("/ready", &server_info),
+        Ok(Request::SetReady { ready: true })
+    );
+    assert_eq!(
+        parse_input("/play", &server_info),
+        Ok(Request::SetLobbyStatus {
+            status: LobbyStatus::Player
+        })
+    );
+    assert_eq!(parse_input("/quit", &server_info), Ok(Request::Disconnect));
+    assert_eq!(parse_input("/rooms", &server_info), Ok(Request::ListRooms));
+    assert_eq!(
+        parse_input("/join beach house", &server_info),
+        Ok(Request::JoinRoom {
+            name: Box::from("beach house")
+        })
+    );
+    assert_eq!(parse_input("/leave", &server_info), Ok(Request::LeaveRoom));
+
+    assert!(parse_input("/vote", &server_info).is_err());
+    assert!(parse_input("/vote garnet", &server_info).is_err());
+    assert!(parse_input("/skip pearl", &server_info).is_err());
+    assert!(parse_input("/create", &server_info).is_err());
+    assert!(parse_input("/host garnet", &server_info).is_err());
+    assert!(parse_input("/sub pearl", &server_info).is_err());
+    assert!(parse_input("/dance", &server_info).is_err());
+}
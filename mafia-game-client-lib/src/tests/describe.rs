@@ -0,0 +1,288 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+use mafia_game_lib::ClientInfo;
+use mafia_game_lib::Cycle;
+use mafia_game_lib::DeathCause;
+use mafia_game_lib::Entity;
+use mafia_game_lib::Event;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::GameInfo;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::RoleReveal;
+use mafia_game_lib::ServerInfo;
+use mafia_game_lib::SessionToken;
+use mafia_game_lib::SpecialRole;
+use mafia_game_lib::Verdict;
+use mafia_game_lib::Winners;
+
+use crate::MafiaClient;
+use crate::MafiaClientIdent;
+use crate::describe::describe_event;
+
+fn client_info(id: u64, name: &str) -> (ClientId, ClientInfo) {
+    (
+        ClientId(id),
+        ClientInfo {
+            name: Arc::from(name),
+            id: ClientId(id),
+            lobby_status: LobbyStatus::Player,
+            ready: false,
+        },
+    )
+}
+
+fn server_info() -> ServerInfo {
+    ServerInfo {
+        room: Arc::from("lobby"),
+        host: Some(ClientId(0)),
+        connected_clients: HashMap::from_iter([
+            client_info(0, "garnet"),
+            client_info(1, "amethyst"),
+            client_info(2, "pearl"),
+        ]),
+        active_game: None,
+    }
+}
+
+fn describe(event: Event) -> Option<(EventChannel, String)> {
+    describe_event(&server_info(), &event).map(|message| {
+        assert_eq!(message.from, Entity::System);
+        (message.channel, message.contents.to_string())
+    })
+}
+
+#[test]
+fn test_describe_event() {
+    assert_eq!(
+        describe(Event::PlayerKilled {
+            player: ClientId(2),
+            cycle: Cycle::Night,
+            death_message: Box::from("was found stabbed to death"),
+            reveal: None,
+            cause: DeathCause::Killed,
+        }),
+        Some((
+            EventChannel::Public,
+            "pearl was found stabbed to death the next morning.".to_string()
+        ))
+    );
+
+    assert_eq!(
+        describe(Event::PlayerKilled {
+            player: ClientId(2),
+            cycle: Cycle::Day,
+            death_message: Box::from("was removed from the game for being inactive"),
+            reveal: None,
+            cause: DeathCause::Modkilled,
+        })
+        .unwrap()
+        .1,
+        "pearl was removed from the game for being inactive."
+    );
+
+    assert_eq!(
+        describe(Event::PlayerKilled {
+            player: ClientId(1),
+            cycle: Cycle::Verdict,
+            death_message: Box::from("was hung for their unforgivable sins"),
+            reveal: Some(RoleReveal::Role {
+                allegiance: Allegiance::Villagers,
+                role: Some(SpecialRole::Doctor),
+            }),
+            cause: DeathCause::Killed,
+        }),
+        Some((
+            EventChannel::Public,
+            "amethyst was hung for their unforgivable sins that day. They were the Doctor."
+                .to_string()
+        ))
+    );
+
+    assert_eq!(
+        describe(Event::VoteIssued {
+            voter: ClientId(0),
+            target: Some(ClientId(2)),
+            channel: EventChannel::Mafia,
+        }),
+        Some((EventChannel::Mafia, "garnet voted for pearl.".to_string()))
+    );
+
+    assert_eq!(
+        describe(Event::PlayerInvestigated {
+            actor: ClientId(0),
+            target: ClientId(2),
+            allegiance: Allegiance::Mafia,
+        })
+        .unwrap()
+        .0,
+        EventChannel::Spectator
+    );
+
+    assert_eq!(
+        describe(Event::FailedVote {
+            cycle: Cycle::Night,
+            channel: EventChannel::Mafia,
+        }),
+        Some((
+            EventChannel::Mafia,
+            "The Mafia could not agree on who to kill.".to_string()
+        ))
+    );
+
+    assert_eq!(
+        describe(Event::TrialVerdict {
+            accused: ClientId(1),
+            verdict: Verdict::Guilty,
+            votes: HashMap::from_iter([
+                (ClientId(0), Verdict::Guilty),
+                (ClientId(2), Verdict::Innocent),
+            ]),
+        }),
+        Some((
+            EventChannel::Public,
+            "amethyst was found guilty (1 guilty, 1 innocent).".to_string()
+        ))
+    );
+
+    assert_eq!(
+        describe(Event::GameWon {
+            player_to_role: HashMap::new(),
+            winners: Winners {
+                factions: BTreeSet::from_iter([Allegiance::Villagers]),
+                players: BTreeSet::from_iter([ClientId(1)]),
+            },
+        }),
+        Some((
+            EventChannel::Public,
+            "The game is over, Villagers and amethyst won!".to_string()
+        ))
+    );
+
+    assert_eq!(
+        describe(Event::ClientDisconnected(ClientId(7))).unwrap().1,
+        "<unknown> disconnected."
+    );
+    assert_eq!(
+        describe(Event::HostChanged(ClientId(1))).unwrap().1,
+        "amethyst is now the host."
+    );
+    assert_eq!(
+        describe(Event::LobbyStatusChanged {
+            client: ClientId(1),
+            status: LobbyStatus::Spectator,
+        })
+        .unwrap()
+        .1,
+        "amethyst is spectating."
+    );
+    assert_eq!(
+        describe(Event::PlayerReplaced {
+            player: ClientId(1),
+            replacement: ClientId(0),
+        })
+        .unwrap()
+        .1,
+        "garnet replaced amethyst in the game."
+    );
+    assert_eq!(
+        describe(Event::CountdownStarted {
+            start_time_unix_ts_secs: 10,
+            duration_secs: 10,
+        })
+        .unwrap()
+        .1,
+        "Enough players are ready, the game starts in 10s."
+    );
+    assert_eq!(describe(Event::SetServerInfo(server_info())), None);
+    assert_eq!(
+        describe(Event::SetServerInfo(ServerInfo {
+            room: Arc::from("beach"),
+            ..server_info()
+        })),
+        Some((
+            EventChannel::Public,
+            "You joined the room 'beach'.".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_apply_event_describes_disconnect() {
+    let client = MafiaClient::new(
+        MafiaClientIdent {
+            id: ClientId(0),
+            session_token: SessionToken::new(),
+        },
+        server_info(),
+    );
+
+    client.apply_event(Event::ClientDisconnected(ClientId(2)));
+
+    let inner = client.get_inner();
+    assert_eq!(inner.server_info.client_name(ClientId(2)), None);
+    assert_eq!(
+        inner.messages.back().map(|message| &*message.contents),
+        Some("pearl disconnected.")
+    );
+}
+
+#[test]
+fn test_apply_event_player_replaced() {
+    let client = MafiaClient::new(
+        MafiaClientIdent {
+            id: ClientId(0),
+            session_token: SessionToken::new(),
+        },
+        ServerInfo {
+            active_game: Some(GameInfo {
+                cycle_start_time_unix_ts_secs: 0,
+                cycle_duration_secs: 10,
+                current_cycle: Cycle::Day,
+                day_num: 2,
+                player_to_role: HashMap::from_iter([(ClientId(1), SpecialRole::Doctor)]),
+                player_status: HashMap::from_iter([
+                    (ClientId(0), PlayerStatus::Alive),
+                    (ClientId(1), PlayerStatus::Dead),
+                ]),
+                votes: HashMap::new(),
+                accused: None,
+                death_reveals: HashMap::from_iter([(
+                    ClientId(1),
+                    RoleReveal::Allegiance(Allegiance::Villagers),
+                )]),
+                winner: None,
+                draw: None,
+            }),
+            ..server_info()
+        },
+    );
+
+    client.apply_event(Event::PlayerReplaced {
+        player: ClientId(1),
+        replacement: ClientId(2),
+    });
+
+    // Everything known about the player moves over to their replacement.
+    let inner = client.get_inner();
+    let game = inner.server_info.active_game.as_ref().unwrap();
+    assert_eq!(
+        game.player_status,
+        HashMap::from_iter([
+            (ClientId(0), PlayerStatus::Alive),
+            (ClientId(2), PlayerStatus::Dead),
+        ])
+    );
+    assert_eq!(
+        game.player_to_role,
+        HashMap::from_iter([(ClientId(2), SpecialRole::Doctor)])
+    );
+    assert_eq!(
+        game.death_reveals,
+        HashMap::from_iter([(ClientId(2), RoleReveal::Allegiance(Allegiance::Villagers))])
+    );
+}
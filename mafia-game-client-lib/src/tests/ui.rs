@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mafia_game_lib::ClientId;
+use mafia_game_lib::ClientInfo;
+use mafia_game_lib::Cycle;
+use mafia_game_lib::Entity;
+use mafia_game_lib::Event;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::GameInfo;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::Message;
+use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::ServerInfo;
+use mafia_game_lib::SessionToken;
+use mafia_game_lib::SpecialRole;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use tui_textarea::TextArea;
+
+use crate::MafiaClient;
+use crate::MafiaClientIdent;
+
+fn client_info(id: u64, name: &str) -> (ClientId, ClientInfo) {
+    (
+        ClientId(id),
+        ClientInfo {
+            name: Arc::from(name),
+            id: ClientId(id),
+            lobby_status: LobbyStatus::Player,
+            ready: false,
+        },
+    )
+}
+
+#[test]
+fn test_draw() {
+    let client = MafiaClient::new(
+        MafiaClientIdent {
+            id: ClientId(0),
+            session_token: SessionToken::new(),
+        },
+        ServerInfo {
+            room: Arc::from("lobby"),
+            host: Some(ClientId(0)),
+            connected_clients: HashMap::from_iter([
+                client_info(0, "garnet"),
+                client_info(1, "amethyst"),
+                client_info(2, "pearl"),
+            ]),
+            active_game: Some(GameInfo {
+                cycle_start_time_unix_ts_secs: 0,
+                cycle_duration_secs: 0,
+                current_cycle: Cycle::Night,
+                day_num: 2,
+                player_to_role: HashMap::from_iter([(ClientId(0), SpecialRole::Mafia)]),
+                player_status: HashMap::from_iter([
+                    (ClientId(0), PlayerStatus::Alive),
+                    (ClientId(1), PlayerStatus::Dead),
+                ]),
+                votes: HashMap::from_iter([(ClientId(0), None)]),
+                accused: None,
+                death_reveals: HashMap::new(),
+                winner: None,
+                draw: None,
+            }),
+        },
+    );
+
+    client.apply_event(Event::MessageReceived(Message {
+        channel: EventChannel::Mafia,
+        contents: Box::from("who should we pick?"),
+        from: Entity::Client(ClientId(0)),
+    }));
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+    terminal
+        .draw(|frame| client.draw(frame, &TextArea::default()))
+        .unwrap();
+
+    let screen = terminal
+        .backend()
+        .buffer()
+        .content()
+        .chunks(100)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    assert!(screen.contains("Night 2 | 0s left | you are Mafia"));
+    assert!(screen.contains("[mafia] garnet: who should we pick?"));
+    assert!(screen.contains("garnet (you) (host) Mafia"));
+    assert!(screen.contains("amethyst [dead]"));
+    assert!(screen.contains("pearl [spectator]"));
+    assert!(screen.contains("garnet -> skip"));
+}
@@ -0,0 +1,251 @@
+//! Draws [`MafiaClient`] state to the terminal.
+//!
+//! The screen is split into a status line, the chat, a sidebar with the player list and current
+//! votes, and the input line.
+
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use mafia_game_lib::Cycle;
+use mafia_game_lib::Entity;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::GameInfo;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::Message;
+use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::ServerInfo;
+use ratatui::Frame;
+use ratatui::layout::Constraint;
+use ratatui::layout::Layout;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Line;
+use ratatui::text::Span;
+use ratatui::widgets::Block;
+use ratatui::widgets::List;
+use ratatui::widgets::ListItem;
+use ratatui::widgets::Paragraph;
+use tui_textarea::TextArea;
+
+use crate::MafiaClient;
+use crate::MafiaClientInner;
+use crate::describe::name;
+use crate::describe::winner_names;
+
+impl MafiaClient {
+    /// Draws the full client UI, with `input` as the line being typed.
+    pub fn draw(&self, frame: &mut Frame, input: &TextArea) {
+        let inner = self.get_inner();
+
+        let [status_area, main_area, input_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+        let [chat_area, sidebar_area] =
+            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
+                .areas(main_area);
+        let [players_area, votes_area] =
+            Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(sidebar_area);
+
+        frame.render_widget(self.status_line(&inner.server_info), status_area);
+        draw_chat(frame, &inner, chat_area);
+        frame.render_widget(self.player_list(&inner.server_info), players_area);
+        frame.render_widget(vote_panel(&inner.server_info), votes_area);
+        frame.render_widget(input, input_area);
+    }
+
+    fn status_line(&self, server_info: &ServerInfo) -> Paragraph<'static> {
+        let Some(game) = &server_info.active_game else {
+            return Paragraph::new(format!(
+                "[{}] No game in progress, type /start to start one",
+                server_info.room
+            ));
+        };
+
+        if let Some(winners) = &game.winner {
+            return Paragraph::new(format!(
+                "Game over, won by {}",
+                winner_names(server_info, winners).join(", ")
+            ))
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        }
+
+        if let Some(reason) = game.draw {
+            return Paragraph::new(format!("Game over, draw ({:?})", reason))
+                .style(Style::new().add_modifier(Modifier::BOLD));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        let time_left =
+            (game.cycle_start_time_unix_ts_secs + game.cycle_duration_secs).saturating_sub(now);
+
+        let you = match (
+            game.player_status.get(&self.get_ident().id),
+            game.player_to_role.get(&self.get_ident().id),
+        ) {
+            (None, _) => "spectating".to_string(),
+            (Some(PlayerStatus::Dead), _) => "you are dead".to_string(),
+            (Some(PlayerStatus::Alive), Some(role)) => format!("you are {}", role),
+            (Some(PlayerStatus::Alive), None) => "you are a Villager".to_string(),
+        };
+
+        let cycle = match game.current_cycle {
+            Cycle::Day => format!("Day {}", game.day_num),
+            Cycle::Defense => format!("Day {}, defense", game.day_num),
+            Cycle::Verdict => format!("Day {}, verdict", game.day_num),
+            Cycle::Night => format!("Night {}", game.day_num),
+        };
+
+        Paragraph::new(format!(
+            "[{}] {} | {}s left | {}",
+            server_info.room, cycle, time_left, you
+        ))
+    }
+
+    fn player_list(&self, server_info: &ServerInfo) -> List<'static> {
+        let mut client_ids = server_info.connected_clients.keys().collect::<Vec<_>>();
+        client_ids.sort();
+
+        let items = client_ids.into_iter().map(|&client_id| {
+            let mut text = name(server_info, client_id).to_string();
+            let mut style = Style::new();
+
+            if client_id == self.get_ident().id {
+                text.push_str(" (you)");
+                style = style.add_modifier(Modifier::BOLD);
+            }
+
+            if server_info.host == Some(client_id) {
+                text.push_str(" (host)");
+            }
+
+            if let Some(game) = &server_info.active_game {
+                match game.player_status.get(&client_id) {
+                    None => {
+                        text.push_str(" [spectator]");
+                        style = style.fg(Color::DarkGray);
+                    }
+                    Some(PlayerStatus::Dead) => {
+                        text.push_str(" [dead]");
+                        style = style
+                            .fg(Color::DarkGray)
+                            .add_modifier(Modifier::CROSSED_OUT);
+                    }
+                    Some(PlayerStatus::Alive) => {}
+                }
+
+                if let Some(role) = game.player_to_role.get(&client_id) {
+                    text.push_str(&format!(" {}", role));
+                } else if let Some(reveal) = game.death_reveals.get(&client_id) {
+                    text.push_str(&format!(" {}", reveal.allegiance()));
+                }
+            } else {
+                let info = &server_info.connected_clients[&client_id];
+
+                if info.lobby_status == LobbyStatus::Spectator {
+                    text.push_str(" [spectator]");
+                    style = style.fg(Color::DarkGray);
+                } else if info.ready {
+                    text.push_str(" [ready]");
+                }
+            }
+
+            ListItem::new(text).style(style)
+        });
+
+        List::new(items).block(Block::bordered().title("Players"))
+    }
+}
+
+fn draw_chat(frame: &mut Frame, inner: &MafiaClientInner, area: Rect) {
+    // Only the latest messages that fit, minus the borders.
+    let num_visible = usize::from(area.height.saturating_sub(2));
+
+    let items = inner
+        .messages
+        .iter()
+        .skip(inner.messages.len().saturating_sub(num_visible))
+        .map(|message| ListItem::new(chat_line(&inner.server_info, message)))
+        .collect::<Vec<_>>();
+
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title("Chat")),
+        area,
+    );
+}
+
+fn chat_line(server_info: &ServerInfo, message: &Message) -> Line<'static> {
+    let style = match message.channel {
+        EventChannel::Public => Style::new(),
+        EventChannel::Mafia => Style::new().fg(Color::Red),
+        EventChannel::Spectator => Style::new().fg(Color::DarkGray),
+    };
+
+    let mut spans = Vec::new();
+
+    match message.channel {
+        EventChannel::Public => {}
+        EventChannel::Mafia => spans.push(Span::raw("[mafia] ")),
+        EventChannel::Spectator => spans.push(Span::raw("[dead] ")),
+    }
+
+    match message.from {
+        Entity::Client(client_id) => {
+            spans.push(Span::styled(
+                format!("{}: ", name(server_info, client_id)),
+                Style::new().add_modifier(Modifier::BOLD),
+            ));
+            spans.push(Span::raw(message.contents.to_string()));
+        }
+        Entity::System => {
+            spans.push(Span::styled(
+                message.contents.to_string(),
+                Style::new().add_modifier(Modifier::ITALIC),
+            ));
+        }
+    }
+
+    Line::from(spans).style(style)
+}
+
+fn vote_panel(server_info: &ServerInfo) -> List<'static> {
+    let Some(game) = &server_info.active_game else {
+        return List::default().block(Block::bordered().title("Votes"));
+    };
+
+    let mut items = Vec::new();
+
+    if let Some(accused) = game.accused {
+        items.push(
+            ListItem::new(format!("On trial: {}", name(server_info, accused)))
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        );
+    }
+
+    items.extend(vote_lines(server_info, game).into_iter().map(ListItem::new));
+
+    List::new(items).block(Block::bordered().title("Votes"))
+}
+
+fn vote_lines(server_info: &ServerInfo, game: &GameInfo) -> Vec<String> {
+    let mut votes = game.votes.iter().collect::<Vec<_>>();
+    votes.sort();
+
+    votes
+        .into_iter()
+        .map(|(&voter, target)| {
+            format!(
+                "{} -> {}",
+                name(server_info, voter),
+                target.map_or("skip", |target| name(server_info, target))
+            )
+        })
+        .collect()
+}
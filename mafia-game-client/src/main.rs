@@ -0,0 +1,228 @@
+//! Terminal client for the Mafia game, connects to a `mafia-game-server`.
+//!
+//! Usage: `mafia-game-client <name> [addr]`
+
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use mafia_game_client_lib::MafiaClient;
+use mafia_game_client_lib::MafiaClientIdent;
+use mafia_game_client_lib::command::HELP;
+use mafia_game_client_lib::command::parse_input;
+use mafia_game_lib::Entity;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::Message;
+use mafia_game_lib::Request;
+use mafia_game_lib::Response;
+use mafia_game_lib::ServerInfo;
+use mafia_game_lib::ServerMessage;
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event;
+use ratatui::crossterm::event::KeyCode;
+use ratatui::crossterm::event::KeyEventKind;
+use ratatui::crossterm::event::KeyModifiers;
+use ratatui::widgets::Block;
+use tui_textarea::TextArea;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:4567";
+/// How often the screen is redrawn when there is no input.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+fn send(writer: &mut TcpStream, request: &Request) -> io::Result<()> {
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+
+    writer.write_all(&line)
+}
+
+fn recv<R: BufRead>(reader: &mut R) -> io::Result<Option<ServerMessage>> {
+    let mut line = String::new();
+
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+fn system_message(contents: impl Into<Box<str>>) -> Message {
+    Message {
+        channel: EventChannel::Public,
+        contents: contents.into(),
+        from: Entity::System,
+    }
+}
+
+fn new_input() -> TextArea<'static> {
+    let mut input = TextArea::default();
+    input.set_block(Block::bordered().title("Message, or /help"));
+
+    input
+}
+
+/// Applies messages from the server to `client` until the connection closes.
+fn receive_messages<R: BufRead>(mut reader: R, client: &MafiaClient, closed: &AtomicBool) {
+    loop {
+        match recv(&mut reader) {
+            Ok(Some(ServerMessage::Event(event))) => {
+                client.apply_event(Arc::unwrap_or_clone(event.event));
+            }
+            Ok(Some(ServerMessage::Response(Response::Error(e)))) => {
+                client.get_inner().push_message(system_message(e));
+            }
+            Ok(Some(ServerMessage::Response(Response::Rooms(rooms)))) => {
+                let rooms = rooms
+                    .iter()
+                    .map(|room| {
+                        format!(
+                            "{} ({} clients{})",
+                            room.name,
+                            room.num_clients,
+                            if room.in_game { ", in game" } else { "" }
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                client
+                    .get_inner()
+                    .push_message(system_message(format!("rooms: {}", rooms.join(", "))));
+            }
+            Ok(Some(ServerMessage::Response(_))) => {}
+            Ok(None) => break,
+            Err(e) => {
+                client
+                    .get_inner()
+                    .push_message(system_message(format!("connection error: {}", e)));
+                break;
+            }
+        }
+    }
+
+    closed.store(true, Ordering::Relaxed);
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    client: &MafiaClient,
+    writer: &mut TcpStream,
+    closed: &AtomicBool,
+) -> io::Result<()> {
+    let mut input = new_input();
+
+    while !closed.load(Ordering::Relaxed) {
+        terminal.draw(|frame| client.draw(frame, &input))?;
+
+        if !event::poll(REDRAW_INTERVAL)? {
+            continue;
+        }
+
+        let event::Event::Key(key) = event::read()? else {
+            continue;
+        };
+
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Enter => {
+                let line = input.lines().join(" ");
+                input = new_input();
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                if line.trim() == "/help" {
+                    client.get_inner().push_message(system_message(HELP));
+                    continue;
+                }
+
+                let request = parse_input(&line, &client.get_inner().server_info);
+
+                match request {
+                    Ok(Request::Disconnect) => break,
+                    Ok(request) => send(writer, &request)?,
+                    Err(e) => client.get_inner().push_message(system_message(e)),
+                }
+            }
+            _ => {
+                input.input(key);
+            }
+        }
+    }
+
+    send(writer, &Request::Disconnect)
+}
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+
+    let Some(name) = args.next() else {
+        eprintln!("usage: mafia-game-client <name> [addr]");
+        std::process::exit(2);
+    };
+    let addr = args.next().unwrap_or_else(|| DEFAULT_ADDR.to_string());
+
+    let mut writer = TcpStream::connect(&addr)?;
+    let mut reader = BufReader::new(writer.try_clone()?);
+
+    send(
+        &mut writer,
+        &Request::Connect {
+            name: Box::from(name.as_str()),
+            resume_from: None,
+        },
+    )?;
+
+    let (id, session_token) = match recv(&mut reader)? {
+        Some(ServerMessage::Response(Response::Connected { id, session_token })) => {
+            (id, session_token)
+        }
+        Some(ServerMessage::Response(Response::Error(e))) => {
+            return Err(io::Error::other(format!("failed to connect: {}", e)));
+        }
+        other => {
+            return Err(io::Error::other(format!(
+                "unexpected message from server: {:?}",
+                other
+            )));
+        }
+    };
+
+    let client = Arc::new(MafiaClient::new(
+        MafiaClientIdent { id, session_token },
+        ServerInfo {
+            // Set by the first event from the server.
+            room: Arc::from(""),
+            host: None,
+            connected_clients: HashMap::new(),
+            active_game: None,
+        },
+    ));
+    let closed = Arc::new(AtomicBool::new(false));
+
+    thread::spawn({
+        let client = Arc::clone(&client);
+        let closed = Arc::clone(&closed);
+
+        move || receive_messages(reader, &client, &closed)
+    });
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &client, &mut writer, &closed);
+    ratatui::restore();
+
+    result
+}
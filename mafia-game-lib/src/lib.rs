@@ -1,16 +1,37 @@
 //! Data structured shared by both the Mafia server and client.
 
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::sync::Arc;
 
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use serde::Serializer;
 use uuid::Uuid;
 
 /// Identifier for a connected client.
+///
+/// The low 32 bits are the client's slot on the server, which is handed out again once the
+/// client is purged. The high 32 bits are the slot's generation, bumped on every reuse, so IDs
+/// of purged clients never refer to the client that took their slot.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
-pub struct ClientId(pub usize);
+pub struct ClientId(pub u64);
+
+impl ClientId {
+    pub fn new(slot: u32, generation: u32) -> Self {
+        Self(u64::from(generation) << 32 | u64::from(slot))
+    }
+
+    pub fn slot(self) -> u32 {
+        self.0 as u32
+    }
+
+    pub fn generation(self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+}
 
 /// Unique token to auth a client to the server.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
@@ -39,25 +60,96 @@ impl Display for SessionToken {
 pub enum Allegiance {
     Mafia,
     Villagers,
+    /// Not part of a faction, each neutral player has their own win condition.
+    Neutral,
+}
+
+impl Display for Allegiance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Allegiance::Mafia => "Mafia",
+            Allegiance::Villagers => "Villagers",
+            Allegiance::Neutral => "Neutral",
+        }
+        .fmt(f)
+    }
 }
 
 /// A special role a player can be.
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+///
+/// Serialized as the role's ID, e.g. `"Mafia"`, so roles can be used as map keys in configs.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum SpecialRole {
     Mafia,
     /// Protects one player from Mafia death each night.
     Doctor,
     /// Investigates the allegiance of one player each night.
     Detective,
+    /// Neutral, wins if lynched.
+    Jester,
+    /// Neutral, kills one player each night and wins if they are the last one standing.
+    SerialKiller,
+    /// House role added by the server, identified by an ID not taken by the roles above.
+    Custom(Arc<str>),
 }
 
 impl SpecialRole {
-    pub fn allegiance(&self) -> Allegiance {
+    /// Returns the ID the role is serialized as.
+    pub fn id(&self) -> &str {
+        match self {
+            SpecialRole::Mafia => "Mafia",
+            SpecialRole::Doctor => "Doctor",
+            SpecialRole::Detective => "Detective",
+            SpecialRole::Jester => "Jester",
+            SpecialRole::SerialKiller => "SerialKiller",
+            SpecialRole::Custom(id) => id,
+        }
+    }
+}
+
+impl From<Arc<str>> for SpecialRole {
+    /// Returns the built-in role with the ID `id`, or a [`SpecialRole::Custom`] one.
+    fn from(id: Arc<str>) -> Self {
+        match &*id {
+            "Mafia" => SpecialRole::Mafia,
+            "Doctor" => SpecialRole::Doctor,
+            "Detective" => SpecialRole::Detective,
+            "Jester" => SpecialRole::Jester,
+            "SerialKiller" => SpecialRole::SerialKiller,
+            _ => SpecialRole::Custom(id),
+        }
+    }
+}
+
+impl From<&str> for SpecialRole {
+    fn from(id: &str) -> Self {
+        SpecialRole::from(Arc::<str>::from(id))
+    }
+}
+
+impl Serialize for SpecialRole {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for SpecialRole {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Arc::<str>::deserialize(deserializer).map(SpecialRole::from)
+    }
+}
+
+impl Display for SpecialRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SpecialRole::Mafia => Allegiance::Mafia,
-            SpecialRole::Doctor => Allegiance::Villagers,
-            SpecialRole::Detective => Allegiance::Villagers,
+            SpecialRole::Mafia => "Mafia",
+            SpecialRole::Doctor => "Doctor",
+            SpecialRole::Detective => "Detective",
+            SpecialRole::Jester => "Jester",
+            SpecialRole::SerialKiller => "Serial Killer",
+            SpecialRole::Custom(id) => id,
         }
+        .fmt(f)
     }
 }
 
@@ -72,23 +164,123 @@ pub enum PlayerStatus {
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub enum Cycle {
     Day,
+    /// Part of the day where a player on trial defends themselves.
+    Defense,
+    /// Part of the day where players vote on the verdict of a trial.
+    Verdict,
     Night,
 }
 
 impl Cycle {
+    /// Returns the cycle following this one, assuming it is not interrupted by a trial.
     pub fn next(self) -> Self {
         match self {
             Self::Night => Self::Day,
             Self::Day => Self::Night,
+            Self::Defense => Self::Verdict,
+            Self::Verdict => Self::Night,
+        }
+    }
+
+    /// Returns `true` if this cycle is part of the day.
+    pub fn is_day(self) -> bool {
+        match self {
+            Self::Day | Self::Defense | Self::Verdict => true,
+            Self::Night => false,
+        }
+    }
+}
+
+/// Vote cast on a player on trial.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Verdict {
+    Guilty,
+    Innocent,
+}
+
+/// Information revealed about a player's role when they die.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum RoleReveal {
+    /// Only which side the player was on.
+    Allegiance(Allegiance),
+    /// The player's full role. `role` is `None` if the player had no special role.
+    Role {
+        allegiance: Allegiance,
+        role: Option<SpecialRole>,
+    },
+}
+
+impl RoleReveal {
+    pub fn allegiance(&self) -> Allegiance {
+        match self {
+            RoleReveal::Allegiance(allegiance) => *allegiance,
+            RoleReveal::Role {
+                allegiance,
+                role: _,
+            } => *allegiance,
+        }
+    }
+}
+
+/// Who won a game.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Winners {
+    /// Factions that met their win condition, every member of a faction wins with it.
+    pub factions: BTreeSet<Allegiance>,
+    /// Players that won on their own, e.g. neutral players.
+    pub players: BTreeSet<ClientId>,
+}
+
+impl From<Allegiance> for Winners {
+    fn from(value: Allegiance) -> Self {
+        Winners {
+            factions: BTreeSet::from([value]),
+            players: BTreeSet::new(),
         }
     }
 }
 
+/// Why a game ended without a winner.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum DrawReason {
+    /// No one won before the maximum number of days was reached.
+    MaxDays,
+    /// No one can be killed anymore, so no one can win.
+    Stalemate,
+}
+
+/// Why a player died.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DeathCause {
+    /// Lynched during the day or killed at night.
+    #[default]
+    Killed,
+    /// Removed from the game by the server for not voting.
+    Modkilled,
+}
+
+/// Whether a client wants to play in the next game of their room.
+#[derive(
+    Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize,
+)]
+pub enum LobbyStatus {
+    /// Gets a role when the next game starts.
+    #[default]
+    Player,
+    /// Only watches games, sees everything spectators in a game do.
+    Spectator,
+}
+
 /// Public information about a client.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct ClientInfo {
     pub name: Arc<str>,
     pub id: ClientId,
+    #[serde(default)]
+    pub lobby_status: LobbyStatus,
+    /// Set once the client is ready for the next game to start.
+    #[serde(default)]
+    pub ready: bool,
 }
 
 /// Public information about a game.
@@ -103,15 +295,50 @@ pub struct GameInfo {
     pub player_to_role: HashMap<ClientId, SpecialRole>,
     pub player_status: HashMap<ClientId, PlayerStatus>,
     pub votes: HashMap<ClientId, Option<ClientId>>,
-    pub winner: Option<Allegiance>,
+    /// Player currently on trial.
+    pub accused: Option<ClientId>,
+    /// What was revealed about each dead player when they died.
+    pub death_reveals: HashMap<ClientId, RoleReveal>,
+    pub winner: Option<Winners>,
+    pub draw: Option<DrawReason>,
 }
 
+/// Public information about a room.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub name: Arc<str>,
+    pub num_clients: usize,
+    pub in_game: bool,
+}
+
+/// What a client can see of the server, scoped to the room they are in.
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct ServerInfo {
+    pub room: Arc<str>,
+    /// Client allowed to start and end games in the room.
+    pub host: Option<ClientId>,
+    /// Clients in the room.
     pub connected_clients: HashMap<ClientId, ClientInfo>,
     pub active_game: Option<GameInfo>,
 }
 
+impl ServerInfo {
+    /// Returns the name of a connected client.
+    pub fn client_name(&self, client_id: ClientId) -> Option<&str> {
+        self.connected_clients
+            .get(&client_id)
+            .map(|info| info.name.as_ref())
+    }
+
+    /// Returns the ID of the connected client with the given name.
+    pub fn client_id(&self, name: &str) -> Option<ClientId> {
+        self.connected_clients
+            .values()
+            .find(|info| &*info.name == name)
+            .map(|info| info.id)
+    }
+}
+
 /// Actor for messages.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub enum Entity {
@@ -140,18 +367,61 @@ pub struct Message {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Event {
-    /// Set the entire server info state, used on first connection.
+    /// Set the entire server info state, used on first connection and when changing rooms.
     SetServerInfo(ServerInfo),
     SetGame(GameInfo),
     EndGame,
+    /// A client connected to the server in, or moved into, the recipient's room.
     ClientConnected(ClientInfo),
+    /// A client disconnected from the server, or moved out of the recipient's room.
     ClientDisconnected(ClientId),
+    /// The given client is now the host of the recipient's room.
+    HostChanged(ClientId),
+    /// A client in the recipient's room opted in or out of playing in the next game.
+    LobbyStatusChanged {
+        client: ClientId,
+        status: LobbyStatus,
+    },
+    /// A client in the recipient's room became ready or unready for the next game.
+    ReadyChanged {
+        client: ClientId,
+        ready: bool,
+    },
+    /// Enough players are ready, the next game starts on its own `duration_secs` after the
+    /// countdown started unless players unready.
+    CountdownStarted {
+        start_time_unix_ts_secs: u64,
+        duration_secs: u64,
+    },
+    /// Too few players are ready anymore, the game will not start.
+    CountdownCancelled,
+    /// `replacement` took over `player`'s role, status and votes in the game, `player` is now a
+    /// spectator.
+    PlayerReplaced {
+        player: ClientId,
+        replacement: ClientId,
+    },
     MessageReceived(Message),
     VoteIssued {
         voter: ClientId,
         target: Option<ClientId>,
         channel: EventChannel,
     },
+    /// Day vote in a game played with trials, nominating `accused` for trial.
+    PlayerNominated {
+        voter: ClientId,
+        accused: Option<ClientId>,
+    },
+    /// `accused` was nominated by a majority of players and is put on trial.
+    TrialStarted {
+        accused: ClientId,
+    },
+    /// Result of a trial, along with how each player voted.
+    TrialVerdict {
+        accused: ClientId,
+        verdict: Verdict,
+        votes: HashMap<ClientId, Verdict>,
+    },
     // Events from a cycle end.
     FailedVote {
         cycle: Cycle,
@@ -167,6 +437,10 @@ pub enum Event {
         player: ClientId,
         cycle: Cycle,
         death_message: Box<str>,
+        /// Set if the game is configured to reveal roles on death.
+        reveal: Option<RoleReveal>,
+        #[serde(default)]
+        cause: DeathCause,
     },
     PlayerInvestigated {
         actor: ClientId,
@@ -175,10 +449,112 @@ pub enum Event {
     },
     GameWon {
         player_to_role: HashMap<ClientId, SpecialRole>,
-        side: Allegiance,
+        winners: Winners,
+    },
+    GameDrawn {
+        player_to_role: HashMap<ClientId, SpecialRole>,
+        reason: DrawReason,
     },
 }
 
+/// Request from a client to the server.
+///
+/// Over the network, requests are sent one per line as JSON. Every request is answered with a
+/// [`Response`], in order. A connection must start with [`Request::Connect`] or
+/// [`Request::Resume`], later requests act on behalf of the client that connected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Request {
+    Connect {
+        name: Box<str>,
+        /// Sequence number of the last event received before the connection dropped. If set,
+        /// every event after it is sent again.
+        #[serde(default)]
+        resume_from: Option<u64>,
+    },
+    /// Connect as the client holding `session_token`, keeping the token. Lets clients come back
+    /// after the server restarts.
+    Resume {
+        session_token: SessionToken,
+        /// Same as [`Request::Connect`]'s `resume_from`.
+        #[serde(default)]
+        resume_from: Option<u64>,
+    },
+    Disconnect,
+    SendMessage {
+        contents: Box<str>,
+    },
+    CastVote {
+        target: Option<ClientId>,
+    },
+    CastVerdict {
+        verdict: Verdict,
+    },
+    /// Start a game in the client's room, with the room's game settings. Only the room's host can
+    /// start games.
+    StartGame,
+    /// End the game in the client's room. Only the room's host can end games.
+    EndGame,
+    /// Make another client in the room the host. Only the room's host can hand it over.
+    TransferHost {
+        to: ClientId,
+    },
+    /// Opt in or out of playing in the next game. Clients cannot change this while playing.
+    SetLobbyStatus {
+        status: LobbyStatus,
+    },
+    /// Mark the client as ready or unready for the next game. Games start on their own once
+    /// enough players are ready, if the server allows it.
+    SetReady {
+        ready: bool,
+    },
+    /// Replace a player in the game with a spectator in the room, e.g. if the player left. Only
+    /// the room's host can substitute players.
+    SubstitutePlayer {
+        player: ClientId,
+        replacement: ClientId,
+    },
+    ListRooms,
+    /// Create a room with the server's game settings and move into it.
+    CreateRoom {
+        name: Box<str>,
+    },
+    JoinRoom {
+        name: Box<str>,
+    },
+    /// Go back to the lobby.
+    LeaveRoom,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Response {
+    Connected {
+        id: ClientId,
+        session_token: SessionToken,
+    },
+    Ok,
+    Rooms(Vec<RoomInfo>),
+    Error(String),
+}
+
+/// Event tagged with its position in the recipient's event stream.
+///
+/// Sequence numbers start at 1 and increase by 1 for every event sent to the client, so a jump
+/// means events were missed. They are kept across reconnects so a client can resume where it
+/// left off.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    pub event: Arc<Event>,
+}
+
+/// Line sent from the server to a client, either a response to the client's last request or an
+/// event pushed by the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Response(Response),
+    Event(SequencedEvent),
+}
+
 impl From<Message> for Event {
     fn from(value: Message) -> Self {
         Event::MessageReceived(value)
@@ -0,0 +1,234 @@
+//! Benchmarks for sending events to many clients and reading them back.
+//!
+//! Each benchmark runs against the server (`log`), and against a copy of the per-client inboxes
+//! the shared event log replaced (`inbox`). The inboxes are benchmarked without the rest of the
+//! server, e.g. authenticating the client, so they understate the cost of the old design.
+//!
+//! Run with `cargo bench -p mafia-game-server-lib`.
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use mafia_game_lib::SessionToken;
+use mafia_game_server_lib::MafiaGameServer;
+use mafia_game_server_lib::MafiaGameServerConfig;
+use mafia_game_server_lib::clock::MockClock;
+
+const NUM_CLIENTS: [usize; 3] = [10, 100, 1000];
+
+/// Per-client inboxes, as events were sent before the shared event log.
+mod inbox {
+    use std::collections::BTreeSet;
+    use std::collections::HashMap;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+    use std::sync::Condvar;
+    use std::sync::Mutex;
+    use std::sync::RwLock;
+
+    use mafia_game_lib::ClientId;
+    use mafia_game_lib::Entity;
+    use mafia_game_lib::Event;
+    use mafia_game_lib::EventChannel;
+    use mafia_game_lib::Message;
+    use mafia_game_lib::SequencedEvent;
+    use mafia_game_server_lib::event_log::MAX_RETAINED_EVENTS;
+
+    /// Events sent to a client, every event is copied into the inbox of each recipient.
+    struct Inbox {
+        state: Mutex<InboxState>,
+        new_events: Condvar,
+    }
+
+    struct InboxState {
+        /// The latest events sent, oldest first.
+        events: VecDeque<SequencedEvent>,
+        /// Sequence number of the next event sent.
+        next_seq: u64,
+        /// Sequence number of the first event not taken yet.
+        next_unread: u64,
+    }
+
+    impl Inbox {
+        fn push(&self, event: Arc<Event>) {
+            let mut state = self.state.lock().unwrap();
+
+            if state.events.len() >= MAX_RETAINED_EVENTS {
+                state.events.pop_front();
+            }
+
+            let event = SequencedEvent {
+                seq: state.next_seq,
+                event,
+            };
+            state.next_seq += 1;
+            state.events.push_back(event);
+
+            self.new_events.notify_all();
+        }
+
+        fn take_unread(&self) -> Box<[SequencedEvent]> {
+            let mut state = self.state.lock().unwrap();
+
+            let first_retained = state
+                .events
+                .front()
+                .map_or(state.next_seq, |event| event.seq);
+            let skip = state.next_unread.max(first_retained) - first_retained;
+            state.next_unread = state.next_seq;
+
+            state.events.iter().skip(skip as usize).cloned().collect()
+        }
+    }
+
+    /// A room of clients, each with their own inbox.
+    pub struct InboxServer {
+        members: RwLock<BTreeSet<ClientId>>,
+        inboxes: HashMap<ClientId, Inbox>,
+    }
+
+    impl InboxServer {
+        pub fn new(num_clients: usize) -> Self {
+            let members = (0..num_clients as u64)
+                .map(ClientId)
+                .collect::<BTreeSet<_>>();
+
+            InboxServer {
+                inboxes: members
+                    .iter()
+                    .map(|&client_id| {
+                        (
+                            client_id,
+                            Inbox {
+                                state: Mutex::new(InboxState {
+                                    events: VecDeque::new(),
+                                    next_seq: 1,
+                                    next_unread: 1,
+                                }),
+                                new_events: Condvar::new(),
+                            },
+                        )
+                    })
+                    .collect(),
+                members: RwLock::new(members),
+            }
+        }
+
+        pub fn client_ids(&self) -> Vec<ClientId> {
+            self.members.read().unwrap().iter().copied().collect()
+        }
+
+        /// Sends a public chat message to every member of the room.
+        pub fn send_message(&self, from: ClientId, contents: Box<str>) {
+            let members = self.members.read().unwrap().clone();
+            let event = Arc::new(Event::MessageReceived(Message {
+                channel: EventChannel::Public,
+                contents,
+                from: Entity::Client(from),
+            }));
+
+            for client_id in &members {
+                if let Some(inbox) = self.inboxes.get(client_id) {
+                    inbox.push(Arc::clone(&event));
+                }
+            }
+        }
+
+        pub fn take_events(&self, client_id: ClientId) -> Box<[SequencedEvent]> {
+            self.inboxes[&client_id].take_unread()
+        }
+    }
+}
+
+/// Returns a server with `num_clients` clients in the lobby, along with their tokens.
+fn server_with_clients(num_clients: usize) -> (MafiaGameServer, Vec<SessionToken>) {
+    let server = MafiaGameServer::new(
+        MafiaGameServerConfig::default(),
+        Arc::new(MockClock::default()),
+    );
+
+    let tokens = (0..num_clients)
+        .map(|i| server.connect_client(&format!("client-{}", i)).unwrap().1)
+        .collect::<Vec<_>>();
+
+    for &token in &tokens {
+        server.take_events(token).unwrap();
+    }
+
+    (server, tokens)
+}
+
+/// Cost of sending a public chat message, which every client in the lobby can see.
+fn bench_send_message(c: &mut Criterion) {
+    let mut group = c.benchmark_group("send_message");
+
+    for num_clients in NUM_CLIENTS {
+        let (server, tokens) = server_with_clients(num_clients);
+
+        group.bench_with_input(
+            BenchmarkId::new("log", num_clients),
+            &tokens[0],
+            |b, &token| b.iter(|| server.send_message(token, Box::from("hi")).unwrap()),
+        );
+
+        let server = inbox::InboxServer::new(num_clients);
+        let client_ids = server.client_ids();
+
+        group.bench_with_input(
+            BenchmarkId::new("inbox", num_clients),
+            &client_ids[0],
+            |b, &client_id| b.iter(|| server.send_message(client_id, Box::from("hi"))),
+        );
+    }
+
+    group.finish();
+}
+
+/// Cost of sending a public chat message, then every client taking it.
+fn bench_send_and_take(c: &mut Criterion) {
+    let mut group = c.benchmark_group("send_and_take");
+
+    for num_clients in NUM_CLIENTS {
+        let (server, tokens) = server_with_clients(num_clients);
+
+        group.bench_with_input(
+            BenchmarkId::new("log", num_clients),
+            &tokens,
+            |b, tokens| {
+                b.iter(|| {
+                    server.send_message(tokens[0], Box::from("hi")).unwrap();
+
+                    for &token in tokens {
+                        black_box(server.take_events(token).unwrap());
+                    }
+                })
+            },
+        );
+
+        let server = inbox::InboxServer::new(num_clients);
+        let client_ids = server.client_ids();
+
+        group.bench_with_input(
+            BenchmarkId::new("inbox", num_clients),
+            &client_ids,
+            |b, client_ids| {
+                b.iter(|| {
+                    server.send_message(client_ids[0], Box::from("hi"));
+
+                    for &client_id in client_ids {
+                        black_box(server.take_events(client_id));
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_send_message, bench_send_and_take);
+criterion_main!(benches);
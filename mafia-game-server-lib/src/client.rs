@@ -1,28 +1,33 @@
 //! Manages connection to the server. Clients may or may not be players, e.g. if a client connects
 //! while a game is ongoing they will not be a player in the game.
+use std::collections::BTreeSet;
 use std::collections::HashMap;
-use std::collections::VecDeque;
 use std::sync::Arc;
-use std::sync::Mutex;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
-use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
-use bit_set::BitSet;
 use mafia_game_lib::ClientId;
 use mafia_game_lib::ClientInfo;
 use mafia_game_lib::Event;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::SequencedEvent;
 use mafia_game_lib::SessionToken;
+use serde::Deserialize;
+use serde::Serialize;
 
+use crate::clock::Clock;
 use crate::error::MafiaGameError;
-
-pub const MAX_PLAYERS: usize = 64;
+use crate::event_log::CursorState;
+use crate::event_log::EventCursor;
+use crate::event_log::EventLog;
+use crate::event_log::LogEntries;
+use crate::event_log::Subscription;
 
 /// State for a connected client.
 pub(crate) struct Client {
-    inbox: Mutex<VecDeque<Arc<Event>>>,
+    cursor: Arc<EventCursor>,
     info: ClientInfo,
     session_token: SessionToken,
     /// Seconds since unix epoch.
@@ -36,41 +41,52 @@ impl Client {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct ClientSet(BitSet);
+/// Saved state of a [`Client`], see [`ClientState::snapshot`].
+#[derive(Serialize, Deserialize)]
+struct ClientSnapshot {
+    info: ClientInfo,
+    session_token: SessionToken,
+    events: CursorState,
+}
+
+/// Set of clients. Clones share the set until one of them is modified, so a room's members can
+/// be attached to events without copying them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClientSet(Arc<BTreeSet<ClientId>>);
 
 impl From<ClientId> for ClientSet {
     fn from(value: ClientId) -> Self {
-        let mut v = BitSet::with_capacity(MAX_PLAYERS);
-        v.insert(value.0);
-
-        ClientSet(v)
+        ClientSet(Arc::new(BTreeSet::from([value])))
     }
 }
 
 impl ClientSet {
     pub fn new() -> Self {
-        ClientSet(BitSet::with_capacity(MAX_PLAYERS))
+        ClientSet(Arc::new(BTreeSet::new()))
     }
 
     pub fn intersect_with(&mut self, other: &Self) {
-        self.0.intersect_with(&other.0)
+        Arc::make_mut(&mut self.0).retain(|client_id| other.0.contains(client_id))
     }
 
     pub fn union_with(&mut self, other: &Self) {
-        self.0.union_with(&other.0)
+        Arc::make_mut(&mut self.0).extend(other.0.iter())
     }
 
     pub fn difference_with(&mut self, other: &Self) {
-        self.0.difference_with(&other.0)
+        Arc::make_mut(&mut self.0).retain(|client_id| !other.0.contains(client_id))
     }
 
     pub fn insert(&mut self, client_id: ClientId) -> bool {
-        self.0.insert(client_id.0)
+        !self.0.contains(&client_id) && Arc::make_mut(&mut self.0).insert(client_id)
     }
 
     pub fn remove(&mut self, client_id: ClientId) -> bool {
-        self.0.remove(client_id.0)
+        self.0.contains(&client_id) && Arc::make_mut(&mut self.0).remove(&client_id)
+    }
+
+    pub fn contains(&self, client_id: ClientId) -> bool {
+        self.0.contains(&client_id)
     }
 
     pub fn count(&self) -> usize {
@@ -86,47 +102,115 @@ impl Default for ClientSet {
 
 impl FromIterator<ClientId> for ClientSet {
     fn from_iter<T: IntoIterator<Item = ClientId>>(iter: T) -> Self {
-        let mut v = BitSet::with_capacity(MAX_PLAYERS);
-
-        for client_id in iter {
-            v.insert(client_id.0);
-        }
-
-        ClientSet(v)
+        ClientSet(Arc::new(BTreeSet::from_iter(iter)))
     }
 }
 
 impl<'a> IntoIterator for &'a ClientSet {
     type Item = ClientId;
-    type IntoIter = std::iter::Map<bit_set::Iter<'a, u32>, fn(usize) -> ClientId>;
+    type IntoIter = std::iter::Copied<std::collections::btree_set::Iter<'a, ClientId>>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter().map(ClientId)
+        self.0.iter().copied()
     }
 }
 
+/// Saved state of a [`ClientState`].
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ClientStateSnapshot {
+    clients: Vec<ClientSnapshot>,
+    generations: Vec<u32>,
+    events: LogEntries,
+}
+
 pub(crate) struct ClientState {
     /// Holds state for connected clients.
     clients: HashMap<ClientId, Client>,
     /// Holds mapping of client names to IDs, can hold stale client names.
     client_name_to_id: HashMap<Arc<str>, ClientId>,
     session_token_to_id: HashMap<SessionToken, ClientId>,
-    claimed_ids: BitSet,
-}
-
-impl Default for ClientState {
-    fn default() -> Self {
-        Self::new()
-    }
+    /// Generation of the latest ID handed out for each slot, see [`ClientId`].
+    generations: Vec<u32>,
+    /// Slots released by purged clients, handed out again before new slots.
+    free_slots: Vec<u32>,
+    /// Events sent to clients, kept so clients can resume after reconnecting.
+    events: Arc<EventLog>,
+    clock: Arc<dyn Clock>,
 }
 
 impl ClientState {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> Self {
         Self {
             clients: HashMap::new(),
             client_name_to_id: HashMap::new(),
             session_token_to_id: HashMap::new(),
-            claimed_ids: BitSet::with_capacity(MAX_PLAYERS),
+            generations: Vec::new(),
+            free_slots: Vec::new(),
+            events: Arc::new(EventLog::new()),
+            clock,
+        }
+    }
+
+    /// Restores clients saved with [`ClientState::snapshot`]. Restored clients are disconnected
+    /// until they resume their session with [`ClientState::resume_client`] or reconnect.
+    pub(crate) fn restore(snapshot: ClientStateSnapshot, clock: Arc<dyn Clock>) -> Self {
+        let mut state = ClientState::new(clock);
+        let now = state.clock.now_unix_ts_secs();
+        state.events = Arc::new(EventLog::restore(snapshot.events));
+
+        for snapshot in snapshot.clients {
+            let id = snapshot.info.id;
+
+            state
+                .client_name_to_id
+                .insert(Arc::clone(&snapshot.info.name), id);
+            state.session_token_to_id.insert(snapshot.session_token, id);
+            state.clients.insert(
+                id,
+                Client {
+                    cursor: EventCursor::restore(&state.events, id, snapshot.events),
+                    info: snapshot.info,
+                    session_token: snapshot.session_token,
+                    // Restored clients get the full resume time to come back.
+                    last_active: AtomicU64::new(now),
+                    disconnected: true,
+                },
+            );
+        }
+
+        // Generations are kept so IDs from before the snapshot stay stale.
+        state.generations = snapshot.generations;
+        // Reversed so the lowest free slot is handed out first.
+        state.free_slots = (0..state.generations.len() as u32)
+            .rev()
+            .filter(|&slot| {
+                !state
+                    .clients
+                    .contains_key(&ClientId::new(slot, state.generations[slot as usize]))
+            })
+            .collect();
+
+        state
+    }
+
+    pub(crate) fn snapshot(&self) -> ClientStateSnapshot {
+        // Saved before the cursors, which can only move forward, so the log has every entry they
+        // have not read. Cursors can read past the end of the saved log meanwhile, see
+        // `EventCursor::restore`.
+        let events = self.events.snapshot();
+
+        ClientStateSnapshot {
+            clients: self
+                .clients
+                .values()
+                .map(|client| ClientSnapshot {
+                    info: client.info.clone(),
+                    session_token: client.session_token,
+                    events: client.cursor.snapshot(),
+                })
+                .collect(),
+            generations: self.generations.clone(),
+            events,
         }
     }
 
@@ -149,46 +233,46 @@ impl ClientState {
             } else {
                 let session_token = SessionToken::new();
 
+                self.session_token_to_id.remove(&client.session_token);
+                self.session_token_to_id
+                    .insert(session_token, existing_client_id);
                 client.session_token = session_token;
-                client.last_active.store(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .expect("now is after epoch")
-                        .as_secs(),
-                    Ordering::Relaxed,
-                );
+                client
+                    .last_active
+                    .store(self.clock.now_unix_ts_secs(), Ordering::Relaxed);
                 client.disconnected = false;
+                client.cursor.set_closed(false);
 
                 return Ok((existing_client_id, session_token));
             }
         }
 
-        let mut new_id: Option<usize> = None;
-        for i in 0..MAX_PLAYERS {
-            if !self.claimed_ids.contains(i) {
-                new_id = Some(i);
-                break;
+        let id = match self.free_slots.pop() {
+            Some(slot) => {
+                let generation = &mut self.generations[slot as usize];
+                *generation = generation.wrapping_add(1);
+
+                ClientId::new(slot, *generation)
             }
-        }
+            None => {
+                self.generations.push(0);
 
-        let id = ClientId(new_id.ok_or(MafiaGameError::TooManyClientsRegistered)?);
-        self.claimed_ids.insert(id.0);
+                ClientId::new(self.generations.len() as u32 - 1, 0)
+            }
+        };
 
         let session_token = SessionToken::new();
 
         let client = Client {
-            inbox: Mutex::new(VecDeque::with_capacity(100)),
+            cursor: EventCursor::new(&self.events, id),
             info: ClientInfo {
                 name: Arc::clone(&client_name),
                 id,
+                lobby_status: LobbyStatus::default(),
+                ready: false,
             },
             session_token,
-            last_active: AtomicU64::new(
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .expect("now is after epoch")
-                    .as_secs(),
-            ),
+            last_active: AtomicU64::new(self.clock.now_unix_ts_secs()),
             disconnected: false,
         };
 
@@ -199,6 +283,34 @@ impl ClientState {
         Ok((id, session_token))
     }
 
+    /// Reconnects the client holding `session_token`, keeping the token. Returns the client and
+    /// whether it was disconnected.
+    pub(crate) fn resume_client(
+        &mut self,
+        session_token: SessionToken,
+    ) -> Result<(ClientId, bool), MafiaGameError> {
+        let client_id = self
+            .session_token_to_id
+            .get(&session_token)
+            .copied()
+            .ok_or(MafiaGameError::InvalidSessionToken(session_token))?;
+
+        let client = self.clients.get_mut(&client_id).expect("valid client");
+
+        client
+            .last_active
+            .store(self.clock.now_unix_ts_secs(), Ordering::Relaxed);
+
+        if !client.disconnected {
+            return Ok((client_id, false));
+        }
+
+        client.disconnected = false;
+        client.cursor.set_closed(false);
+
+        Ok((client_id, true))
+    }
+
     /// Disconnects the client from the game.
     pub(crate) fn disconnect_client(&mut self, client_id: ClientId) -> Result<(), MafiaGameError> {
         let Some(client) = self.clients.get_mut(&client_id) else {
@@ -209,35 +321,50 @@ impl ClientState {
             return Err(MafiaGameError::ClientDisconnected(client_id));
         }
 
+        // Events are still collected while disconnected, so the client can resume if it
+        // reconnects before being purged.
         client.disconnected = true;
-        client.inbox = Mutex::new(VecDeque::with_capacity(100));
+        client.cursor.set_closed(true);
+        client
+            .last_active
+            .store(self.clock.now_unix_ts_secs(), Ordering::Relaxed);
 
         Ok(())
     }
 
-    /// Purges disconnect clients from the client name map.
+    /// Purges clients inactive for `max_inactive_time`, and clients disconnected for
+    /// `max_resume_time`, from the client name map. Clients in `keep` are never purged.
     ///
     /// Returns a list of clients newly disconnected.
     pub(crate) fn purge_disconnected_clients(
         &mut self,
         max_inactive_time: Duration,
+        max_resume_time: Duration,
+        keep: &ClientSet,
     ) -> Vec<ClientId> {
-        let now = SystemTime::now();
+        let now = self.clock.now();
 
         let mut ret = Vec::new();
 
         for client_id in self
             .clients
             .values()
+            .filter(|client| !keep.contains(client.info.id))
             .filter_map(|client| {
-                if client.disconnected
-                    || now
-                        .duration_since(
-                            UNIX_EPOCH
-                                + Duration::from_secs(client.last_active.load(Ordering::Relaxed)),
-                        )
-                        .unwrap_or(Duration::from_secs(0))
-                        >= max_inactive_time
+                // For disconnected clients, this is the time since they disconnected.
+                let inactive_time = now
+                    .duration_since(
+                        UNIX_EPOCH
+                            + Duration::from_secs(client.last_active.load(Ordering::Relaxed)),
+                    )
+                    .unwrap_or(Duration::from_secs(0));
+
+                if inactive_time
+                    >= if client.disconnected {
+                        max_resume_time
+                    } else {
+                        max_inactive_time
+                    }
                 {
                     Some(client.info.id)
                 } else {
@@ -250,7 +377,7 @@ impl ClientState {
 
             self.client_name_to_id.remove(&client.info.name);
             self.session_token_to_id.remove(&client.session_token);
-            self.claimed_ids.remove(client_id.0);
+            self.free_slots.push(client_id.slot());
 
             if !client.disconnected {
                 ret.push(client_id);
@@ -278,13 +405,9 @@ impl ClientState {
             return Err(MafiaGameError::ClientDisconnected(client_id));
         }
 
-        client.last_active.store(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("now is after epoch")
-                .as_secs(),
-            Ordering::Relaxed,
-        );
+        client
+            .last_active
+            .store(self.clock.now_unix_ts_secs(), Ordering::Relaxed);
 
         Ok(client_id)
     }
@@ -295,50 +418,126 @@ impl ClientState {
             .ok_or(MafiaGameError::InvalidClientId(client_id))
     }
 
+    #[cfg(test)]
     pub(crate) fn list_clients(&self) -> &HashMap<Arc<str>, ClientId> {
         &self.client_name_to_id
     }
 
     pub(crate) fn all_client_ids(&self) -> ClientSet {
-        ClientSet(self.claimed_ids.clone())
+        self.clients.keys().copied().collect()
     }
 
-    pub(crate) fn all_client_info(&self) -> HashMap<ClientId, ClientInfo> {
-        self.clients
-            .iter()
-            .map(|(&k, v)| (k, v.info.clone()))
+    /// Returns the clients in `clients` for which `f` returns `true`.
+    fn filter_clients(&self, clients: &ClientSet, f: impl Fn(&Client) -> bool) -> ClientSet {
+        clients
+            .into_iter()
+            .filter(|client_id| self.clients.get(client_id).is_some_and(&f))
             .collect()
     }
 
-    /// Send a [`Event`] to the specified client's inboxes, if they exist.
-    pub(crate) fn send_event<E: Into<Event>>(&self, to: ClientSet, event: E) {
-        let event = Arc::new(event.into());
+    /// Returns the clients in `clients` that opted to play in the next game.
+    pub(crate) fn players(&self, clients: &ClientSet) -> ClientSet {
+        self.filter_clients(clients, |client| {
+            client.info.lobby_status == LobbyStatus::Player
+        })
+    }
 
-        if to.0.len() < self.clients.len() {
-            for id in &to.0 {
-                let client_id = ClientId(id);
+    /// Returns the connected clients in `clients` that opted to play in the next game and are
+    /// ready for it.
+    pub(crate) fn ready_players(&self, clients: &ClientSet) -> ClientSet {
+        self.filter_clients(clients, |client| {
+            client.info.lobby_status == LobbyStatus::Player
+                && client.info.ready
+                && !client.disconnected
+        })
+    }
 
-                if let Some(client) = self.clients.get(&client_id) {
-                    if !client.disconnected {
-                        client.inbox.lock().unwrap().push_back(Arc::clone(&event));
-                    }
-                }
-            }
-        } else {
-            for (&client_id, client) in &self.clients {
-                if !client.disconnected && to.0.contains(client_id.0) {
-                    client.inbox.lock().unwrap().push_back(Arc::clone(&event));
-                }
-            }
-        }
+    pub(crate) fn set_lobby_status(
+        &mut self,
+        client_id: ClientId,
+        status: LobbyStatus,
+    ) -> Result<(), MafiaGameError> {
+        self.clients
+            .get_mut(&client_id)
+            .ok_or(MafiaGameError::InvalidClientId(client_id))?
+            .info
+            .lobby_status = status;
+
+        Ok(())
+    }
+
+    /// Sets whether the client is ready, returning `true` if it changed.
+    pub(crate) fn set_ready(
+        &mut self,
+        client_id: ClientId,
+        ready: bool,
+    ) -> Result<bool, MafiaGameError> {
+        let info = &mut self
+            .clients
+            .get_mut(&client_id)
+            .ok_or(MafiaGameError::InvalidClientId(client_id))?
+            .info;
+
+        Ok(std::mem::replace(&mut info.ready, ready) != ready)
+    }
+
+    /// Returns info for each of the clients in `clients`.
+    pub(crate) fn client_info(&self, clients: &ClientSet) -> HashMap<ClientId, ClientInfo> {
+        clients
+            .into_iter()
+            .filter_map(|client_id| Some((client_id, self.clients.get(&client_id)?.info.clone())))
+            .collect()
     }
 
-    /// Drains a given client's event inbox.
-    pub(crate) fn take_events(&self, for_client: ClientId) -> Box<[Arc<Event>]> {
+    /// Send a [`Event`] to the specified clients. Disconnected clients still get the event, in
+    /// case they resume.
+    pub(crate) fn send_event<E: Into<Event>>(&self, to: ClientSet, event: E) {
+        self.events.push(to, Arc::new(event.into()));
+    }
+
+    /// Takes the events a given client has not taken yet. Returns an `Err` if some of these
+    /// events are no longer retained.
+    pub(crate) fn take_events(
+        &self,
+        for_client: ClientId,
+    ) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
         if let Some(client) = self.clients.get(&for_client) {
-            client.inbox.lock().unwrap().drain(..).collect()
+            client.cursor.take_unread()
         } else {
-            Box::new([])
+            Ok(Box::new([]))
         }
     }
+
+    /// Takes every event a given client was sent after the event numbered `seq`. Returns an
+    /// `Err` if some of these events are no longer retained.
+    pub(crate) fn take_events_since(
+        &self,
+        for_client: ClientId,
+        seq: u64,
+    ) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
+        self.get_client(for_client)?.cursor.take_since(seq)
+    }
+
+    /// Skips the events a given client has not taken yet, e.g. once some of them are no longer
+    /// retained.
+    pub(crate) fn skip_events(&self, for_client: ClientId) -> Result<(), MafiaGameError> {
+        self.get_client(for_client)?.cursor.skip_unread();
+
+        Ok(())
+    }
+
+    /// Subscribes to a given client's events, see [`Subscription`].
+    pub(crate) fn subscribe(&self, for_client: ClientId) -> Result<Subscription, MafiaGameError> {
+        Ok(Subscription::new(Arc::clone(
+            &self.get_client(for_client)?.cursor,
+        )))
+    }
+
+    /// Returns a given client's cursor, to wait on events without borrowing the client state.
+    pub(crate) fn get_cursor(
+        &self,
+        for_client: ClientId,
+    ) -> Result<Arc<EventCursor>, MafiaGameError> {
+        Ok(Arc::clone(&self.get_client(for_client)?.cursor))
+    }
 }
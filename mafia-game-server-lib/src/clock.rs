@@ -0,0 +1,75 @@
+//! Source of the current time for the server and games.
+//!
+//! All timers (cycle timeouts, vote grace periods, client inactivity) read the time through a
+//! [`Clock`], so they can be driven by a [`MockClock`] in tests instead of sleeping.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Source of the current time.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// Seconds since unix epoch.
+    fn now_unix_ts_secs(&self) -> u64 {
+        unix_ts_secs(self.now())
+    }
+}
+
+/// Converts `time` to seconds since unix epoch.
+pub(crate) fn unix_ts_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .expect("time is after epoch")
+        .as_secs()
+}
+
+/// Returns a [`SystemClock`], for state restored without a clock.
+pub(crate) fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Reads the time from the system.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to.
+#[derive(Debug)]
+pub struct MockClock(Mutex<SystemTime>);
+
+impl MockClock {
+    pub fn new(start: SystemTime) -> Self {
+        MockClock(Mutex::new(start))
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.0.lock().unwrap() += by;
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.0.lock().unwrap() = now;
+    }
+}
+
+impl Default for MockClock {
+    /// Starts the clock at unix epoch.
+    fn default() -> Self {
+        MockClock::new(UNIX_EPOCH)
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().unwrap()
+    }
+}
@@ -0,0 +1,171 @@
+//! Loading configs from TOML or JSON files, and named game presets.
+//!
+//! Durations are written for humans, e.g. `"90s"` or `"2m 30s"`. Wherever a server config
+//! expects a [`GameConfig`], the name of a preset can be given instead:
+//!
+//! ```toml
+//! max_client_inactive_time = "5m"
+//! min_ready_players = 7
+//! lobby_game_config = "classic-7"
+//! ```
+//!
+//! Game configs must set `start_cycle`, `time_for_day`, `end_day_after_all_votes`,
+//! `time_for_night`, `end_night_after_all_votes`, `num_special_roles` and `max_days`. The other
+//! fields default to no vote grace period, revealing nothing on death, no trials, no player cap
+//! and no AFK handling. Unknown fields are rejected, in game and server configs alike.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use mafia_game_lib::Cycle;
+use mafia_game_lib::SpecialRole;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::de;
+use serde::de::DeserializeOwned;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+use serde::de::value::MapAccessDeserializer;
+
+use crate::MafiaGameError;
+use crate::MafiaGameServerConfig;
+use crate::game::GameConfig;
+use crate::game::RevealPolicy;
+use crate::game::TrialConfig;
+use crate::role::RoleRegistry;
+
+/// Names of every preset, see [`preset`].
+pub const PRESETS: &[&str] = &["quick-5", "classic-7", "mafia-heavy-12"];
+
+/// Preset used when a server config does not set a lobby game.
+pub const DEFAULT_PRESET: &str = "quick-5";
+
+/// Returns the game config for a preset, named after the number of players it is meant for.
+pub fn preset(name: &str) -> Option<GameConfig> {
+    let base = |num_special_roles: &[(SpecialRole, usize)]| GameConfig {
+        start_cycle: Cycle::Day,
+        time_for_day: Duration::from_secs(120),
+        end_day_after_all_votes: true,
+        time_for_night: Duration::from_secs(60),
+        end_night_after_all_votes: true,
+        num_special_roles: HashMap::from_iter(num_special_roles.iter().cloned()),
+        vote_grace_period: Duration::from_secs(2),
+        roles: RoleRegistry::default(),
+        reveal_on_death: RevealPolicy::Nothing,
+        trial: None,
+        max_days: 100,
+        max_players: None,
+        afk: None,
+    };
+
+    match name {
+        "quick-5" => Some(base(&[
+            (SpecialRole::Mafia, 1),
+            (SpecialRole::Doctor, 1),
+            (SpecialRole::Detective, 1),
+        ])),
+        "classic-7" => Some(GameConfig {
+            time_for_day: Duration::from_secs(180),
+            reveal_on_death: RevealPolicy::Role,
+            trial: Some(TrialConfig {
+                time_for_defense: Duration::from_secs(30),
+                time_for_verdict: Duration::from_secs(20),
+                max_trials_per_day: 2,
+            }),
+            ..base(&[
+                (SpecialRole::Mafia, 2),
+                (SpecialRole::Doctor, 1),
+                (SpecialRole::Detective, 1),
+            ])
+        }),
+        "mafia-heavy-12" => Some(GameConfig {
+            time_for_day: Duration::from_secs(240),
+            time_for_night: Duration::from_secs(90),
+            reveal_on_death: RevealPolicy::Allegiance,
+            ..base(&[
+                (SpecialRole::Mafia, 4),
+                (SpecialRole::Doctor, 1),
+                (SpecialRole::Detective, 1),
+            ])
+        }),
+        _ => None,
+    }
+}
+
+/// Reads a config from `path`, parsed as JSON if it ends in `.json` and TOML otherwise.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, MafiaGameError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| MafiaGameError::Config(format!("failed to read {:?}: {}", path, e)))?;
+
+    let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&contents).map_err(|e| e.to_string())
+    };
+
+    parsed.map_err(|e| MafiaGameError::Config(format!("failed to parse {:?}: {}", path, e)))
+}
+
+/// Reads and validates a game config, see [`load`].
+pub fn load_game_config(path: &Path) -> Result<GameConfig, MafiaGameError> {
+    let config: GameConfig = load(path)?;
+    config.validate(None).map_err(invalid_config)?;
+
+    Ok(config)
+}
+
+/// Reads and validates a server config, see [`load`].
+///
+/// If games start on their own, the lobby game is checked against `min_ready_players`.
+pub fn load_server_config(path: &Path) -> Result<MafiaGameServerConfig, MafiaGameError> {
+    let config: MafiaGameServerConfig = load(path)?;
+    config
+        .lobby_game_config
+        .validate(config.min_ready_players)
+        .map_err(invalid_config)?;
+
+    Ok(config)
+}
+
+/// Combines every problem found in a config into one error.
+fn invalid_config(problems: Vec<MafiaGameError>) -> MafiaGameError {
+    MafiaGameError::Config(
+        problems
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+    )
+}
+
+/// Deserializes a [`GameConfig`] given inline or as the name of a preset.
+pub(crate) fn deserialize_game_config<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<GameConfig, D::Error> {
+    struct GameConfigVisitor;
+
+    impl<'de> Visitor<'de> for GameConfigVisitor {
+        type Value = GameConfig;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a game config or one of the presets {:?}", PRESETS)
+        }
+
+        fn visit_str<E: de::Error>(self, name: &str) -> Result<GameConfig, E> {
+            preset(name).ok_or_else(|| {
+                E::custom(format!(
+                    "unknown preset '{}', expected one of {:?}",
+                    name, PRESETS
+                ))
+            })
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<GameConfig, A::Error> {
+            GameConfig::deserialize(MapAccessDeserializer::new(map))
+        }
+    }
+
+    deserializer.deserialize_any(GameConfigVisitor)
+}
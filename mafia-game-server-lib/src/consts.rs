@@ -17,3 +17,6 @@ pub const NIGHT_DEATH_MESSAGES: &[&str] = &[
 /// <PLAYER> <DEATH_MESSAGE> that day.
 #[allow(dead_code)]
 pub const DAY_DEATH_MESSAGES: &[&str] = &["was hung for their unforgivable sins"];
+
+/// Death message for players removed from the game for not voting.
+pub const MODKILL_DEATH_MESSAGE: &str = "was removed from the game for being inactive";
@@ -11,8 +11,8 @@ pub enum MafiaGameError {
     InvalidSessionToken(SessionToken),
     #[error("{0:?} is not registered")]
     InvalidClientId(ClientId),
-    #[error("too many clients are registered")]
-    TooManyClientsRegistered,
+    #[error("too many players: {0}")]
+    TooManyPlayers(String),
     #[error("not enough clients: {0}")]
     NotEnoughPlayers(String),
     #[error("invalid game config: {0}")]
@@ -23,6 +23,26 @@ pub enum MafiaGameError {
     GameInProgress,
     #[error("no game is in progress")]
     NoGameInProgress,
+    #[error("no room named '{0}'")]
+    NoSuchRoom(String),
+    #[error("a room named '{0}' already exists")]
+    RoomExists(String),
+    #[error("invalid room name: {0}")]
+    InvalidRoomName(String),
+    #[error("only the room's host or an admin can do this")]
+    PermissionDenied,
+    #[error("cannot leave a room while playing in its game")]
+    PlayerInGame,
+    #[error("invalid substitution: {0}")]
+    InvalidSubstitution(String),
+    #[error("events after sequence number {0} are not available")]
+    EventsUnavailable(u64),
+    #[error("invalid game log: {0}")]
+    InvalidGameLog(String),
+    #[error("invalid config: {0}")]
+    Config(String),
+    #[error("failed to save or restore server state: {0}")]
+    Snapshot(String),
     #[error("client was disconnected, must reconnect first")]
     ClientDisconnected(ClientId),
 }
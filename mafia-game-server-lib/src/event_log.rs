@@ -0,0 +1,537 @@
+//! Log of every event sent to clients, shared by all of them.
+//!
+//! Each event is appended once along with the clients it was sent to, so sending an event costs
+//! the same no matter how many clients see it. Clients read the log through an [`EventCursor`]
+//! holding their position in it, skipping events that were not sent to them.
+//!
+//! Events are numbered by the cursor as the client takes them, so the numbers a client sees
+//! increase by 1 for every event sent to it. Entries stay in the log until every cursor has read
+//! past them, up to [`MAX_LOG_ENTRIES`]. A client that falls further behind loses its unread
+//! events and gets [`MafiaGameError::EventsUnavailable`], then has to catch up from the current
+//! state of its room. Cursors keep the latest events they took so their client can resume from
+//! them.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::sync::Weak;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::TryRecvError;
+use std::time::Duration;
+use std::time::Instant;
+
+use mafia_game_lib::ClientId;
+use mafia_game_lib::Event;
+use mafia_game_lib::SequencedEvent;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::client::ClientSet;
+use crate::error::MafiaGameError;
+
+/// Number of events kept for each client once taken, older events cannot be resumed from.
+pub const MAX_RETAINED_EVENTS: usize = 1024;
+
+/// Number of entries kept in the log, whether or not every cursor read them.
+pub const MAX_LOG_ENTRIES: usize = 16 * MAX_RETAINED_EVENTS;
+
+/// Number of events appended between two attempts at dropping entries every cursor read.
+const PRUNE_INTERVAL: u64 = 256;
+
+/// An event along with the clients it was sent to.
+#[derive(Clone, Serialize, Deserialize)]
+struct LogEntry {
+    to: ClientSet,
+    event: Arc<Event>,
+}
+
+/// The entries not read by every cursor yet, saved as is in snapshots.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct LogEntries {
+    /// Oldest first.
+    entries: VecDeque<LogEntry>,
+    /// Position in the log of the first entry in `entries`.
+    first_pos: u64,
+}
+
+impl LogEntries {
+    fn next_pos(&self) -> u64 {
+        self.first_pos + self.entries.len() as u64
+    }
+
+    /// Returns the events at position `pos` or later sent to `client_id`, along with their
+    /// position. Returns `None` if some of these entries were dropped.
+    fn events_from(
+        &self,
+        client_id: ClientId,
+        pos: u64,
+    ) -> Option<impl Iterator<Item = (u64, &Arc<Event>)>> {
+        if pos < self.first_pos {
+            return None;
+        }
+
+        let start = pos.min(self.next_pos());
+
+        Some(
+            self.entries
+                .range((start - self.first_pos) as usize..)
+                .zip(start..)
+                .filter(move |(entry, _)| entry.to.contains(client_id))
+                .map(|(entry, pos)| (pos, &entry.event)),
+        )
+    }
+}
+
+/// Wakes the threads waiting on a cursor for new events.
+struct Wakeup {
+    client_id: ClientId,
+    /// Position after the latest entry sent to the client while a thread was waiting.
+    next_pos: Mutex<u64>,
+    new_events: Condvar,
+}
+
+impl Wakeup {
+    fn wake(&self, next_pos: u64) {
+        let mut wake_pos = self.next_pos.lock().unwrap();
+        *wake_pos = (*wake_pos).max(next_pos);
+
+        self.new_events.notify_all();
+    }
+}
+
+/// Every event sent to clients, see the [module docs](self).
+pub(crate) struct EventLog {
+    entries: RwLock<LogEntries>,
+    /// Cursors reading the log, entries they have not read yet are never dropped.
+    cursors: Mutex<Vec<Weak<EventCursor>>>,
+    /// Cursors with threads waiting on them, only those the event is sent to are woken.
+    waiting: Mutex<Vec<Arc<Wakeup>>>,
+}
+
+impl EventLog {
+    pub(crate) fn new() -> Self {
+        Self::restore(LogEntries {
+            entries: VecDeque::new(),
+            first_pos: 1,
+        })
+    }
+
+    /// Restores a log saved with [`EventLog::snapshot`].
+    pub(crate) fn restore(entries: LogEntries) -> Self {
+        EventLog {
+            entries: RwLock::new(entries),
+            cursors: Mutex::new(Vec::new()),
+            waiting: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> LogEntries {
+        self.entries.read().unwrap().clone()
+    }
+
+    /// Appends `event`, sent to the clients in `to`, waking threads waiting on their events.
+    pub(crate) fn push(&self, to: ClientSet, event: Arc<Event>) {
+        let next_pos = {
+            let mut entries = self.entries.write().unwrap();
+
+            entries.entries.push_back(LogEntry {
+                to: to.clone(),
+                event,
+            });
+
+            if entries.entries.len() > MAX_LOG_ENTRIES {
+                entries.entries.pop_front();
+                entries.first_pos += 1;
+            }
+
+            if entries.next_pos().is_multiple_of(PRUNE_INTERVAL) {
+                self.prune(&mut entries);
+            }
+
+            entries.next_pos()
+        };
+
+        for wakeup in self
+            .waiting
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|wakeup| to.contains(wakeup.client_id))
+        {
+            wakeup.wake(next_pos);
+        }
+    }
+
+    /// Drops the entries every cursor has read.
+    fn prune(&self, entries: &mut LogEntries) {
+        let mut cursors = self.cursors.lock().unwrap();
+
+        cursors.retain(|cursor| cursor.strong_count() > 0);
+
+        // Cursors only move while holding the entries lock, so they cannot move past this.
+        let first_unread = cursors
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|cursor| cursor.first_unread.load(Ordering::Relaxed))
+            .fold(entries.next_pos(), u64::min);
+
+        while entries.first_pos < first_unread {
+            entries.entries.pop_front();
+            entries.first_pos += 1;
+        }
+    }
+}
+
+/// Saved state of an [`EventCursor`].
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct CursorState {
+    /// Position in the log of the first entry not read yet.
+    next_unread: u64,
+    /// Sequence number of the next event taken.
+    next_seq: u64,
+    /// The latest events taken, oldest first.
+    taken: VecDeque<SequencedEvent>,
+}
+
+impl CursorState {
+    /// Numbers an event taken by the client, keeping it so the client can resume from it.
+    fn take(&mut self, event: &Arc<Event>) -> SequencedEvent {
+        let event = SequencedEvent {
+            seq: self.next_seq,
+            event: Arc::clone(event),
+        };
+        self.next_seq += 1;
+
+        if self.taken.len() >= MAX_RETAINED_EVENTS {
+            self.taken.pop_front();
+        }
+
+        self.taken.push_back(event.clone());
+
+        event
+    }
+
+    /// Sequence number of the first event that can be resumed from.
+    fn first_taken(&self) -> u64 {
+        self.next_seq - self.taken.len() as u64
+    }
+}
+
+/// A client's position in the [`EventLog`], shared with threads waiting on events without holding
+/// the server lock.
+pub(crate) struct EventCursor {
+    log: Arc<EventLog>,
+    client_id: ClientId,
+    state: Mutex<CursorState>,
+    /// Copy of [`CursorState::next_unread`], read by the log without locking the cursor.
+    first_unread: AtomicU64,
+    /// Set while the client is disconnected.
+    closed: AtomicBool,
+    /// Number of times the client disconnected, ends subscriptions made before the last one.
+    disconnects: AtomicU64,
+    wakeup: Arc<Wakeup>,
+}
+
+impl EventCursor {
+    /// Returns a cursor for `client_id` starting after the last event in `log`.
+    pub(crate) fn new(log: &Arc<EventLog>, client_id: ClientId) -> Arc<Self> {
+        // Hold the entries lock so no entry is appended before the cursor is registered.
+        let entries = log.entries.read().unwrap();
+
+        Self::register(
+            log,
+            client_id,
+            CursorState {
+                next_unread: entries.next_pos(),
+                next_seq: 1,
+                taken: VecDeque::new(),
+            },
+        )
+    }
+
+    /// Restores a cursor saved with [`EventCursor::snapshot`], reading the restored `log`.
+    pub(crate) fn restore(
+        log: &Arc<EventLog>,
+        client_id: ClientId,
+        mut state: CursorState,
+    ) -> Arc<Self> {
+        // Events can be sent and taken while a snapshot is saved, so the cursor may have read
+        // entries appended after the log was saved. Those are not in the restored log, and new
+        // entries take their positions.
+        state.next_unread = state
+            .next_unread
+            .min(log.entries.read().unwrap().next_pos());
+
+        Self::register(log, client_id, state)
+    }
+
+    fn register(log: &Arc<EventLog>, client_id: ClientId, state: CursorState) -> Arc<Self> {
+        let cursor = Arc::new(EventCursor {
+            log: Arc::clone(log),
+            client_id,
+            first_unread: AtomicU64::new(state.next_unread),
+            state: Mutex::new(state),
+            closed: AtomicBool::new(false),
+            disconnects: AtomicU64::new(0),
+            wakeup: Arc::new(Wakeup {
+                client_id,
+                next_pos: Mutex::new(0),
+                new_events: Condvar::new(),
+            }),
+        });
+
+        log.cursors.lock().unwrap().push(Arc::downgrade(&cursor));
+
+        cursor
+    }
+
+    pub(crate) fn snapshot(&self) -> CursorState {
+        self.state.lock().unwrap().clone()
+    }
+
+    fn next_unread(&self) -> u64 {
+        self.state.lock().unwrap().next_unread
+    }
+
+    /// Moves the cursor to the entry at `pos`, must be called with the entries lock held.
+    fn set_next_unread(&self, state: &mut CursorState, pos: u64) {
+        state.next_unread = pos;
+        self.first_unread.store(pos, Ordering::Relaxed);
+    }
+
+    /// Marks the client as disconnected or not, waking waiting threads. Disconnecting ends all
+    /// subscriptions.
+    pub(crate) fn set_closed(&self, closed: bool) {
+        // Hold the lock waiters check under, so they cannot miss the wake up.
+        let _next_pos = self.wakeup.next_pos.lock().unwrap();
+
+        self.closed.store(closed, Ordering::Relaxed);
+
+        if closed {
+            self.disconnects.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.wakeup.new_events.notify_all();
+    }
+
+    /// Returns `true` if some entries the cursor has not read yet were dropped.
+    fn is_behind(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        let entries = self.log.entries.read().unwrap();
+
+        state.next_unread < entries.first_pos
+    }
+
+    /// Skips every entry not read yet, so the cursor can be read again once its unread entries
+    /// were dropped.
+    pub(crate) fn skip_unread(&self) {
+        let mut state = self.state.lock().unwrap();
+        let entries = self.log.entries.read().unwrap();
+
+        self.set_next_unread(&mut state, entries.next_pos());
+    }
+
+    /// Returns events after `seq`, marking them as taken.
+    pub(crate) fn take_since(&self, seq: u64) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
+        let mut state = self.state.lock().unwrap();
+        let entries = self.log.entries.read().unwrap();
+
+        if seq >= state.next_seq || seq + 1 < state.first_taken() {
+            return Err(MafiaGameError::EventsUnavailable(seq));
+        }
+
+        let unread = entries
+            .events_from(self.client_id, state.next_unread)
+            .ok_or(MafiaGameError::EventsUnavailable(seq))?;
+
+        let mut events = state
+            .taken
+            .range((seq + 1 - state.first_taken()) as usize..)
+            .cloned()
+            .collect::<Vec<_>>();
+        events.extend(unread.map(|(_, event)| state.take(event)));
+
+        self.set_next_unread(&mut state, entries.next_pos());
+
+        Ok(events.into_boxed_slice())
+    }
+
+    /// Returns events not taken yet, marking them as taken. Returns an `Err` if some of them were
+    /// dropped, the client has to resume with [`EventCursor::take_since`] then.
+    pub(crate) fn take_unread(&self) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
+        let mut state = self.state.lock().unwrap();
+        let entries = self.log.entries.read().unwrap();
+
+        let events = entries
+            .events_from(self.client_id, state.next_unread)
+            .ok_or(MafiaGameError::EventsUnavailable(state.next_seq - 1))?
+            .map(|(_, event)| state.take(event))
+            .collect();
+
+        self.set_next_unread(&mut state, entries.next_pos());
+
+        Ok(events)
+    }
+
+    /// Takes the first event not taken yet, if there is one.
+    fn take_next(&self) -> Result<Option<SequencedEvent>, MafiaGameError> {
+        let mut state = self.state.lock().unwrap();
+        let entries = self.log.entries.read().unwrap();
+
+        let next = entries
+            .events_from(self.client_id, state.next_unread)
+            .ok_or(MafiaGameError::EventsUnavailable(state.next_seq - 1))?
+            .next();
+
+        Ok(match next {
+            Some((pos, event)) => {
+                let event = state.take(event);
+                self.set_next_unread(&mut state, pos + 1);
+
+                Some(event)
+            }
+            None => {
+                self.set_next_unread(&mut state, entries.next_pos());
+
+                None
+            }
+        })
+    }
+
+    /// Waits up to `timeout` for events not taken yet, then takes them. Returns early without
+    /// events if the client disconnects.
+    pub(crate) fn wait_unread(
+        &self,
+        timeout: Duration,
+    ) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let events = self.take_unread()?;
+
+            if !events.is_empty() || !self.wait_for_events(self.next_unread(), deadline) {
+                return Ok(events);
+            }
+        }
+    }
+
+    /// Waits until entries sent to the client at position `pos` or later are appended, or some of
+    /// them are dropped. Returns `false` if the client disconnects or `deadline` passes first.
+    fn wait_for_events(&self, pos: u64, deadline: Instant) -> bool {
+        // Registered before looking for entries, so entries appended after that wake us up.
+        self.log
+            .waiting
+            .lock()
+            .unwrap()
+            .push(Arc::clone(&self.wakeup));
+
+        let appended = self
+            .log
+            .entries
+            .read()
+            .unwrap()
+            .events_from(self.client_id, pos)
+            .is_none_or(|mut events| events.next().is_some());
+
+        let woken = if appended {
+            !self.closed.load(Ordering::Relaxed)
+        } else {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+
+            let (next_pos, _) = self
+                .wakeup
+                .new_events
+                .wait_timeout_while(self.wakeup.next_pos.lock().unwrap(), timeout, |next_pos| {
+                    !self.closed.load(Ordering::Relaxed) && *next_pos <= pos
+                })
+                .unwrap();
+
+            !self.closed.load(Ordering::Relaxed) && *next_pos > pos
+        };
+
+        let mut waiting = self.log.waiting.lock().unwrap();
+
+        if let Some(i) = waiting
+            .iter()
+            .position(|wakeup| Arc::ptr_eq(wakeup, &self.wakeup))
+        {
+            waiting.swap_remove(i);
+        }
+
+        woken
+    }
+}
+
+/// Events of a client as they are sent, see
+/// [`MafiaGameServer::subscribe`](crate::MafiaGameServer::subscribe).
+///
+/// Events count as taken once received, dropping the subscription leaves the rest unread. The
+/// subscription ends when the client disconnects, or if events it was about to receive were
+/// dropped.
+pub struct Subscription {
+    cursor: Arc<EventCursor>,
+    /// Value of [`EventCursor::disconnects`] when subscribing.
+    disconnects: u64,
+}
+
+impl Subscription {
+    pub(crate) fn new(cursor: Arc<EventCursor>) -> Self {
+        Subscription {
+            disconnects: cursor.disconnects.load(Ordering::Relaxed),
+            cursor,
+        }
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.cursor.closed.load(Ordering::Relaxed)
+            || self.cursor.disconnects.load(Ordering::Relaxed) != self.disconnects
+    }
+
+    /// Returns `true` if the subscription ended because events it was about to receive were
+    /// dropped. Taking events catches the client up then, see
+    /// [`MafiaGameServer::take_events`](crate::MafiaGameServer::take_events).
+    pub fn fell_behind(&self) -> bool {
+        self.cursor.is_behind()
+    }
+
+    /// Takes the next event if there is one.
+    pub fn try_recv(&self) -> Result<SequencedEvent, TryRecvError> {
+        if self.is_disconnected() {
+            return Err(TryRecvError::Disconnected);
+        }
+
+        match self.cursor.take_next() {
+            Ok(event) => event.ok_or(TryRecvError::Empty),
+            Err(_) => Err(TryRecvError::Disconnected),
+        }
+    }
+
+    /// Waits up to `timeout` for the next event, then takes it.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<SequencedEvent, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match self.try_recv() {
+                Ok(event) => return Ok(event),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+
+            if !self
+                .cursor
+                .wait_for_events(self.cursor.next_unread(), deadline)
+            {
+                return Err(if self.is_disconnected() {
+                    RecvTimeoutError::Disconnected
+                } else {
+                    RecvTimeoutError::Timeout
+                });
+            }
+        }
+    }
+}
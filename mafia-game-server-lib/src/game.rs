@@ -1,35 +1,63 @@
 //! Core logic for a game of Mafia.
 
+use std::collections::BTreeSet;
 use std::collections::HashMap;
-use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::SystemTime;
-use std::time::UNIX_EPOCH;
 
 use mafia_game_lib::Allegiance;
 use mafia_game_lib::ClientId;
 use mafia_game_lib::Cycle;
+use mafia_game_lib::DeathCause;
+use mafia_game_lib::DrawReason;
 use mafia_game_lib::Event;
 use mafia_game_lib::EventChannel;
 use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::RoleReveal;
 use mafia_game_lib::SpecialRole;
+use mafia_game_lib::Verdict;
+use mafia_game_lib::Winners;
 use rand::Rng;
 use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serde::Serialize;
+use tap::Tap;
 use tracing::field;
 
 use crate::client::ClientSet;
-use crate::client::ClientState;
+use crate::clock::Clock;
+use crate::clock::system_clock;
+use crate::clock::unix_ts_secs;
 use crate::consts::DAY_DEATH_MESSAGES;
+use crate::consts::MODKILL_DEATH_MESSAGE;
 use crate::consts::NIGHT_DEATH_MESSAGES;
 use crate::error::MafiaGameError;
-
-// TODO(emersonford): allow this to be populated at runtime
-#[derive(Clone, Debug)]
+use crate::game_log::GameInput;
+use crate::game_log::GameLog;
+use crate::game_log::RecordingRng;
+use crate::night::NightOutcome;
+use crate::night::NightQueue;
+use crate::night::QueuedAction;
+use crate::role::NightAction;
+use crate::role::Role;
+use crate::role::RoleRegistry;
+use crate::win::LivingPlayer;
+use crate::win::WinContext;
+use crate::win::faction_has_won;
+use crate::win::is_stalemate;
+
+/// Settings for a game. Can be loaded from a file or picked from a preset, see the
+/// [`config`](crate::config) module.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct GameConfig {
     pub start_cycle: Cycle,
+    #[serde(with = "humantime_serde")]
     pub time_for_day: Duration,
     /// End the day cycle early if all votes have been submitted.
     pub end_day_after_all_votes: bool,
+    #[serde(with = "humantime_serde")]
     pub time_for_night: Duration,
     /// End the night cycle early if all votes have been submitted.
     pub end_night_after_all_votes: bool,
@@ -37,24 +65,225 @@ pub struct GameConfig {
     /// Time after cycle start during switch votes are rejected.
     ///
     /// Useful to avoid last-minute votes leaking into the next cycle and spoiling results.
+    #[serde(default, with = "humantime_serde")]
     pub vote_grace_period: Duration,
-    // TODO(emersonford): add option to reveal roles on death
+    /// Behavior for each role in `num_special_roles`.
+    ///
+    /// Not serialized, deserialized configs use the built-in roles.
+    #[serde(skip)]
+    pub roles: RoleRegistry,
+    /// What is revealed about a player's role when they die.
+    #[serde(default)]
+    pub reveal_on_death: RevealPolicy,
+    /// If set, day votes nominate players for a trial instead of lynching them directly.
+    #[serde(default)]
+    pub trial: Option<TrialConfig>,
+    /// The game ends in a draw if no one has won by the end of this day.
+    pub max_days: usize,
+    /// Most players a game can start with, unlimited if `None`.
+    #[serde(default)]
+    pub max_players: Option<usize>,
+    /// If set, players that stop voting are skipped and eventually removed from the game.
+    #[serde(default)]
+    pub afk: Option<AfkPolicy>,
+}
+
+impl GameConfig {
+    /// Checks the config, returning every problem found rather than just the first.
+    ///
+    /// Player counts are only checked if `num_players` is given.
+    pub fn validate(&self, num_players: Option<usize>) -> Result<(), Vec<MafiaGameError>> {
+        let mut problems = Vec::new();
+
+        let num_mafia_roles = self
+            .num_special_roles
+            .get(&SpecialRole::Mafia)
+            .copied()
+            .unwrap_or(0);
+        let total_special_roles = self.num_special_roles.values().copied().sum::<usize>();
+
+        let mut unregistered_roles = self
+            .num_special_roles
+            .keys()
+            .filter(|role| self.roles.get(role).is_none())
+            .collect::<Vec<_>>();
+        unregistered_roles.sort();
+
+        for role in unregistered_roles {
+            problems.push(MafiaGameError::InvalidGameConfig(format!(
+                "no behavior registered for role {:?}",
+                role
+            )));
+        }
+
+        if let Some(afk) = &self.afk
+            && (afk.skip_after_missed_cycles == 0
+                || afk.modkill_after_missed_cycles < afk.skip_after_missed_cycles)
+        {
+            problems.push(MafiaGameError::InvalidGameConfig(
+                "afk players must be skipped after at least 1 missed cycle, and before they are \
+                 modkilled"
+                    .to_string(),
+            ));
+        }
+
+        if self.max_days == 0 {
+            problems.push(MafiaGameError::InvalidGameConfig(
+                "max_days must be at least 1".to_string(),
+            ));
+        }
+
+        if self
+            .trial
+            .as_ref()
+            .is_some_and(|trial| trial.max_trials_per_day == 0)
+        {
+            problems.push(MafiaGameError::InvalidGameConfig(
+                "max_trials_per_day must be at least 1, disable trials to play without them"
+                    .to_string(),
+            ));
+        }
+
+        if num_mafia_roles == 0 {
+            problems.push(MafiaGameError::InvalidGameConfig(
+                "need at least 1 mafia, got 0".to_string(),
+            ));
+        }
+
+        if let Some(max_players) = self.max_players
+            && (num_mafia_roles * 2 >= max_players || total_special_roles > max_players)
+        {
+            problems.push(MafiaGameError::InvalidGameConfig(format!(
+                "max_players is {}, too few for {} mafia and {} special roles",
+                max_players, num_mafia_roles, total_special_roles
+            )));
+        }
+
+        if let Some(num_players) = num_players {
+            if num_mafia_roles * 2 >= num_players {
+                problems.push(MafiaGameError::NotEnoughPlayers(format!(
+                    "need at least {} players to play with {} mafia, only have {} players",
+                    num_mafia_roles * 2 + 1,
+                    num_mafia_roles,
+                    num_players
+                )));
+            }
+
+            if total_special_roles > num_players {
+                problems.push(MafiaGameError::NotEnoughPlayers(format!(
+                    "{} special roles were provided, but only have {} players",
+                    total_special_roles, num_players
+                )));
+            }
+
+            if let Some(max_players) = self.max_players
+                && num_players > max_players
+            {
+                problems.push(MafiaGameError::TooManyPlayers(format!(
+                    "games are capped at {} players, have {} players",
+                    max_players, num_players
+                )));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+/// Settings for days played in the trial format.
+///
+/// A player nominated by a majority of living players is put on trial. The accused gets
+/// `time_for_defense` to make their case, then every other living player has `time_for_verdict`
+/// to vote guilty or innocent. A guilty verdict kills the accused and ends the day, an innocent
+/// verdict returns to the day for the time it had left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrialConfig {
+    #[serde(with = "humantime_serde")]
+    pub time_for_defense: Duration,
+    #[serde(with = "humantime_serde")]
+    pub time_for_verdict: Duration,
+    /// Trials allowed per day, after which the day ends without a lynch. Must be at least 1.
+    pub max_trials_per_day: usize,
+}
+
+/// How players that stop voting are handled.
+///
+/// A player misses a cycle if it ends without them casting a vote or verdict they were entitled
+/// to. Votes are counted rather than client activity, so idle clients that stay connected are
+/// caught too, and replays see the same misses.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AfkPolicy {
+    /// After missing this many cycles in a row, the player is treated as skipping their vote,
+    /// so they no longer hold up cycles that end once everyone voted.
+    pub skip_after_missed_cycles: usize,
+    /// After missing this many cycles in a row, the player is killed by the server.
+    pub modkill_after_missed_cycles: usize,
+}
+
+/// What is revealed about a player's role when they die.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum RevealPolicy {
+    #[default]
+    Nothing,
+    /// Only reveal which side the player was on.
+    Allegiance,
+    /// Reveal the player's full role.
+    Role,
 }
 
 /// State for an active game.
+#[derive(Serialize, Deserialize)]
 pub(crate) struct Game {
     config: GameConfig,
-    role_to_players: HashMap<SpecialRole, Vec<ClientId>>,
     player_to_role: HashMap<ClientId, SpecialRole>,
     player_status: HashMap<ClientId, PlayerStatus>,
     cycle: Cycle,
     day_num: usize,
     cycle_start: SystemTime,
+    /// Time the input being applied arrived at. The clock is read once per input, so replaying
+    /// the input at the same time gives the same result.
+    input_time: SystemTime,
     /// Map of voter -> who they are voting for.
     ///
     /// If value is `None`, means the voter skipped voting.
     votes: HashMap<ClientId, Option<ClientId>>,
-    winner: Option<Allegiance>,
+    /// Trial in progress, if the game is in [`Cycle::Defense`] or [`Cycle::Verdict`].
+    trial: Option<Trial>,
+    /// Number of trials held in the current day.
+    trials_today: usize,
+    /// Neutral players that met their win condition so far.
+    neutral_winners: BTreeSet<ClientId>,
+    /// Number of cycles in a row each player missed, see [`AfkPolicy`].
+    #[serde(default)]
+    missed_cycles: HashMap<ClientId, usize>,
+    /// If set, death messages are picked at random from this seed, see
+    /// [`Game::randomize_death_messages`].
+    #[serde(default)]
+    death_message_seed: Option<u64>,
+    /// Number of players killed so far, picks a different death message for each.
+    #[serde(default)]
+    num_killed: u64,
+    winner: Option<Winners>,
+    draw: Option<DrawReason>,
+    log: GameLog,
+    /// Restored games read the system time until given their server's clock with
+    /// [`Game::set_clock`].
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Trial {
+    accused: ClientId,
+    /// Time the day had been running for when the trial started.
+    day_elapsed: Duration,
+    verdicts: HashMap<ClientId, Verdict>,
 }
 
 pub(crate) fn is_alive(
@@ -65,52 +294,29 @@ pub(crate) fn is_alive(
     st == PlayerStatus::Alive
 }
 
-pub(crate) fn is_alive_and_mafia(
-    st: PlayerStatus,
-    _role: Option<SpecialRole>,
-    allegiance: Allegiance,
-) -> bool {
-    st == PlayerStatus::Alive && allegiance == Allegiance::Mafia
+/// Scrambles `x`, see <https://prng.di.unimi.it/splitmix64.c>. Unlike the generators in `rand`,
+/// the output is fixed, so logged games replay the same on any version.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+    z ^ (z >> 31)
 }
 
 impl Game {
     pub(crate) fn start<S: Rng>(
         config: GameConfig,
-        clients: &ClientState,
-        mut seed: S,
+        players: &ClientSet,
+        seed: S,
+        clock: Arc<dyn Clock>,
     ) -> Result<Self, MafiaGameError> {
-        let mut clients = clients.list_clients().values().copied().collect::<Vec<_>>();
+        let mut clients = players.into_iter().collect::<Vec<_>>();
         // Sort for determinism with deterministic seed.
         clients.sort();
 
-        let num_mafia_roles = config
-            .num_special_roles
-            .get(&SpecialRole::Mafia)
-            .copied()
-            .unwrap_or(0);
-        let total_special_roles = config.num_special_roles.values().copied().sum::<usize>();
-
-        if num_mafia_roles == 0 {
-            return Err(MafiaGameError::InvalidGameConfig(
-                "need at least 1 mafia, got 0".to_string(),
-            ));
-        }
-
-        if num_mafia_roles * 2 >= clients.len() {
-            return Err(MafiaGameError::NotEnoughPlayers(format!(
-                "need at least {} players to play with {} mafia, only have {} players",
-                num_mafia_roles * 2 + 1,
-                num_mafia_roles,
-                clients.len()
-            )));
-        }
-
-        if total_special_roles > clients.len() {
-            return Err(MafiaGameError::NotEnoughPlayers(format!(
-                "{} special roles were provided, but only have {} players",
-                total_special_roles,
-                clients.len()
-            )));
+        if let Err(mut problems) = config.validate(Some(clients.len())) {
+            return Err(problems.remove(0));
         }
 
         let mut num_special_roles = config
@@ -119,11 +325,12 @@ impl Game {
             .into_iter()
             .collect::<Vec<_>>();
         // Sort for determinism with deterministic seed.
-        num_special_roles.sort_by_key(|(role, _)| *role);
+        num_special_roles.sort_by_key(|(role, _)| role.clone());
 
+        let players = clients.clone();
+        let mut seed = RecordingRng::new(seed);
         clients.shuffle(&mut seed);
 
-        let mut role_to_players = HashMap::new();
         let mut player_to_role = HashMap::new();
 
         let mut client_idx = 0;
@@ -131,21 +338,28 @@ impl Game {
             for _ in 0..num {
                 let client_id = clients[client_idx];
 
-                role_to_players
-                    .entry(special_role)
-                    .or_insert_with(Vec::new)
-                    .push(client_id);
-                player_to_role.insert(client_id, special_role);
+                player_to_role.insert(client_id, special_role.clone());
 
                 client_idx += 1;
             }
         }
 
         let cycle = config.start_cycle;
+        let now = clock.now();
+
+        let mut log = GameLog::default();
+        log.push(
+            now,
+            GameInput::Start {
+                config: Box::new(config.clone()),
+                roles: config.roles.ids(),
+                players,
+                rng_draws: seed.into_draws(),
+            },
+        );
 
         Ok(Game {
             config,
-            role_to_players,
             player_to_role,
             player_status: clients
                 .into_iter()
@@ -153,12 +367,52 @@ impl Game {
                 .collect(),
             cycle,
             day_num: 1,
-            cycle_start: SystemTime::now(),
+            cycle_start: now,
+            input_time: now,
             votes: HashMap::new(),
+            trial: None,
+            trials_today: 0,
+            neutral_winners: BTreeSet::new(),
+            missed_cycles: HashMap::new(),
+            death_message_seed: None,
+            num_killed: 0,
             winner: None,
+            draw: None,
+            log,
+            clock,
         })
     }
 
+    pub(crate) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Picks death messages at random from now on, rather than always the first one. The pick
+    /// only depends on `seed` and the number of players killed before, so replays get the same
+    /// messages.
+    pub(crate) fn randomize_death_messages(&mut self, seed: u64) {
+        self.input_time = self.clock.now();
+        self.death_message_seed = Some(seed);
+
+        self.log
+            .push(self.input_time, GameInput::RandomizeDeathMessages { seed });
+    }
+
+    /// Returns the message for the next player killed, out of `messages`.
+    fn next_death_message(&mut self, messages: &[&str]) -> Box<str> {
+        let index = self.death_message_seed.map_or(0, |seed| {
+            (splitmix64(seed.wrapping_add(self.num_killed)) % messages.len() as u64) as usize
+        });
+        self.num_killed += 1;
+
+        Box::from(messages[index])
+    }
+
+    /// Returns every input applied to this game so far.
+    pub(crate) fn get_log(&self) -> &GameLog {
+        &self.log
+    }
+
     pub(crate) fn get_player_roles(&self) -> &HashMap<ClientId, SpecialRole> {
         &self.player_to_role
     }
@@ -172,14 +426,54 @@ impl Game {
     }
 
     pub(crate) fn get_player_role(&self, client_id: ClientId) -> Option<SpecialRole> {
-        self.player_to_role.get(&client_id).copied()
+        self.player_to_role.get(&client_id).cloned()
     }
 
-    pub(crate) fn get_player_allegiance(&self, client_id: ClientId) -> Allegiance {
+    /// Returns the behavior for `role`.
+    pub(crate) fn get_role_impl(&self, role: &SpecialRole) -> &dyn Role {
+        self.config
+            .roles
+            .get(role)
+            .expect("roles are validated on game start")
+    }
+
+    pub(crate) fn get_player_role_impl(&self, client_id: ClientId) -> Option<&dyn Role> {
         self.get_player_role(client_id)
+            .map(|role| self.get_role_impl(&role))
+    }
+
+    pub(crate) fn get_player_allegiance(&self, client_id: ClientId) -> Allegiance {
+        self.get_player_role_impl(client_id)
             .map_or(Allegiance::Villagers, |role| role.allegiance())
     }
 
+    /// Returns what is revealed about `client_id`'s role when they die, per
+    /// [`GameConfig::reveal_on_death`].
+    pub(crate) fn get_death_reveal(&self, client_id: ClientId) -> Option<RoleReveal> {
+        let allegiance = self.get_player_allegiance(client_id);
+
+        match self.config.reveal_on_death {
+            RevealPolicy::Nothing => None,
+            RevealPolicy::Allegiance => Some(RoleReveal::Allegiance(allegiance)),
+            RevealPolicy::Role => Some(RoleReveal::Role {
+                allegiance,
+                role: self.get_player_role(client_id),
+            }),
+        }
+    }
+
+    /// Returns what was revealed about each dead player.
+    pub(crate) fn get_death_reveals(&self) -> HashMap<ClientId, RoleReveal> {
+        self.player_status
+            .iter()
+            .filter(|(_, st)| **st == PlayerStatus::Dead)
+            .filter_map(|(&client_id, _)| {
+                self.get_death_reveal(client_id)
+                    .map(|reveal| (client_id, reveal))
+            })
+            .collect()
+    }
+
     pub(crate) fn get_players<F: Fn(PlayerStatus, Option<SpecialRole>, Allegiance) -> bool>(
         &self,
         filter: F,
@@ -188,7 +482,7 @@ impl Game {
             .iter()
             .filter_map(|(&client_id, &st)| {
                 let role = self.get_player_role(client_id);
-                let allegiance = role.map_or(Allegiance::Villagers, |r| r.allegiance());
+                let allegiance = self.get_player_allegiance(client_id);
 
                 filter(st, role, allegiance).then_some(client_id)
             })
@@ -198,42 +492,26 @@ impl Game {
     fn end_cycle(&mut self) -> Vec<Event> {
         let mut ret = vec![];
 
-        if self.winner.is_some() {
+        if self.is_over() {
             return ret;
         }
 
         tracing::info!("ending cycle with votes: {:?}", self.votes);
 
-        match self.cycle {
-            Cycle::Day => {
-                let num_votes_for_player =
-                    self.votes
-                        .iter()
-                        .fold(HashMap::new(), |mut acc, (_, &target)| {
-                            if let Some(target) = target {
-                                *acc.entry(target).or_insert(0) += 1;
-                            }
-
-                            acc
-                        });
+        self.count_missed_cycles();
+        self.modkill_afk_players(&mut ret);
 
-                let num_players_alive = self.get_players(is_alive).count();
+        let mut lynched = None;
 
-                if let Some((voted_player, _)) = num_votes_for_player
-                    .into_iter()
-                    .find(|(_, count)| count * 2 > num_players_alive)
+        match self.cycle {
+            Cycle::Day => {
+                // When playing with trials, a lynch can only happen through a trial.
+                if let Some(voted_player) = self
+                    .get_day_vote_majority()
+                    .filter(|_| self.config.trial.is_none())
                 {
-                    tracing::info!("{:?} was killed during the day", voted_player);
-                    ret.push(Event::PlayerKilled {
-                        player: voted_player,
-                        cycle: self.cycle,
-                        death_message: Box::from(DAY_DEATH_MESSAGES[0]),
-                    });
-
-                    *self
-                        .player_status
-                        .get_mut(&voted_player)
-                        .expect("valid player") = PlayerStatus::Dead;
+                    self.kill_player_in_day(voted_player, &mut ret);
+                    lynched = Some(voted_player);
                 } else {
                     ret.push(Event::FailedVote {
                         cycle: self.cycle,
@@ -241,155 +519,404 @@ impl Game {
                     });
                 }
             }
-            Cycle::Night => {
-                let protected_players = self.role_to_players.get(&SpecialRole::Doctor).map_or_else(
-                    HashSet::new,
-                    |players| {
-                        players
-                            .iter()
-                            .filter_map(|client_id| self.votes.get(client_id))
-                            .flatten()
-                            .copied()
-                            .collect::<HashSet<_>>()
-                    },
-                );
+            Cycle::Defense => {
+                ret.push(self.set_cycle(Cycle::Verdict));
 
-                let num_mafia_votes_for_player = self
-                    .votes
-                    .iter()
-                    .filter(|(voter, _)| self.get_player_allegiance(**voter) == Allegiance::Mafia)
-                    .fold(HashMap::new(), |mut acc, (_, &target)| {
-                        if let Some(target) = target {
-                            *acc.entry(target).or_insert(0) += 1;
-                        }
-                        acc
-                    });
-
-                let num_mafia_alive = self.get_players(is_alive_and_mafia).count();
+                return ret;
+            }
+            Cycle::Verdict => {
+                let trial = self.trial.take().expect("trial in progress");
+
+                let num_guilty = trial
+                    .verdicts
+                    .values()
+                    .filter(|v| **v == Verdict::Guilty)
+                    .count();
+                let num_innocent = trial.verdicts.len() - num_guilty;
+
+                let verdict = if num_guilty > num_innocent {
+                    Verdict::Guilty
+                } else {
+                    Verdict::Innocent
+                };
+
+                tracing::info!(
+                    "{:?} was found {:?} {}-{}",
+                    trial.accused,
+                    verdict,
+                    num_guilty,
+                    num_innocent
+                );
 
-                if let Some((mafia_voted_player, _)) = num_mafia_votes_for_player
-                    .into_iter()
-                    .find(|(_, count)| count * 2 > num_mafia_alive)
+                ret.push(Event::TrialVerdict {
+                    accused: trial.accused,
+                    verdict,
+                    votes: trial.verdicts,
+                });
+
+                if verdict == Verdict::Guilty {
+                    self.kill_player_in_day(trial.accused, &mut ret);
+                    lynched = Some(trial.accused);
+                } else if self.trials_today
+                    < self
+                        .config
+                        .trial
+                        .as_ref()
+                        .map_or(0, |trial| trial.max_trials_per_day)
                 {
-                    // TODO(emersonford): add event for vote result / death
-                    if !protected_players.contains(&mafia_voted_player) {
-                        tracing::info!(
-                            "{:?} was killed by the mafia in the night",
-                            mafia_voted_player
-                        );
-                        ret.push(Event::PlayerKilled {
-                            player: mafia_voted_player,
-                            cycle: self.cycle,
-                            death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
-                        });
+                    // Resume the day where it left off.
+                    ret.push(self.resume_cycle(Cycle::Day, trial.day_elapsed));
 
-                        *self
-                            .player_status
-                            .get_mut(&mafia_voted_player)
-                            .expect("valid player") = PlayerStatus::Dead;
-                    } else {
-                        tracing::info!(
-                            "{:?} was protected from a mafia kill in the night",
-                            mafia_voted_player
-                        );
-                    }
+                    return ret;
                 } else {
                     ret.push(Event::FailedVote {
                         cycle: self.cycle,
-                        channel: EventChannel::Mafia,
+                        channel: EventChannel::Public,
                     });
                 }
+            }
+            Cycle::Night => {
+                let mut queue = NightQueue::new();
 
-                for investigator in self
-                    .role_to_players
-                    .get(&SpecialRole::Detective)
+                let mut night_actors = self
+                    .get_players(|st, role, _| st == PlayerStatus::Alive && role.is_some())
                     .into_iter()
-                    .flatten()
-                {
-                    if let Some(target) = self.votes.get(investigator).copied().flatten() {
-                        let allegiance = self.get_player_allegiance(target);
+                    .collect::<Vec<_>>();
+                // Sort for determinism.
+                night_actors.sort();
+
+                for actor in night_actors {
+                    let role = self.get_player_role_impl(actor).expect("has role");
+
+                    if let Some(action) = role.night_action() {
+                        queue.push(QueuedAction {
+                            actor,
+                            allegiance: role.allegiance(),
+                            action,
+                            target: self.votes.get(&actor).copied().flatten(),
+                            priority: role.night_priority(),
+                        });
+                    }
+                }
 
-                        tracing::info!(
-                            "{:?} was investigated by {:?} and discovered to be {:?}",
-                            target,
-                            investigator,
-                            allegiance
-                        );
+                let report = queue.resolve(|client_id| self.get_player_allegiance(client_id));
 
-                        ret.push(Event::PlayerInvestigated {
-                            actor: *investigator,
+                for outcome in report.outcomes {
+                    match outcome {
+                        NightOutcome::Killed {
+                            allegiance: _,
+                            target,
+                        } => {
+                            ret.push(Event::PlayerKilled {
+                                player: target,
+                                cycle: self.cycle,
+                                death_message: self.next_death_message(NIGHT_DEATH_MESSAGES),
+                                reveal: self.get_death_reveal(target),
+                                cause: DeathCause::Killed,
+                            });
+
+                            *self.player_status.get_mut(&target).expect("valid player") =
+                                PlayerStatus::Dead;
+                        }
+                        NightOutcome::FailedKill { allegiance } => {
+                            ret.push(Event::FailedVote {
+                                cycle: self.cycle,
+                                channel: if allegiance == Allegiance::Mafia {
+                                    EventChannel::Mafia
+                                } else {
+                                    EventChannel::Spectator
+                                },
+                            });
+                        }
+                        NightOutcome::Investigated {
+                            actor,
                             target,
                             allegiance,
-                        });
+                        } => {
+                            ret.push(Event::PlayerInvestigated {
+                                actor,
+                                target,
+                                allegiance,
+                            });
+                        }
+                        // Only logged during resolution, not surfaced to clients.
+                        NightOutcome::Blocked { .. }
+                        | NightOutcome::Redirected { .. }
+                        | NightOutcome::Protected { .. }
+                        | NightOutcome::Saved { .. } => {}
                     }
                 }
             }
         }
 
-        let num_mafia_alive = self.get_players(is_alive_and_mafia).count();
+        let alive = self.get_living_players();
+        let ctx = WinContext {
+            alive: &alive,
+            lynched,
+        };
 
-        if num_mafia_alive == 0 {
-            tracing::info!("all mafia eliminated, villagers win");
+        if let Some(winners) = self.check_winners(&ctx) {
+            tracing::info!("game won by {:?}", winners);
             ret.push(Event::GameWon {
                 player_to_role: self.player_to_role.clone(),
-                side: Allegiance::Villagers,
+                winners: winners.clone(),
             });
 
-            self.winner = Some(Allegiance::Villagers);
+            self.winner = Some(winners);
             return ret;
         }
 
-        let num_players_alive = self.get_players(is_alive).count();
+        let draw = if is_stalemate(&ctx) {
+            Some(DrawReason::Stalemate)
+        } else if self.cycle.next() == Cycle::Day && self.day_num >= self.config.max_days {
+            Some(DrawReason::MaxDays)
+        } else {
+            None
+        };
 
-        if num_mafia_alive * 2 >= num_players_alive {
-            tracing::info!("#mafia >= #non mafia; mafia win");
-            ret.push(Event::GameWon {
+        if let Some(reason) = draw {
+            tracing::info!("game ended in a draw: {:?}", reason);
+            ret.push(Event::GameDrawn {
                 player_to_role: self.player_to_role.clone(),
-                side: Allegiance::Mafia,
+                reason,
             });
 
-            self.winner = Some(Allegiance::Mafia);
+            self.draw = Some(reason);
             return ret;
         }
 
-        if self.day_num >= 100 {
-            tracing::error!("game exceeded 100 rounds, defaulting win to mafia");
-            ret.push(Event::GameWon {
-                player_to_role: self.player_to_role.clone(),
-                side: Allegiance::Mafia,
-            });
+        if self.cycle.next() == Cycle::Day {
+            self.day_num += 1;
+            self.trials_today = 0;
+        }
 
-            self.winner = Some(Allegiance::Mafia);
-            return ret;
+        ret.push(self.set_cycle(self.cycle.next()));
+
+        ret
+    }
+
+    /// Returns every living player, as seen by win conditions.
+    fn get_living_players(&self) -> Vec<LivingPlayer> {
+        let mut alive = self
+            .get_players(is_alive)
+            .into_iter()
+            .map(|id| LivingPlayer {
+                id,
+                allegiance: self.get_player_allegiance(id),
+                is_killer: self
+                    .get_player_role_impl(id)
+                    .is_some_and(|role| role.night_action() == Some(NightAction::Kill)),
+            })
+            .collect::<Vec<_>>();
+        // Sort for determinism.
+        alive.sort_by_key(|player| player.id);
+
+        alive
+    }
+
+    /// Records neutral players that met their win condition, and returns who won if the game is
+    /// over. See the `win` module for when a game ends.
+    fn check_winners(&mut self, ctx: &WinContext) -> Option<Winners> {
+        let neutral_winners = self
+            .player_to_role
+            .iter()
+            .filter(|&(&player, role)| {
+                let role = self.get_role_impl(role);
+
+                role.allegiance() == Allegiance::Neutral && role.has_won(player, ctx)
+            })
+            .map(|(&player, _)| player)
+            .collect::<Vec<_>>();
+        self.neutral_winners.extend(neutral_winners);
+
+        let factions = [Allegiance::Villagers, Allegiance::Mafia]
+            .into_iter()
+            .filter(|&faction| faction_has_won(faction, ctx))
+            .collect::<BTreeSet<_>>();
+
+        if factions.is_empty()
+            && (ctx.alive.is_empty()
+                || !ctx
+                    .alive
+                    .iter()
+                    .all(|player| self.neutral_winners.contains(&player.id)))
+        {
+            return None;
         }
 
+        Some(Winners {
+            factions,
+            players: self.neutral_winners.clone(),
+        })
+    }
+
+    /// Switches to `cycle`, clearing votes and restarting the cycle timer.
+    fn set_cycle(&mut self, cycle: Cycle) -> Event {
+        self.resume_cycle(cycle, Duration::from_secs(0))
+    }
+
+    /// Switches to `cycle` as if it had already been running for `elapsed`, clearing votes.
+    fn resume_cycle(&mut self, cycle: Cycle, elapsed: Duration) -> Event {
+        let now = self.input_time;
+
         self.votes = HashMap::new();
-        self.cycle = self.cycle.next();
-        self.day_num = if matches!(self.cycle, Cycle::Day) {
-            self.day_num + 1
-        } else {
-            self.day_num
-        };
+        self.cycle = cycle;
+        self.cycle_start = now.checked_sub(elapsed).unwrap_or(now);
 
-        ret.push(Event::SetCycle {
-            start_time_unix_ts_secs: if cfg!(test) {
-                0
-            } else {
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .expect("now is later than epoch")
-                    .as_secs()
-            },
-            duration_secs: if self.cycle == Cycle::Day {
-                self.config.time_for_day.as_secs()
-            } else {
-                self.config.time_for_night.as_secs()
-            },
+        Event::SetCycle {
+            start_time_unix_ts_secs: unix_ts_secs(self.cycle_start),
+            duration_secs: self.get_cycle_duration().as_secs(),
             cycle: self.cycle,
             day_num: self.day_num,
+        }
+    }
+
+    /// Returns the player a majority of living players voted for during the day, if any.
+    fn get_day_vote_majority(&self) -> Option<ClientId> {
+        let num_votes_for_player =
+            self.votes
+                .iter()
+                .fold(HashMap::new(), |mut acc, (_, &target)| {
+                    if let Some(target) = target {
+                        *acc.entry(target).or_insert(0) += 1;
+                    }
+
+                    acc
+                });
+
+        let num_players_alive = self.get_players(is_alive).count();
+
+        num_votes_for_player
+            .into_iter()
+            .find(|(_, count)| count * 2 > num_players_alive)
+            .map(|(voted_player, _)| voted_player)
+    }
+
+    fn kill_player_in_day(&mut self, player: ClientId, events: &mut Vec<Event>) {
+        tracing::info!("{:?} was killed during the day", player);
+        events.push(Event::PlayerKilled {
+            player,
+            cycle: self.cycle,
+            death_message: self.next_death_message(DAY_DEATH_MESSAGES),
+            reveal: self.get_death_reveal(player),
+            cause: DeathCause::Killed,
         });
 
-        ret
+        *self.player_status.get_mut(&player).expect("valid player") = PlayerStatus::Dead;
+    }
+
+    /// Returns the players expected to vote or cast a verdict in the current cycle.
+    fn get_expected_voters(&self) -> ClientSet {
+        match self.cycle {
+            Cycle::Day => self.get_players(is_alive),
+            Cycle::Defense => ClientSet::new(),
+            Cycle::Verdict => self.get_players(is_alive).tap_mut(|s| {
+                if let Some(trial) = &self.trial {
+                    s.remove(trial.accused);
+                }
+            }),
+            Cycle::Night => self.get_players(|st, role, _| {
+                st == PlayerStatus::Alive
+                    && role.is_some_and(|role| self.get_role_impl(&role).votes_at_night())
+            }),
+        }
+    }
+
+    /// Returns `true` if `player` voted or cast a verdict in the current cycle.
+    fn has_voted(&self, player: ClientId) -> bool {
+        match self.cycle {
+            Cycle::Day | Cycle::Night => self.votes.contains_key(&player),
+            Cycle::Defense => false,
+            Cycle::Verdict => self
+                .trial
+                .as_ref()
+                .is_some_and(|trial| trial.verdicts.contains_key(&player)),
+        }
+    }
+
+    /// Returns `true` if `player` missed enough cycles to have their vote skipped.
+    fn is_afk(&self, player: ClientId) -> bool {
+        self.config.afk.as_ref().is_some_and(|afk| {
+            self.missed_cycles.get(&player).copied().unwrap_or(0) >= afk.skip_after_missed_cycles
+        })
+    }
+
+    /// Returns `true` if every expected voter voted, or is skipped for being AFK.
+    fn all_votes_cast(&self) -> bool {
+        self.get_expected_voters()
+            .into_iter()
+            .all(|player| self.has_voted(player) || self.is_afk(player))
+    }
+
+    /// Updates how many cycles in a row each expected voter missed, as the current cycle ends.
+    fn count_missed_cycles(&mut self) {
+        if self.config.afk.is_none() {
+            return;
+        }
+
+        for player in &self.get_expected_voters() {
+            if self.has_voted(player) {
+                self.missed_cycles.remove(&player);
+            } else {
+                *self.missed_cycles.entry(player).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Kills living players that missed too many cycles in a row.
+    fn modkill_afk_players(&mut self, events: &mut Vec<Event>) {
+        let Some(afk) = &self.config.afk else {
+            return;
+        };
+
+        let mut afk_players = self
+            .get_players(is_alive)
+            .into_iter()
+            .filter(|player| {
+                self.missed_cycles.get(player).copied().unwrap_or(0)
+                    >= afk.modkill_after_missed_cycles
+            })
+            .collect::<Vec<_>>();
+        // Sort for determinism.
+        afk_players.sort();
+
+        for player in afk_players {
+            tracing::info!("{:?} was modkilled for being inactive", player);
+            events.push(Event::PlayerKilled {
+                player,
+                cycle: self.cycle,
+                death_message: Box::from(MODKILL_DEATH_MESSAGE),
+                reveal: self.get_death_reveal(player),
+                cause: DeathCause::Modkilled,
+            });
+
+            *self.player_status.get_mut(&player).expect("valid player") = PlayerStatus::Dead;
+
+            // Votes by or for the player no longer count.
+            self.votes.remove(&player);
+            for target in self.votes.values_mut() {
+                if *target == Some(player) {
+                    *target = None;
+                }
+            }
+        }
+    }
+
+    /// Puts `accused` on trial, moving the game to [`Cycle::Defense`].
+    fn start_trial(&mut self, accused: ClientId) -> Vec<Event> {
+        tracing::info!("{:?} was put on trial", accused);
+
+        self.trials_today += 1;
+        self.trial = Some(Trial {
+            accused,
+            day_elapsed: self.get_cycle_elapsed(),
+            verdicts: HashMap::new(),
+        });
+
+        vec![
+            Event::TrialStarted { accused },
+            self.set_cycle(Cycle::Defense),
+        ]
     }
 
     #[tracing::instrument(
@@ -405,7 +932,9 @@ impl Game {
         voter: ClientId,
         target: Option<ClientId>,
     ) -> Result<&mut Self, MafiaGameError> {
-        if self.winner.is_some() {
+        self.input_time = self.clock.now();
+
+        if self.is_over() {
             return Err(MafiaGameError::InvalidVote("game is complete".to_string()));
         }
 
@@ -423,11 +952,7 @@ impl Game {
             )));
         }
 
-        if SystemTime::now()
-            .duration_since(self.cycle_start)
-            .unwrap_or(Duration::from_secs(0))
-            < self.config.vote_grace_period
-        {
+        if self.get_cycle_elapsed() < self.config.vote_grace_period {
             return Err(MafiaGameError::InvalidVote(format!(
                 "must wait {:?} after cycle start to cast vote",
                 self.config.vote_grace_period
@@ -439,13 +964,17 @@ impl Game {
                 // TODO(emersonford): add event for vote cast
                 self.votes.insert(voter, target);
             }
+            Cycle::Defense | Cycle::Verdict => {
+                return Err(MafiaGameError::InvalidVote(format!(
+                    "cannot vote during {:?}, only cast verdicts",
+                    self.cycle
+                )));
+            }
             Cycle::Night => {
-                if !self.player_to_role.get(&voter).is_some_and(|v| {
-                    matches!(
-                        v,
-                        SpecialRole::Mafia | SpecialRole::Doctor | SpecialRole::Detective
-                    )
-                }) {
+                if !self
+                    .get_player_role_impl(voter)
+                    .is_some_and(|role| role.votes_at_night())
+                {
                     return Err(MafiaGameError::InvalidVote(format!(
                         "{:?} does not have a role eligible to vote in {:?}",
                         voter, self.cycle
@@ -457,46 +986,231 @@ impl Game {
             }
         }
 
+        self.log
+            .push(self.input_time, GameInput::Vote { voter, target });
+
+        Ok(self)
+    }
+
+    /// Returns the event announcing a vote just cast by `voter`, which only players that can see
+    /// it get.
+    pub(crate) fn vote_event(&self, voter: ClientId, target: Option<ClientId>) -> Event {
+        if self.cycle == Cycle::Day && self.has_trials() {
+            return Event::PlayerNominated {
+                voter,
+                accused: target,
+            };
+        }
+
+        let channel = if self.cycle.is_day() {
+            EventChannel::Public
+        }
+        // Is night
+        else if self.get_player_allegiance(voter) == Allegiance::Mafia {
+            EventChannel::Mafia
+        }
+        // Only self + spectator can see this vote.
+        else {
+            EventChannel::Spectator
+        };
+
+        Event::VoteIssued {
+            voter,
+            target,
+            channel,
+        }
+    }
+
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            cycle = format!("{:?} {}", self.cycle, self.day_num),
+            voter = voter.0,
+            verdict = field::debug(verdict),
+        )
+    )]
+    pub(crate) fn cast_verdict(
+        &mut self,
+        voter: ClientId,
+        verdict: Verdict,
+    ) -> Result<&mut Self, MafiaGameError> {
+        self.input_time = self.clock.now();
+
+        if self.is_over() {
+            return Err(MafiaGameError::InvalidVote("game is complete".to_string()));
+        }
+
+        if self.get_player_status(voter) != Some(PlayerStatus::Alive) {
+            return Err(MafiaGameError::InvalidVote(format!(
+                "voter {:?} is not alive",
+                voter
+            )));
+        }
+
+        let Some(trial) = self.trial.as_mut().filter(|_| self.cycle == Cycle::Verdict) else {
+            return Err(MafiaGameError::InvalidVote(format!(
+                "cannot cast a verdict during {:?}",
+                self.cycle
+            )));
+        };
+
+        if trial.accused == voter {
+            return Err(MafiaGameError::InvalidVote(
+                "cannot cast a verdict on your own trial".to_string(),
+            ));
+        }
+
+        trial.verdicts.insert(voter, verdict);
+
+        self.log
+            .push(self.input_time, GameInput::Verdict { voter, verdict });
+
         Ok(self)
     }
 
+    /// Hands the living `player`'s role, status and votes to `replacement`, who must not be in
+    /// the game. Votes for `player` become votes for `replacement`.
+    pub(crate) fn substitute(
+        &mut self,
+        player: ClientId,
+        replacement: ClientId,
+    ) -> Result<(), MafiaGameError> {
+        self.input_time = self.clock.now();
+
+        if self.is_over() {
+            return Err(MafiaGameError::NoGameInProgress);
+        }
+
+        if self.get_player_status(player) != Some(PlayerStatus::Alive) {
+            return Err(MafiaGameError::InvalidSubstitution(format!(
+                "{:?} is not a living player",
+                player
+            )));
+        }
+
+        if self.get_player_status(replacement).is_some() {
+            return Err(MafiaGameError::InvalidSubstitution(format!(
+                "{:?} is already playing",
+                replacement
+            )));
+        }
+
+        tracing::info!("{:?} replaced {:?}", replacement, player);
+
+        let swap = |client_id: ClientId| {
+            if client_id == player {
+                replacement
+            } else {
+                client_id
+            }
+        };
+
+        if let Some(role) = self.player_to_role.remove(&player) {
+            self.player_to_role.insert(replacement, role);
+        }
+
+        let status = self
+            .player_status
+            .remove(&player)
+            .expect("player is playing");
+        self.player_status.insert(replacement, status);
+
+        self.votes = std::mem::take(&mut self.votes)
+            .into_iter()
+            .map(|(voter, target)| (swap(voter), target.map(swap)))
+            .collect();
+
+        if let Some(trial) = &mut self.trial {
+            trial.accused = swap(trial.accused);
+            trial.verdicts = std::mem::take(&mut trial.verdicts)
+                .into_iter()
+                .map(|(voter, verdict)| (swap(voter), verdict))
+                .collect();
+        }
+
+        // The replacement starts with a clean record.
+        self.missed_cycles.remove(&player);
+
+        self.neutral_winners = std::mem::take(&mut self.neutral_winners)
+            .into_iter()
+            .map(swap)
+            .collect();
+
+        self.log.push(
+            self.input_time,
+            GameInput::Substitute {
+                player,
+                replacement,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Records that `player` disconnected from the server, returning the event announcing it.
+    /// Does not change the game, players stay in it until substituted.
+    pub(crate) fn disconnect(&mut self, player: ClientId) -> Event {
+        self.input_time = self.clock.now();
+
+        self.log
+            .push(self.input_time, GameInput::Disconnect { player });
+
+        Event::ClientDisconnected(player)
+    }
+
     /// Checks if we've met the conditions to end the cycle, and if so, ends the cycle.
     #[tracing::instrument(
         skip(self),
         fields(cycle = format!("{:?} {}", self.cycle, self.day_num)),
     )]
     pub(crate) fn poll_end_cycle(&mut self) -> Vec<Event> {
-        if self.winner.is_some() {
+        self.input_time = self.clock.now();
+
+        let events = self.check_end_cycle();
+
+        // Polls that did not change anything are not worth replaying.
+        if !events.is_empty() {
+            self.log.push(self.input_time, GameInput::EndCycle);
+        }
+
+        events
+    }
+
+    fn check_end_cycle(&mut self) -> Vec<Event> {
+        if self.is_over() {
             return vec![];
         }
 
-        if SystemTime::now()
-            .duration_since(self.cycle_start)
-            .unwrap_or(Duration::from_secs(0))
-            > self.get_cycle_duration()
-        {
+        if self.get_cycle_elapsed() > self.get_cycle_duration() {
             tracing::info!("reached cycle end time, ending cycle");
             return self.end_cycle();
         }
 
-        if self.cycle == Cycle::Day && self.config.end_day_after_all_votes {
-            let num_players_alive = self.get_players(is_alive).count();
-
-            if self.votes.len() == num_players_alive {
-                tracing::info!("all votes cast, ending cycle");
-                return self.end_cycle();
-            }
+        if self.cycle == Cycle::Day
+            && self
+                .config
+                .trial
+                .as_ref()
+                .is_some_and(|trial| self.trials_today < trial.max_trials_per_day)
+            && let Some(accused) = self.get_day_vote_majority()
+        {
+            return self.start_trial(accused);
         }
 
-        if self.cycle == Cycle::Night && self.config.end_night_after_all_votes {
-            let num_special_roles_alive = self
-                .get_players(|st, role, _| st == PlayerStatus::Alive && role.is_some())
-                .count();
+        if matches!(self.cycle, Cycle::Day | Cycle::Verdict)
+            && self.config.end_day_after_all_votes
+            && self.all_votes_cast()
+        {
+            tracing::info!("all votes cast, ending cycle");
+            return self.end_cycle();
+        }
 
-            if self.votes.len() == num_special_roles_alive {
-                tracing::info!("all votes cast, ending cycle");
-                return self.end_cycle();
-            }
+        if self.cycle == Cycle::Night
+            && self.config.end_night_after_all_votes
+            && self.all_votes_cast()
+        {
+            tracing::info!("all votes cast, ending cycle");
+            return self.end_cycle();
         }
 
         vec![]
@@ -507,19 +1221,59 @@ impl Game {
     }
 
     pub(crate) fn get_cycle_duration(&self) -> Duration {
-        if self.cycle == Cycle::Day {
-            self.config.time_for_day
-        } else {
-            self.config.time_for_night
+        match self.cycle {
+            Cycle::Day => self.config.time_for_day,
+            Cycle::Defense => self
+                .config
+                .trial
+                .as_ref()
+                .map_or(Duration::from_secs(0), |trial| trial.time_for_defense),
+            Cycle::Verdict => self
+                .config
+                .trial
+                .as_ref()
+                .map_or(Duration::from_secs(0), |trial| trial.time_for_verdict),
+            Cycle::Night => self.config.time_for_night,
         }
     }
 
+    /// Seconds since unix epoch the current cycle started at.
+    pub(crate) fn get_cycle_start_unix_ts_secs(&self) -> u64 {
+        unix_ts_secs(self.cycle_start)
+    }
+
+    /// Time the current cycle had been running for when the current input arrived.
+    pub(crate) fn get_cycle_elapsed(&self) -> Duration {
+        self.input_time
+            .duration_since(self.cycle_start)
+            .unwrap_or(Duration::from_secs(0))
+    }
+
+    /// Returns the player on trial, if any.
+    pub(crate) fn get_accused(&self) -> Option<ClientId> {
+        self.trial.as_ref().map(|trial| trial.accused)
+    }
+
+    /// Returns `true` if day votes nominate players for trial.
+    pub(crate) fn has_trials(&self) -> bool {
+        self.config.trial.is_some()
+    }
+
     pub(crate) fn get_day_num(&self) -> usize {
         self.day_num
     }
 
-    pub(crate) fn get_winner(&self) -> Option<Allegiance> {
-        self.winner
+    pub(crate) fn get_winner(&self) -> Option<&Winners> {
+        self.winner.as_ref()
+    }
+
+    pub(crate) fn get_draw(&self) -> Option<DrawReason> {
+        self.draw
+    }
+
+    /// Returns `true` if the game was won or drawn.
+    pub(crate) fn is_over(&self) -> bool {
+        self.winner.is_some() || self.draw.is_some()
     }
 
     pub(crate) fn get_votes(&self) -> &HashMap<ClientId, Option<ClientId>> {
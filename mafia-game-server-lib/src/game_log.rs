@@ -0,0 +1,269 @@
+//! Log of every input that changed a game, used to replay games deterministically.
+//!
+//! A [`Game`] appends to its log as inputs are applied. Replaying the log feeds the same inputs
+//! at the same times to a new game, so it ends up in the same state and emits the same events,
+//! e.g. to investigate disputed games or reproduce bugs.
+//!
+//! The events replayed are every event about the game: those it emits as cycles end, votes and
+//! nominations, disconnects of its players and substitutions. Events about the room are not,
+//! e.g. each client's view of the game or the game ending early because the host ended it.
+//!
+//! Role behavior is code and is not logged, only the identifiers of the roles registered for the
+//! game. Replaying looks them up in a [`RoleRegistry`] given by the caller.
+
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use mafia_game_lib::ClientId;
+use mafia_game_lib::Event;
+use mafia_game_lib::SpecialRole;
+use mafia_game_lib::Verdict;
+use rand::Rng;
+use rand::RngCore;
+use rand::rand_core::impls;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::client::ClientSet;
+use crate::clock::MockClock;
+use crate::error::MafiaGameError;
+use crate::game::Game;
+use crate::game::GameConfig;
+use crate::role::RoleRegistry;
+
+/// Input that changed a game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GameInput {
+    /// The game started with `players`. `rng_draws` holds every value drawn from the seed, so the
+    /// same roles are handed out on replay.
+    Start {
+        config: Box<GameConfig>,
+        /// Identifiers of the roles in `config`'s registry, which is not serialized.
+        roles: Vec<SpecialRole>,
+        players: Vec<ClientId>,
+        rng_draws: Vec<u64>,
+    },
+    Vote {
+        voter: ClientId,
+        target: Option<ClientId>,
+    },
+    Verdict {
+        voter: ClientId,
+        verdict: Verdict,
+    },
+    /// The game was polled and ended a cycle or started a trial.
+    EndCycle,
+    /// `replacement` took over `player`'s place in the game.
+    Substitute {
+        player: ClientId,
+        replacement: ClientId,
+    },
+    /// Death messages are picked at random from `seed` from now on.
+    RandomizeDeathMessages {
+        seed: u64,
+    },
+    /// `player` disconnected from the server.
+    Disconnect {
+        player: ClientId,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameLogEntry {
+    /// Time the input was applied at.
+    pub time: SystemTime,
+    pub input: GameInput,
+}
+
+/// Every input that changed a game, oldest first. Always starts with a [`GameInput::Start`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameLog(Vec<GameLogEntry>);
+
+impl GameLog {
+    pub(crate) fn push(&mut self, time: SystemTime, input: GameInput) {
+        self.0.push(GameLogEntry { time, input });
+    }
+
+    pub fn entries(&self) -> &[GameLogEntry] {
+        &self.0
+    }
+
+    /// Replays the log, returning every event the game emitted in order.
+    ///
+    /// `roles` must hold every role registered when the game started, e.g.
+    /// [`RoleRegistry::default`] plus any house roles.
+    pub fn replay(&self, roles: &RoleRegistry) -> Result<Vec<Event>, MafiaGameError> {
+        self.replay_game(roles).map(|(_, events)| events)
+    }
+
+    /// Rebuilds the game from the log, returning it along with every event it emitted. The game
+    /// is left with a [`MockClock`] set to the time of the last input.
+    pub(crate) fn replay_game(
+        &self,
+        roles: &RoleRegistry,
+    ) -> Result<(Game, Vec<Event>), MafiaGameError> {
+        let Some((
+            GameLogEntry {
+                time,
+                input:
+                    GameInput::Start {
+                        config,
+                        roles: role_ids,
+                        players,
+                        rng_draws,
+                    },
+            },
+            inputs,
+        )) = self.0.split_first()
+        else {
+            return Err(MafiaGameError::InvalidGameLog(
+                "log must start with the game start".to_string(),
+            ));
+        };
+
+        let config = GameConfig {
+            roles: roles.select(role_ids).map_err(|role| {
+                MafiaGameError::InvalidGameLog(format!("no behavior given for role {:?}", role))
+            })?,
+            ..GameConfig::clone(config)
+        };
+
+        let clock = Arc::new(MockClock::new(*time));
+        let mut rng = ReplayRng {
+            draws: rng_draws.iter(),
+            exhausted: false,
+        };
+        let mut game = Game::start(
+            config,
+            &players.iter().copied().collect::<ClientSet>(),
+            &mut rng,
+            clock.clone(),
+        )?;
+
+        if rng.exhausted {
+            return Err(MafiaGameError::InvalidGameLog(
+                "not enough random values to start the game".to_string(),
+            ));
+        }
+
+        let mut events = Vec::new();
+
+        for (i, entry) in inputs.iter().enumerate() {
+            clock.set(entry.time);
+
+            match &entry.input {
+                GameInput::Start {
+                    config: _,
+                    roles: _,
+                    players: _,
+                    rng_draws: _,
+                } => {
+                    return Err(MafiaGameError::InvalidGameLog(format!(
+                        "game started again at entry {}",
+                        i + 1
+                    )));
+                }
+                GameInput::Vote { voter, target } => {
+                    game.cast_vote(*voter, *target)?;
+                    events.push(game.vote_event(*voter, *target));
+                }
+                GameInput::Verdict { voter, verdict } => {
+                    game.cast_verdict(*voter, *verdict)?;
+                }
+                GameInput::EndCycle => {
+                    let cycle_events = game.poll_end_cycle();
+
+                    if cycle_events.is_empty() {
+                        return Err(MafiaGameError::InvalidGameLog(format!(
+                            "cycle did not end at entry {}",
+                            i + 1
+                        )));
+                    }
+
+                    events.extend(cycle_events);
+                }
+                GameInput::Substitute {
+                    player,
+                    replacement,
+                } => {
+                    game.substitute(*player, *replacement)?;
+                    events.push(Event::PlayerReplaced {
+                        player: *player,
+                        replacement: *replacement,
+                    });
+                }
+                GameInput::RandomizeDeathMessages { seed } => {
+                    game.randomize_death_messages(*seed);
+                }
+                GameInput::Disconnect { player } => {
+                    events.push(game.disconnect(*player));
+                }
+            }
+        }
+
+        Ok((game, events))
+    }
+}
+
+/// Passes through values drawn from `S`, remembering them for the game log.
+pub(crate) struct RecordingRng<S> {
+    rng: S,
+    draws: Vec<u64>,
+}
+
+impl<S: Rng> RecordingRng<S> {
+    pub(crate) fn new(rng: S) -> Self {
+        RecordingRng {
+            rng,
+            draws: Vec::new(),
+        }
+    }
+
+    pub(crate) fn into_draws(self) -> Vec<u64> {
+        self.draws
+    }
+}
+
+impl<S: Rng> RngCore for RecordingRng<S> {
+    fn next_u32(&mut self) -> u32 {
+        let value = self.rng.next_u32();
+        self.draws.push(value.into());
+
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.rng.next_u64();
+        self.draws.push(value);
+
+        value
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dst)
+    }
+}
+
+/// Yields the values remembered by a [`RecordingRng`].
+struct ReplayRng<'a> {
+    draws: std::slice::Iter<'a, u64>,
+    /// Set if more values were drawn than remembered, e.g. if the log was edited.
+    exhausted: bool,
+}
+
+impl RngCore for ReplayRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws.next().copied().unwrap_or_else(|| {
+            self.exhausted = true;
+            0
+        })
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dst)
+    }
+}
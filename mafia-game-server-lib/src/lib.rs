@@ -1,314 +1,450 @@
 //! Provides an implementation for the [Mafia game](https://en.wikipedia.org/wiki/Mafia_(party_game)).
 
 use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::sync::atomic::AtomicBool;
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::Duration;
-use std::time::SystemTime;
-use std::time::UNIX_EPOCH;
 
 use client::ClientSet;
 use client::ClientState;
-use consts::DAY_DEATH_MESSAGES;
-use consts::NIGHT_DEATH_MESSAGES;
+use client::ClientStateSnapshot;
+use clock::Clock;
+use event_log::Subscription;
 use game::Game;
 use game::GameConfig;
-use game::is_alive;
+use game_log::GameLog;
 use mafia_game_lib::Allegiance;
 use mafia_game_lib::ClientId;
-use mafia_game_lib::Cycle;
 use mafia_game_lib::Entity;
 use mafia_game_lib::Event;
 use mafia_game_lib::EventChannel;
-use mafia_game_lib::GameInfo;
+use mafia_game_lib::LobbyStatus;
 use mafia_game_lib::Message;
 use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::RoomInfo;
+use mafia_game_lib::SequencedEvent;
 use mafia_game_lib::ServerInfo;
 use mafia_game_lib::SessionToken;
-use mafia_game_lib::SpecialRole;
+use mafia_game_lib::Verdict;
 use rand::Rng;
-use rand::seq::IndexedRandom;
+use room::LOBBY;
+use room::Room;
+use serde::Deserialize;
+use serde::Serialize;
 
 pub mod client;
+pub mod clock;
+pub mod config;
 mod consts;
 mod error;
+pub mod event_log;
 pub mod game;
+pub mod game_log;
+mod night;
+pub mod role;
+pub mod room;
+pub mod win;
 
 pub use error::MafiaGameError;
-use tap::Tap;
 
+/// Server settings, can be loaded from a file with [`config::load_server_config`]. Fields missing
+/// from the file keep their [`Default`] value.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct MafiaGameServerConfig {
     /// Max time a client can be inactive before we force disconnect it.
+    #[serde(with = "humantime_serde")]
     pub max_client_inactive_time: Duration,
+    /// Max time a disconnected client can reconnect and resume its event stream before we purge
+    /// it.
+    #[serde(with = "humantime_serde")]
+    pub max_resume_time: Duration,
     pub randomize_death_message: bool,
+    /// Settings for games played in the [`LOBBY`]. Either a game config or a preset name.
+    #[serde(deserialize_with = "config::deserialize_game_config")]
+    pub lobby_game_config: GameConfig,
+    /// File the server ticker saves the server state to, see [`MafiaGameServer::save`].
+    pub snapshot_path: Option<PathBuf>,
+    /// How often the server ticker saves the server state, it is also saved on shutdown.
+    #[serde(with = "humantime_serde")]
+    pub snapshot_interval: Duration,
+    /// Token that acts as the host of every room, and can broadcast to every client.
+    pub admin_token: Option<SessionToken>,
+    /// Games start on their own once this many players in a room are ready. If `None`, games
+    /// are only started by hosts.
+    pub min_ready_players: Option<usize>,
+    /// Time between enough players being ready and the game starting.
+    #[serde(with = "humantime_serde")]
+    pub start_countdown: Duration,
+}
+
+impl Default for MafiaGameServerConfig {
+    fn default() -> Self {
+        MafiaGameServerConfig {
+            max_client_inactive_time: Duration::from_secs(300),
+            max_resume_time: Duration::from_secs(60),
+            randomize_death_message: true,
+            lobby_game_config: config::preset(config::DEFAULT_PRESET)
+                .expect("default preset exists"),
+            snapshot_path: None,
+            snapshot_interval: Duration::from_secs(30),
+            admin_token: None,
+            min_ready_players: None,
+            start_countdown: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Saved state of a [`MafiaGameServer`], generic over owned or borrowed rooms.
+#[derive(Serialize, Deserialize)]
+struct ServerSnapshot<R> {
+    clients: ClientStateSnapshot,
+    rooms: Vec<R>,
+    client_rooms: HashMap<ClientId, Arc<str>>,
 }
 
 struct MafiaGameServerInner {
     config: MafiaGameServerConfig,
     clients: ClientState,
-    active_game: Option<Game>,
+    rooms: HashMap<Arc<str>, Room>,
+    /// Room each connected client is in.
+    client_rooms: HashMap<ClientId, Arc<str>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl MafiaGameServerInner {
-    fn get_active_game(&self) -> Result<&Game, MafiaGameError> {
-        let Some(game) = self.active_game.as_ref() else {
-            return Err(MafiaGameError::NoGameInProgress);
-        };
+    fn get_room(&self, name: &str) -> Result<&Room, MafiaGameError> {
+        self.rooms
+            .get(name)
+            .ok_or_else(|| MafiaGameError::NoSuchRoom(name.to_string()))
+    }
 
-        if game.get_winner().is_some() {
-            return Err(MafiaGameError::NoGameInProgress);
+    fn get_room_mut(&mut self, name: &str) -> Result<&mut Room, MafiaGameError> {
+        self.rooms
+            .get_mut(name)
+            .ok_or_else(|| MafiaGameError::NoSuchRoom(name.to_string()))
+    }
+
+    /// Returns the name of the room `client_id` is in.
+    fn get_client_room_name(&self, client_id: ClientId) -> Arc<str> {
+        Arc::clone(
+            self.client_rooms
+                .get(&client_id)
+                .expect("connected clients are in a room"),
+        )
+    }
+
+    fn get_client_room(&self, client_id: ClientId) -> &Room {
+        &self.rooms[&self.get_client_room_name(client_id)]
+    }
+
+    fn get_client_room_mut(&mut self, client_id: ClientId) -> &mut Room {
+        let name = self.get_client_room_name(client_id);
+
+        self.rooms.get_mut(&name).expect("room exists")
+    }
+
+    /// Moves `client_id` into the room `to`, letting clients in both rooms know.
+    fn move_client(&mut self, client_id: ClientId, to: &str) -> Result<(), MafiaGameError> {
+        let to = Arc::clone(&self.get_room(to)?.name);
+        let from = self.get_client_room_name(client_id);
+
+        if from == to {
+            return Ok(());
+        }
+
+        if self.rooms[&from].is_playing(client_id) {
+            return Err(MafiaGameError::PlayerInGame);
         }
 
-        Ok(game)
+        if let Some(from) = self.remove_from_room(client_id) {
+            self.send_event(&self.rooms[&from], Event::ClientDisconnected(client_id));
+        }
+
+        // Readiness is for the next game of the room the client was in.
+        self.clients.set_ready(client_id, false)?;
+
+        self.add_to_room(client_id, to);
+        self.announce_client(client_id)
     }
 
-    fn get_active_game_mut(&mut self) -> Result<&mut Game, MafiaGameError> {
-        let Some(game) = self.active_game.as_mut() else {
-            return Err(MafiaGameError::NoGameInProgress);
-        };
+    fn add_to_room(&mut self, client_id: ClientId, name: Arc<str>) {
+        let room = self.rooms.get_mut(&name).expect("room exists");
 
-        if game.get_winner().is_some() {
-            return Err(MafiaGameError::NoGameInProgress);
+        room.members.insert(client_id);
+        room.host.get_or_insert(client_id);
+
+        self.client_rooms.insert(client_id, name);
+    }
+
+    /// Takes `client_id` out of its room, removing the room if it is now empty. Returns the name
+    /// of the room if it still exists.
+    fn remove_from_room(&mut self, client_id: ClientId) -> Option<Arc<str>> {
+        let name = self.client_rooms.remove(&client_id)?;
+
+        let room = self.rooms.get_mut(&name).expect("room exists");
+        room.members.remove(client_id);
+
+        if room.members.count() == 0 {
+            room.host = None;
+
+            if &*name != LOBBY {
+                self.rooms.remove(&name);
+
+                return None;
+            }
+        } else if room.host == Some(client_id) {
+            let host = room.members.into_iter().min().expect("room is not empty");
+            room.host = Some(host);
+
+            self.send_event(&self.rooms[&name], Event::HostChanged(host));
         }
 
-        Ok(game)
+        Some(name)
     }
 
-    fn in_active_game(&self) -> bool {
-        self.active_game
-            .as_ref()
-            .is_some_and(|game| game.get_winner().is_none())
+    /// Lets the other clients in `client_id`'s room know it arrived, and sends it the room's
+    /// state.
+    fn announce_client(&self, client_id: ClientId) -> Result<(), MafiaGameError> {
+        let room = self.get_client_room(client_id);
+
+        self.send_event(
+            room,
+            Event::ClientConnected(self.clients.get_client(client_id)?.get_info().clone()),
+        );
+        self.send_server_info(client_id);
+
+        Ok(())
     }
 
-    fn disconnect_client(&mut self, client_id: ClientId) -> Result<(), MafiaGameError> {
-        self.clients.disconnect_client(client_id)?;
+    /// Sends `client_id` the state of its room, letting it start over from there.
+    fn send_server_info(&self, client_id: ClientId) {
+        let room = self.get_client_room(client_id);
 
         self.clients.send_event(
-            self.clients.all_client_ids(),
-            Event::ClientDisconnected(client_id),
+            client_id.into(),
+            Event::SetServerInfo(ServerInfo {
+                room: Arc::clone(&room.name),
+                host: room.host,
+                connected_clients: self.clients.client_info(&room.members),
+                active_game: room.get_game_info_for(client_id),
+            }),
         );
+    }
+
+    /// Passes `result` through, catching the client up if it lost events it had not taken yet.
+    /// The client still gets the `Err`, and the state of its room as its next event.
+    fn resync_if_unavailable<T>(
+        &self,
+        client_id: ClientId,
+        result: Result<T, MafiaGameError>,
+    ) -> Result<T, MafiaGameError> {
+        if let Err(MafiaGameError::EventsUnavailable(seq)) = result {
+            tracing::info!("{:?} fell behind after event {}, resyncing", client_id, seq);
+
+            self.clients.skip_events(client_id)?;
+            self.send_server_info(client_id);
+        }
+
+        result
+    }
+
+    /// Returns an `Err` unless `session_token` is the admin token.
+    fn check_admin(&self, session_token: SessionToken) -> Result<(), MafiaGameError> {
+        if self.config.admin_token != Some(session_token) {
+            return Err(MafiaGameError::PermissionDenied);
+        }
+
+        Ok(())
+    }
+
+    /// Returns an `Err` unless `session_token` is the admin token or belongs to the host of
+    /// `room`.
+    fn check_host(&self, session_token: SessionToken, room: &str) -> Result<(), MafiaGameError> {
+        if self.check_admin(session_token).is_ok() {
+            return Ok(());
+        }
+
+        let client_id = self.clients.auth_client(session_token)?;
+
+        if self.get_room(room)?.host != Some(client_id) {
+            return Err(MafiaGameError::PermissionDenied);
+        }
+
+        Ok(())
+    }
+
+    fn disconnect_client(&mut self, client_id: ClientId) -> Result<(), MafiaGameError> {
+        self.clients.disconnect_client(client_id)?;
+
+        // Clients stay in their room, so they are back in it if they reconnect. Players'
+        // disconnects are part of the game, so they are logged along with it.
+        let name = self.get_client_room_name(client_id);
+        let event = match self.get_room_mut(&name)?.get_active_game_mut() {
+            Ok(game) if game.get_player_status(client_id).is_some() => game.disconnect(client_id),
+            _ => Event::ClientDisconnected(client_id),
+        };
+
+        self.send_room_events(&name, [event]);
 
         Ok(())
     }
 
     fn purge_disconnected_clients(&mut self) {
-        let clients_disconnected = self
-            .clients
-            .purge_disconnected_clients(self.config.max_client_inactive_time);
+        // Players of ongoing games are kept, so their IDs are not handed out to new clients.
+        let players = self
+            .rooms
+            .values()
+            .filter_map(|room| room.get_active_game().ok())
+            .fold(ClientSet::new(), |mut players, game| {
+                players.union_with(&game.get_players(|_, _, _| true));
+                players
+            });
+
+        let clients_disconnected = self.clients.purge_disconnected_clients(
+            self.config.max_client_inactive_time,
+            self.config.max_resume_time,
+            &players,
+        );
 
         for client_id in clients_disconnected {
-            self.clients.send_event(
-                self.clients.all_client_ids(),
+            self.send_event(
+                self.get_client_room(client_id),
                 Event::ClientDisconnected(client_id),
             );
         }
-    }
 
-    fn get_clients_for_channel(&self, actor: Option<ClientId>, channel: EventChannel) -> ClientSet {
-        let all_clients = self.clients.all_client_ids();
+        let purged = self
+            .client_rooms
+            .keys()
+            .copied()
+            .filter(|&client_id| self.clients.get_client(client_id).is_err())
+            .collect::<Vec<_>>();
 
-        match channel {
-            EventChannel::Public => all_clients,
-            EventChannel::Mafia => {
-                if let Some(game) = self.active_game.as_ref() {
-                    all_clients.tap_mut(|s| {
-                        s.difference_with(&game.get_players(|status, _, allegiance| {
-                            status == PlayerStatus::Alive && allegiance == Allegiance::Villagers
-                        }));
-                    })
-                } else {
-                    ClientSet::new()
-                }
-            }
-            EventChannel::Spectator => {
-                if let Some(game) = self.active_game.as_ref() {
-                    all_clients.tap_mut(|s| {
-                        s.difference_with(&game.get_players(is_alive));
-                    })
-                } else {
-                    all_clients
-                }
-            }
+        for client_id in purged {
+            self.remove_from_room(client_id);
         }
-        .tap_mut(|s| {
-            // Sender can always see their own messages.
-            if let Some(client_id) = actor {
-                s.insert(client_id);
-            }
-        })
     }
 
-    /// Returns a set of clients eligible to see the given event.
-    fn get_event_visibility(&self, event: &Event) -> ClientSet {
-        match event {
-            // These events should have their contents tailed to the recipient, hence should not be
-            // called in this function.
-            Event::SetServerInfo(_) | Event::SetGame(_) => {
-                unreachable!("should not be called with `get_event_visibility`")
-            }
-            Event::EndGame => self.clients.all_client_ids(),
-            Event::ClientConnected(info) => self.clients.all_client_ids().tap_mut(|s| {
-                s.remove(info.id);
-            }),
-            Event::ClientDisconnected(client_id) => self.clients.all_client_ids().tap_mut(|s| {
-                s.remove(*client_id);
-            }),
-            Event::MessageReceived(message) => match message.from {
-                Entity::Client(client_id) => {
-                    self.get_clients_for_channel(Some(client_id), message.channel)
-                }
-                Entity::System => self.clients.all_client_ids(),
-            },
-            Event::VoteIssued {
-                voter,
-                target: _,
-                channel,
-            } => self.get_clients_for_channel(Some(*voter), *channel),
-            Event::FailedVote { cycle: _, channel } => self.get_clients_for_channel(None, *channel),
-            Event::PlayerKilled {
-                player: _,
-                cycle: _,
-                death_message: _,
-            } => self.clients.all_client_ids(),
-            Event::SetCycle {
-                start_time_unix_ts_secs: _,
-                duration_secs: _,
-                cycle: _,
-                day_num: _,
-            } => self.clients.all_client_ids(),
-            Event::PlayerInvestigated {
-                actor,
-                target: _,
-                allegiance: _,
-            } => self.get_clients_for_channel(Some(*actor), EventChannel::Spectator),
-            Event::GameWon {
-                player_to_role: _,
-                side: _,
-            } => self.clients.all_client_ids(),
-        }
-    }
-
-    fn send_event(&self, mut event: Event) {
-        let to = self.get_event_visibility(&event);
-
-        if let Event::PlayerKilled {
-            player: _,
-            cycle,
-            death_message,
-        } = &mut event
-        {
-            if self.config.randomize_death_message {
-                let mut rng = rand::rng();
+    /// Starts a game in the room `name` with `players`, replacing any previous game. Everyone in
+    /// the room is unready for the game after.
+    fn start_room_game<S: Rng>(
+        &mut self,
+        name: &str,
+        players: &ClientSet,
+        seed: S,
+    ) -> Result<(), MafiaGameError> {
+        let clock = Arc::clone(&self.clock);
+        let room = self.get_room_mut(name)?;
 
-                match cycle {
-                    Cycle::Day => {
-                        *death_message =
-                            Box::from(*DAY_DEATH_MESSAGES.choose(&mut rng).expect("at least 1"));
-                    }
-                    Cycle::Night => {
-                        *death_message =
-                            Box::from(*NIGHT_DEATH_MESSAGES.choose(&mut rng).expect("at least 1"));
-                    }
-                }
-            }
+        let mut game = Game::start(room.config.clone(), players, seed, clock)?;
+        if self.config.randomize_death_message {
+            game.randomize_death_messages(rand::rng().random());
         }
 
-        self.clients.send_event(to, event);
-    }
+        let room = self.get_room_mut(name)?;
+        room.active_game = Some(game);
+        room.countdown_end = None;
 
-    fn get_game_info_for(&self, client: ClientId) -> Option<GameInfo> {
-        let Some(game) = self.active_game.as_ref() else {
-            return None;
-        };
+        let room = &self.rooms[name];
 
-        let mut game_info = GameInfo {
-            cycle_start_time_unix_ts_secs: if cfg!(test) {
-                0
-            } else {
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .expect("now is after epoch")
-                    .as_secs()
-            },
-            cycle_duration_secs: game.get_cycle_duration().as_secs(),
-            current_cycle: game.get_cycle(),
-            day_num: game.get_day_num(),
-            player_status: game.get_player_statuses().clone(),
-            winner: game.get_winner(),
-            player_to_role: HashMap::new(),
-            votes: HashMap::new(),
-        };
+        for client in &room.members {
+            self.clients.send_event(
+                client.into(),
+                Event::SetGame(room.get_game_info_for(client).expect("is active game")),
+            );
+        }
 
-        let status = game.get_player_status(client);
-        let role = game.get_player_role(client);
-        let cycle = game.get_cycle();
+        self.unready_room(name);
 
-        match (status, role, cycle) {
-            // Spectator or dead person can see everything.
-            (None | Some(PlayerStatus::Dead), _, _) => {
-                game_info.votes = game.get_votes().clone();
-            }
-            // Everyone can see votes during the day.
-            (Some(PlayerStatus::Alive), _, Cycle::Day) => {
-                game_info.votes = game.get_votes().clone();
-            }
-            // Mafia can see every other mafia's vote.
-            (Some(PlayerStatus::Alive), Some(SpecialRole::Mafia), Cycle::Night) => {
-                game_info.votes = game
-                    .get_votes()
-                    .iter()
-                    .filter_map(|(k, v)| {
-                        if game.get_player_allegiance(*k) == Allegiance::Mafia {
-                            Some((*k, *v))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-            }
-            // Special role can only see their own votes in the night.
-            (Some(PlayerStatus::Alive), Some(_), Cycle::Night) => {
-                game_info.votes = game
-                    .get_votes()
-                    .iter()
-                    .filter_map(|(k, v)| if *k == client { Some((*k, *v)) } else { None })
-                    .collect();
+        Ok(())
+    }
+
+    /// Marks every client in the room `name` as unready.
+    fn unready_room(&mut self, name: &str) {
+        for client_id in &self.rooms[name].members.clone() {
+            if self
+                .clients
+                .set_ready(client_id, false)
+                .is_ok_and(|changed| changed)
+            {
+                self.send_room_events(
+                    name,
+                    [Event::ReadyChanged {
+                        client: client_id,
+                        ready: false,
+                    }],
+                );
             }
-            // Villagers without roles cannot see any votes in the night.
-            (Some(PlayerStatus::Alive), None, Cycle::Night) => {}
         }
+    }
 
-        match (status, role) {
-            // Spectator or dead person can see everything.
-            (None | Some(PlayerStatus::Dead), _) => {
-                game_info.player_to_role = game.get_player_roles().clone();
-            }
-            (Some(PlayerStatus::Alive), Some(SpecialRole::Mafia)) => {
-                game_info.player_to_role = game
-                    .get_player_roles()
-                    .iter()
-                    .filter_map(|(&k, &v)| {
-                        if v == SpecialRole::Mafia {
-                            Some((k, v))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
+    /// Starts, cancels or finishes the countdown to the next game in the room `name`, depending
+    /// on whether `min_ready_players` are ready.
+    fn poll_countdown(&mut self, name: &str, min_ready_players: usize) {
+        let room = &self.rooms[name];
+
+        if room.in_active_game() {
+            return;
+        }
+
+        let ready = self.clients.ready_players(&room.members);
+        let now = self.clock.now();
+
+        match (ready.count() >= min_ready_players, room.countdown_end) {
+            (true, None) => {
+                let start_countdown = self.config.start_countdown;
+                let end = now + start_countdown;
+
+                self.rooms.get_mut(name).expect("room exists").countdown_end = Some(end);
+                self.send_room_events(
+                    name,
+                    [Event::CountdownStarted {
+                        start_time_unix_ts_secs: clock::unix_ts_secs(now),
+                        duration_secs: start_countdown.as_secs(),
+                    }],
+                );
             }
-            (Some(PlayerStatus::Alive), Some(SpecialRole::Doctor)) => {
-                game_info.player_to_role = HashMap::from_iter([(client, SpecialRole::Doctor)]);
+            (false, Some(_)) => {
+                self.rooms.get_mut(name).expect("room exists").countdown_end = None;
+                self.send_room_events(name, [Event::CountdownCancelled]);
             }
-            (Some(PlayerStatus::Alive), Some(SpecialRole::Detective)) => {
-                game_info.player_to_role = HashMap::from_iter([(client, SpecialRole::Detective)]);
+            (true, Some(end)) if now >= end => {
+                if let Err(e) = self.start_room_game(name, &ready, rand::rng()) {
+                    tracing::warn!("could not start game in room {}: {}", name, e);
+
+                    // Unready everyone, so the countdown does not start over right away.
+                    self.rooms.get_mut(name).expect("room exists").countdown_end = None;
+                    self.send_room_events(name, [Event::CountdownCancelled]);
+                    self.unready_room(name);
+                }
             }
-            (Some(PlayerStatus::Alive), None) => {}
+            (true, Some(_)) | (false, None) => {}
         }
+    }
+
+    /// Sends `event` to the clients in `room` that can see it.
+    fn send_event(&self, room: &Room, event: Event) {
+        let to = room.get_event_visibility(&event);
 
-        Some(game_info)
+        self.clients.send_event(to, event);
+    }
+
+    /// Sends each of `events` to the clients in the room `name` that can see it.
+    fn send_room_events(&self, name: &str, events: impl IntoIterator<Item = Event>) {
+        let room = &self.rooms[name];
+
+        for event in events {
+            self.send_event(room, event);
+        }
     }
 }
 
@@ -317,72 +453,203 @@ impl MafiaGameServerInner {
 pub struct MafiaGameServer(Arc<RwLock<MafiaGameServerInner>>);
 
 impl MafiaGameServer {
-    pub fn new(config: MafiaGameServerConfig) -> Self {
+    /// Creates a new server, reading the time from `clock`. Use
+    /// [`SystemClock`](clock::SystemClock) outside of tests.
+    pub fn new(config: MafiaGameServerConfig, clock: Arc<dyn Clock>) -> Self {
+        let lobby = Room::new(Arc::from(LOBBY), config.lobby_game_config.clone());
+
         MafiaGameServer(Arc::new(RwLock::new(MafiaGameServerInner {
             config,
-            clients: ClientState::new(),
-            active_game: None,
+            clients: ClientState::new(Arc::clone(&clock)),
+            rooms: HashMap::from_iter([(Arc::clone(&lobby.name), lobby)]),
+            client_rooms: HashMap::new(),
+            clock,
         })))
     }
 
-    /// Returns `true` if the server has an active game that is not in a won condition.
-    pub fn in_active_game(&self) -> bool {
-        self.0.read().unwrap().in_active_game()
+    /// Restores a server saved with [`MafiaGameServer::save`]. Clients come back disconnected,
+    /// and can pick up where they left off with [`MafiaGameServer::resume_client`].
+    ///
+    /// The lobby plays with `config`'s game config, other rooms and games keep the config they
+    /// were saved with. Custom roles are not saved, restored games play with the built-in roles.
+    pub fn restore(
+        path: &Path,
+        config: MafiaGameServerConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, MafiaGameError> {
+        let file = File::open(path).map_err(|e| MafiaGameError::Snapshot(e.to_string()))?;
+        let snapshot: ServerSnapshot<Room> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| MafiaGameError::Snapshot(e.to_string()))?;
+
+        let mut rooms = HashMap::new();
+
+        for mut room in snapshot.rooms {
+            if let Some(game) = room.active_game.as_mut() {
+                game.set_clock(Arc::clone(&clock));
+            }
+
+            if &*room.name == LOBBY {
+                room.config = config.lobby_game_config.clone();
+            }
+
+            rooms.insert(Arc::clone(&room.name), room);
+        }
+
+        if snapshot
+            .client_rooms
+            .values()
+            .any(|name| !rooms.contains_key(name))
+        {
+            return Err(MafiaGameError::Snapshot(
+                "client is in a room that does not exist".to_string(),
+            ));
+        }
+
+        rooms
+            .entry(Arc::from(LOBBY))
+            .or_insert_with(|| Room::new(Arc::from(LOBBY), config.lobby_game_config.clone()));
+
+        Ok(MafiaGameServer(Arc::new(RwLock::new(
+            MafiaGameServerInner {
+                config,
+                clients: ClientState::restore(snapshot.clients, Arc::clone(&clock)),
+                rooms,
+                client_rooms: snapshot.client_rooms,
+                clock,
+            },
+        ))))
+    }
+
+    /// Saves the state of every client, room and game to `path`, replacing the file atomically.
+    pub fn save(&self, path: &Path) -> Result<(), MafiaGameError> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        // Only serialize while holding the lock, writing to disk can be slow.
+        let contents = {
+            let slf = self.0.read().unwrap();
+
+            serde_json::to_vec(&ServerSnapshot {
+                clients: slf.clients.snapshot(),
+                rooms: slf.rooms.values().collect(),
+                client_rooms: slf.client_rooms.clone(),
+            })
+            .map_err(|e| MafiaGameError::Snapshot(e.to_string()))?
+        };
+
+        fs::write(&tmp_path, contents).map_err(|e| MafiaGameError::Snapshot(e.to_string()))?;
+        fs::rename(&tmp_path, path).map_err(|e| MafiaGameError::Snapshot(e.to_string()))
     }
 
-    /// Starts a new game. Returns an `Err` if there is an active game.
-    pub fn start_game<S: Rng>(&self, config: GameConfig, seed: S) -> Result<(), MafiaGameError> {
+    /// Saves the server state to [`MafiaGameServerConfig::snapshot_path`], if set.
+    fn save_configured_snapshot(&self) {
+        let Some(path) = self.0.read().unwrap().config.snapshot_path.clone() else {
+            return;
+        };
+
+        match self.save(&path) {
+            Ok(()) => tracing::debug!("saved server state to {:?}", path),
+            Err(e) => tracing::warn!("{}", e),
+        }
+    }
+
+    /// Returns `true` if the room has an active game that is not in a won condition.
+    pub fn in_active_game(&self, room: &str) -> Result<bool, MafiaGameError> {
+        Ok(self.0.read().unwrap().get_room(room)?.in_active_game())
+    }
+
+    /// Starts a new game in `room` with the room's game config, with every member that opted to
+    /// play as a player. Returns an `Err` if the room has an active game. Only the room's host or
+    /// an admin can start games.
+    pub fn start_game<S: Rng>(
+        &self,
+        session_token: SessionToken,
+        room: &str,
+        seed: S,
+    ) -> Result<(), MafiaGameError> {
         let mut slf = self.0.write().unwrap();
 
-        if slf.in_active_game() {
+        slf.check_host(session_token, room)?;
+
+        if slf.get_room(room)?.in_active_game() {
             return Err(MafiaGameError::GameInProgress);
         }
 
         slf.purge_disconnected_clients();
 
-        let game = Game::start(config, &slf.clients, seed)?;
-        slf.active_game = Some(game);
+        // The room may have been removed if all its clients were purged.
+        let players = slf.clients.players(&slf.get_room(room)?.members);
 
-        for client in &slf.clients.all_client_ids() {
-            slf.clients.send_event(
-                std::iter::once(client).collect(),
-                Event::SetGame(slf.get_game_info_for(client).expect("is active game")),
-            );
-        }
-
-        Ok(())
+        slf.start_room_game(room, &players, seed)
     }
 
-    /// Ends the current game, returning an `Err` if no game is active.
-    pub fn end_game(&self) -> Result<(), MafiaGameError> {
+    /// Ends the game in `room`, returning an `Err` if no game is active. Only the room's host or
+    /// an admin can end games.
+    pub fn end_game(&self, session_token: SessionToken, room: &str) -> Result<(), MafiaGameError> {
         let mut slf = self.0.write().unwrap();
-        if std::mem::take(&mut slf.active_game).is_none() {
+
+        slf.check_host(session_token, room)?;
+
+        let room = slf.get_room_mut(room)?;
+        if std::mem::take(&mut room.active_game).is_none() {
             return Err(MafiaGameError::NoGameInProgress);
         }
 
-        slf.send_event(Event::EndGame);
+        let name = Arc::clone(&room.name);
+        slf.send_room_events(&name, [Event::EndGame]);
 
         Ok(())
     }
 
-    /// Ticks the active game state.
+    /// Returns the log of the game in `room`, or of its last game if it is over. The log holds
+    /// every player's role, so only an admin can read it.
+    pub fn game_log(
+        &self,
+        session_token: SessionToken,
+        room: &str,
+    ) -> Result<GameLog, MafiaGameError> {
+        let slf = self.0.read().unwrap();
+
+        slf.check_admin(session_token)?;
+
+        let Some(game) = slf.get_room(room)?.active_game.as_ref() else {
+            return Err(MafiaGameError::NoGameInProgress);
+        };
+
+        Ok(game.get_log().clone())
+    }
+
+    /// Ticks the state of every room's game.
     pub fn do_tick(&self) {
         let mut slf = self.0.write().unwrap();
 
-        let events = if let Some(game) = slf.active_game.as_mut() {
-            game.poll_end_cycle()
-        } else {
-            slf.purge_disconnected_clients();
+        let events = slf
+            .rooms
+            .values_mut()
+            .filter_map(|room| {
+                let game = room.active_game.as_mut()?;
 
-            vec![]
-        };
+                Some((Arc::clone(&room.name), game.poll_end_cycle()))
+            })
+            .collect::<Vec<_>>();
 
-        for event in events {
-            slf.send_event(event);
+        for (room, events) in events {
+            slf.send_room_events(&room, events);
+        }
+
+        if let Some(min_ready_players) = slf.config.min_ready_players {
+            let rooms = slf.rooms.keys().cloned().collect::<Vec<_>>();
+
+            for room in rooms {
+                slf.poll_countdown(&room, min_ready_players);
+            }
         }
+
+        slf.purge_disconnected_clients();
     }
 
-    /// Handles a client request to connect.
+    /// Handles a client request to connect. New clients are put in the [`LOBBY`], clients that
+    /// reconnect are put back in the room they were in.
     pub fn connect_client(
         &self,
         client_name: &str,
@@ -391,22 +658,29 @@ impl MafiaGameServer {
 
         let (client_id, session_token) = slf.clients.connect_client(client_name)?;
 
-        let new_client_info = slf.clients.get_client(client_id)?.get_info().clone();
-
-        let connected_clients = slf.clients.all_client_info();
+        if !slf.client_rooms.contains_key(&client_id) {
+            slf.add_to_room(client_id, Arc::from(LOBBY));
+        }
 
-        slf.send_event(Event::ClientConnected(new_client_info));
-        slf.clients.send_event(
-            std::iter::once(client_id).collect(),
-            Event::SetServerInfo(ServerInfo {
-                connected_clients,
-                active_game: slf.get_game_info_for(client_id),
-            }),
-        );
+        slf.announce_client(client_id)?;
 
         Ok((client_id, session_token))
     }
 
+    /// Handles a client request to resume its session, e.g. after the server was restored. The
+    /// client keeps its session token and is put back in its room.
+    pub fn resume_client(&self, session_token: SessionToken) -> Result<ClientId, MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+
+        let (client_id, was_disconnected) = slf.clients.resume_client(session_token)?;
+
+        if was_disconnected {
+            slf.announce_client(client_id)?;
+        }
+
+        Ok(client_id)
+    }
+
     /// Handles a client request to disconnect.
     pub fn disconnect_client(&self, session_token: SessionToken) -> Result<(), MafiaGameError> {
         let mut slf = self.0.write().unwrap();
@@ -416,21 +690,258 @@ impl MafiaGameServer {
         slf.disconnect_client(client_id)
     }
 
-    /// Force disconnect a client. Intended as an admin API.
-    pub fn force_disconnect_client(&self, client_id: ClientId) -> Result<(), MafiaGameError> {
+    /// Force disconnect a client. Only the host of the client's room or an admin can do this.
+    pub fn force_disconnect_client(
+        &self,
+        session_token: SessionToken,
+        client_id: ClientId,
+    ) -> Result<(), MafiaGameError> {
         let mut slf = self.0.write().unwrap();
+
+        slf.clients.get_client(client_id)?;
+        slf.check_host(session_token, &slf.get_client_room_name(client_id))?;
+
         slf.disconnect_client(client_id)
     }
 
+    /// Makes `to` the host of the room it is in. Only the room's current host or an admin can do
+    /// this.
+    pub fn transfer_host(
+        &self,
+        session_token: SessionToken,
+        to: ClientId,
+    ) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+
+        slf.clients.get_client(to)?;
+        let name = slf.get_client_room_name(to);
+        slf.check_host(session_token, &name)?;
+
+        slf.get_room_mut(&name)?.host = Some(to);
+        slf.send_room_events(&name, [Event::HostChanged(to)]);
+
+        Ok(())
+    }
+
+    /// Marks the client as ready or unready for the next game of their room. Returns an `Err` if
+    /// the room has an active game.
+    pub fn set_ready(
+        &self,
+        session_token: SessionToken,
+        ready: bool,
+    ) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        if slf.get_client_room(client_id).in_active_game() {
+            return Err(MafiaGameError::GameInProgress);
+        }
+
+        if slf.clients.set_ready(client_id, ready)? {
+            let name = slf.get_client_room_name(client_id);
+            slf.send_room_events(
+                &name,
+                [Event::ReadyChanged {
+                    client: client_id,
+                    ready,
+                }],
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Sets the config for the next games in `room`. Only the room's host or an admin can do
+    /// this.
+    pub fn set_room_config(
+        &self,
+        session_token: SessionToken,
+        room: &str,
+        config: GameConfig,
+    ) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+
+        slf.check_host(session_token, room)?;
+        slf.get_room_mut(room)?.config = config;
+
+        Ok(())
+    }
+
+    /// Replaces `player` in the game of their room with `replacement`, a spectator in the same
+    /// room. Only the room's host or an admin can do this.
+    pub fn substitute_player(
+        &self,
+        session_token: SessionToken,
+        player: ClientId,
+        replacement: ClientId,
+    ) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+
+        slf.clients.get_client(player)?;
+        let name = slf.get_client_room_name(player);
+        slf.check_host(session_token, &name)?;
+
+        if !slf.get_room(&name)?.members.contains(replacement) {
+            return Err(MafiaGameError::InvalidSubstitution(format!(
+                "{:?} is not in the room",
+                replacement
+            )));
+        }
+
+        if slf.clients.get_client(replacement)?.get_info().lobby_status != LobbyStatus::Spectator {
+            return Err(MafiaGameError::InvalidSubstitution(format!(
+                "{:?} is not spectating",
+                replacement
+            )));
+        }
+
+        slf.get_room_mut(&name)?
+            .get_active_game_mut()?
+            .substitute(player, replacement)?;
+
+        slf.clients
+            .set_lobby_status(player, LobbyStatus::Spectator)?;
+        slf.clients
+            .set_lobby_status(replacement, LobbyStatus::Player)?;
+
+        slf.send_room_events(
+            &name,
+            [
+                Event::PlayerReplaced {
+                    player,
+                    replacement,
+                },
+                Event::LobbyStatusChanged {
+                    client: player,
+                    status: LobbyStatus::Spectator,
+                },
+                Event::LobbyStatusChanged {
+                    client: replacement,
+                    status: LobbyStatus::Player,
+                },
+            ],
+        );
+
+        // Both see the game from their new point of view.
+        let room = &slf.rooms[&name];
+        for client in [replacement, player] {
+            slf.clients.send_event(
+                client.into(),
+                Event::SetGame(room.get_game_info_for(client).expect("is active game")),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Opts the client in or out of playing in the next game of their room. Returns an `Err` if
+    /// the client is playing in the room's active game.
+    pub fn set_lobby_status(
+        &self,
+        session_token: SessionToken,
+        status: LobbyStatus,
+    ) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        if slf.get_client_room(client_id).is_playing(client_id) {
+            return Err(MafiaGameError::PlayerInGame);
+        }
+
+        slf.clients.set_lobby_status(client_id, status)?;
+
+        let name = slf.get_client_room_name(client_id);
+        slf.send_room_events(
+            &name,
+            [Event::LobbyStatusChanged {
+                client: client_id,
+                status,
+            }],
+        );
+
+        Ok(())
+    }
+
     pub fn auth_client(&self, session_token: SessionToken) -> Result<ClientId, MafiaGameError> {
         let slf = self.0.read().unwrap();
 
         slf.clients.auth_client(session_token)
     }
 
-    /// Send a message to all clients. Intended as an admin API.
-    pub fn broadcast_message(&self, message: Box<str>) {
+    /// Returns the name of the room the client is in.
+    pub fn client_room(&self, session_token: SessionToken) -> Result<Arc<str>, MafiaGameError> {
         let slf = self.0.read().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        Ok(slf.get_client_room_name(client_id))
+    }
+
+    /// Lists every room, sorted by name.
+    pub fn list_rooms(&self) -> Vec<RoomInfo> {
+        let slf = self.0.read().unwrap();
+
+        let mut rooms = slf.rooms.values().map(Room::get_info).collect::<Vec<_>>();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+
+        rooms
+    }
+
+    /// Handles a client request to create a new room playing with `config`. The client is moved
+    /// into the new room.
+    pub fn create_room(
+        &self,
+        session_token: SessionToken,
+        name: &str,
+        config: GameConfig,
+    ) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(MafiaGameError::InvalidRoomName(
+                "name must not be empty".to_string(),
+            ));
+        }
+
+        if slf.rooms.contains_key(name) {
+            return Err(MafiaGameError::RoomExists(name.to_string()));
+        }
+
+        if slf.get_client_room(client_id).is_playing(client_id) {
+            return Err(MafiaGameError::PlayerInGame);
+        }
+
+        let room = Room::new(Arc::from(name), config);
+        slf.rooms.insert(Arc::clone(&room.name), room);
+
+        slf.move_client(client_id, name)
+    }
+
+    /// Handles a client request to move into another room. Players cannot leave a room while
+    /// its game is in progress.
+    pub fn join_room(&self, session_token: SessionToken, name: &str) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        slf.move_client(client_id, name)
+    }
+
+    /// Handles a client request to go back to the [`LOBBY`].
+    pub fn leave_room(&self, session_token: SessionToken) -> Result<(), MafiaGameError> {
+        self.join_room(session_token, LOBBY)
+    }
+
+    /// Send a message to all clients in every room. Only an admin can broadcast.
+    pub fn broadcast_message(
+        &self,
+        session_token: SessionToken,
+        message: Box<str>,
+    ) -> Result<(), MafiaGameError> {
+        let slf = self.0.read().unwrap();
+
+        slf.check_admin(session_token)?;
 
         let event = Event::MessageReceived(Message {
             channel: EventChannel::Public,
@@ -438,11 +949,13 @@ impl MafiaGameServer {
             from: Entity::System,
         });
 
-        slf.send_event(event);
+        slf.clients.send_event(slf.clients.all_client_ids(), event);
+
+        Ok(())
     }
 
-    /// Handles a client request to send a message to other clients. Messages are routed according
-    /// to the current game state.
+    /// Handles a client request to send a message to the other clients in their room. Messages
+    /// are routed according to the room's game state.
     pub fn send_message(
         &self,
         session_token: SessionToken,
@@ -450,8 +963,9 @@ impl MafiaGameServer {
     ) -> Result<(), MafiaGameError> {
         let slf = self.0.read().unwrap();
         let client_id = slf.clients.auth_client(session_token)?;
+        let room = slf.get_client_room(client_id);
 
-        let channel = if let Ok(game) = slf.get_active_game() {
+        let channel = if let Ok(game) = room.get_active_game() {
             if matches!(
                 game.get_player_status(client_id),
                 Some(PlayerStatus::Dead) | None
@@ -459,7 +973,7 @@ impl MafiaGameServer {
                 EventChannel::Spectator
             }
             // Player is alive
-            else if game.get_cycle() == Cycle::Day {
+            else if game.get_cycle().is_day() {
                 EventChannel::Public
             }
             // Is night
@@ -480,20 +994,73 @@ impl MafiaGameServer {
             from: Entity::Client(client_id),
         });
 
-        slf.send_event(event);
+        slf.send_event(room, event);
 
         Ok(())
     }
 
-    /// Handles a client request to drain all event currently in the client's inbox.
+    /// Handles a client request to take every event in the client's inbox that it has not taken
+    /// yet. Returns an `Err` if some of them were dropped after the client fell too far behind,
+    /// the client is then sent the state of its room to catch up from.
     pub fn take_events(
         &self,
         session_token: SessionToken,
-    ) -> Result<Box<[Arc<Event>]>, MafiaGameError> {
-        let slf = self.0.write().unwrap();
+    ) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
+        let slf = self.0.read().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        slf.resync_if_unavailable(client_id, slf.clients.take_events(client_id))
+    }
+
+    /// Handles a client request to take every event in the client's inbox that it has not taken
+    /// yet, waiting up to `timeout` for one to arrive if there are none. Returns early without
+    /// events if the client disconnects. Returns an `Err` like [`MafiaGameServer::take_events`].
+    pub fn wait_events(
+        &self,
+        session_token: SessionToken,
+        timeout: Duration,
+    ) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
+        // Wait without holding the server lock, so events can be sent in the meantime.
+        let (client_id, cursor) = {
+            let slf = self.0.read().unwrap();
+            let client_id = slf.clients.auth_client(session_token)?;
+
+            (client_id, slf.clients.get_cursor(client_id)?)
+        };
+
+        match cursor.wait_unread(timeout) {
+            Err(e) => self
+                .0
+                .read()
+                .unwrap()
+                .resync_if_unavailable(client_id, Err(e)),
+            events => events,
+        }
+    }
+
+    /// Handles a client request to subscribe to its events. The subscription first gets the
+    /// events the client has not taken yet, then every event sent to the client as it is sent.
+    ///
+    /// The subscription ends when the client disconnects, or if the client falls too far behind.
+    /// Taking events then catches the client up, see [`MafiaGameServer::take_events`].
+    pub fn subscribe(&self, session_token: SessionToken) -> Result<Subscription, MafiaGameError> {
+        let slf = self.0.read().unwrap();
         let client_id = slf.clients.auth_client(session_token)?;
 
-        Ok(slf.clients.take_events(client_id))
+        slf.clients.subscribe(client_id)
+    }
+
+    /// Handles a client request to take every event after the event numbered `seq`, including
+    /// events it already took. Used to resume after reconnecting.
+    pub fn take_events_since(
+        &self,
+        session_token: SessionToken,
+        seq: u64,
+    ) -> Result<Box<[SequencedEvent]>, MafiaGameError> {
+        let slf = self.0.read().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        slf.clients.take_events_since(client_id, seq)
     }
 
     /// Handles a client request to vote in a particular cycle. If `None` is passed, means the
@@ -506,39 +1073,49 @@ impl MafiaGameServer {
         let mut slf = self.0.write().unwrap();
         let client_id = slf.clients.auth_client(session_token)?;
 
-        let game = slf.get_active_game_mut()?;
+        if let Some(target) = target {
+            slf.clients.get_client(target)?;
+        }
+
+        let room = slf.get_client_room_mut(client_id);
+        let name = Arc::clone(&room.name);
+        let game = room.get_active_game_mut()?;
 
         game.cast_vote(client_id, target)?;
 
-        let channel = if game.get_cycle() == Cycle::Day {
-            EventChannel::Public
-        }
-        // Is night
-        else if game.get_player_allegiance(client_id) == Allegiance::Mafia {
-            EventChannel::Mafia
-        }
-        // Only self + spectator can see this vote.
-        else {
-            EventChannel::Spectator
-        };
+        let vote_event = game.vote_event(client_id, target);
+        let events = [vote_event].into_iter().chain(game.poll_end_cycle());
 
-        let events = [Event::VoteIssued {
-            voter: client_id,
-            target,
-            channel,
-        }]
-        .into_iter()
-        .chain(game.poll_end_cycle());
+        slf.send_room_events(&name, events);
 
-        for event in events {
-            slf.send_event(event);
-        }
+        Ok(())
+    }
+
+    /// Handles a client request to vote on the verdict of the current trial. Verdicts are kept
+    /// secret until the trial ends.
+    pub fn cast_verdict(
+        &self,
+        session_token: SessionToken,
+        verdict: Verdict,
+    ) -> Result<(), MafiaGameError> {
+        let mut slf = self.0.write().unwrap();
+        let client_id = slf.clients.auth_client(session_token)?;
+
+        let room = slf.get_client_room_mut(client_id);
+        let name = Arc::clone(&room.name);
+        let game = room.get_active_game_mut()?;
+
+        let events = game.cast_verdict(client_id, verdict)?.poll_end_cycle();
+
+        slf.send_room_events(&name, events);
 
         Ok(())
     }
 
     /// Starts a new background thread for ticking the game state that can be stopped using
-    /// [`TickerShutdown::do_shutdown`].
+    /// [`TickerShutdown::do_shutdown`]. The ticker also saves the server state every
+    /// [`MafiaGameServerConfig::snapshot_interval`] and before stopping, if a snapshot path is
+    /// configured.
     pub fn start_server_ticker(&self, tick_rate: Duration) -> (TickerShutdown, JoinHandle<()>) {
         let server = self.clone();
         let shutdown = TickerShutdown::new();
@@ -547,13 +1124,27 @@ impl MafiaGameServer {
             let shutdown = shutdown.clone();
 
             move || {
+                let mut last_snapshot = server.0.read().unwrap().clock.now();
+
                 loop {
                     if shutdown.is_shutdown() {
+                        server.save_configured_snapshot();
                         return;
                     }
 
                     server.do_tick();
 
+                    let (now, snapshot_interval) = {
+                        let slf = server.0.read().unwrap();
+
+                        (slf.clock.now(), slf.config.snapshot_interval)
+                    };
+
+                    if now.duration_since(last_snapshot).unwrap_or_default() >= snapshot_interval {
+                        server.save_configured_snapshot();
+                        last_snapshot = now;
+                    }
+
                     thread::sleep(tick_rate);
                 }
             }
@@ -575,6 +1166,8 @@ impl TickerShutdown {
         self.0.load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// Stops the ticker after its current tick. Join the ticker's thread to wait for the final
+    /// snapshot to be saved.
     pub fn do_shutdown(&self) {
         self.0.store(true, std::sync::atomic::Ordering::Relaxed);
     }
@@ -583,6 +1176,13 @@ impl TickerShutdown {
 #[cfg(test)]
 mod tests {
     mod client;
+    mod config;
     mod game;
+    mod game_log;
+    mod night;
+    mod role;
+    mod room;
     mod server;
+    mod snapshot;
+    mod win;
 }
@@ -0,0 +1,271 @@
+//! Resolution of night actions.
+//!
+//! Every living player whose role has a [`NightAction`] is queued with the target of their night
+//! vote, then the queue is resolved in ascending priority order. Ties are broken by the action
+//! kind (in the order below), then by the actor's [`ClientId`]. With the default priorities from
+//! [`NightAction::default_priority`], a night resolves as:
+//!
+//! 1. [`NightAction::Block`]: the target's own action is cancelled for the night.
+//! 2. [`NightAction::Redirect`]: any later action aimed at the target is aimed at the actor
+//!    instead.
+//! 3. [`NightAction::Protect`]: the target cannot be killed for the night.
+//! 4. [`NightAction::Kill`]: killers sharing an allegiance vote together, and the target dies if
+//!    a majority of the unblocked killers agree on them. Neutral killers each kill on their own.
+//! 5. [`NightAction::Investigate`]: the actor learns the target's allegiance.
+//!
+//! An action is only affected by actions that resolved before it, e.g. a blocker that is blocked
+//! by an earlier blocker does not block their own target.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+
+use crate::role::NightAction;
+
+/// A night action waiting to be resolved.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) struct QueuedAction {
+    pub(crate) actor: ClientId,
+    /// Allegiance of the actor, used to group killers.
+    pub(crate) allegiance: Allegiance,
+    pub(crate) action: NightAction,
+    /// If `None`, the actor skipped their action.
+    pub(crate) target: Option<ClientId>,
+    pub(crate) priority: u32,
+}
+
+impl QueuedAction {
+    /// Killers in the same group vote together on a single target.
+    fn kill_group(&self) -> (Allegiance, Option<ClientId>) {
+        match self.allegiance {
+            Allegiance::Neutral => (self.allegiance, Some(self.actor)),
+            _ => (self.allegiance, None),
+        }
+    }
+}
+
+/// Result of a single step of night resolution.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum NightOutcome {
+    Blocked {
+        actor: ClientId,
+        target: ClientId,
+    },
+    Redirected {
+        actor: ClientId,
+        from: ClientId,
+    },
+    Protected {
+        actor: ClientId,
+        target: ClientId,
+    },
+    Killed {
+        allegiance: Allegiance,
+        target: ClientId,
+    },
+    /// A kill was stopped by a protection.
+    Saved {
+        allegiance: Allegiance,
+        target: ClientId,
+    },
+    /// Killers of the given allegiance did not agree on a target.
+    FailedKill {
+        allegiance: Allegiance,
+    },
+    Investigated {
+        actor: ClientId,
+        target: ClientId,
+        allegiance: Allegiance,
+    },
+}
+
+/// Outcomes of a resolved night, in resolution order.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub(crate) struct NightReport {
+    pub(crate) outcomes: Vec<NightOutcome>,
+}
+
+#[derive(Default)]
+pub(crate) struct NightQueue {
+    actions: Vec<QueuedAction>,
+}
+
+impl NightQueue {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&mut self, action: QueuedAction) -> &mut Self {
+        self.actions.push(action);
+        self
+    }
+
+    /// Resolves every queued action. `allegiance_of` returns the allegiance of a player, used for
+    /// investigations.
+    pub(crate) fn resolve<F: Fn(ClientId) -> Allegiance>(
+        mut self,
+        allegiance_of: F,
+    ) -> NightReport {
+        self.actions
+            .sort_by_key(|action| (action.priority, action.action, action.actor));
+
+        let mut report = NightReport::default();
+
+        let mut blocked = HashSet::new();
+        // Map of original target -> who actions against them are sent to.
+        let mut redirects = HashMap::new();
+        let mut protected = HashSet::new();
+        let mut killed = HashSet::new();
+
+        let redirect = |redirects: &HashMap<ClientId, ClientId>, target: ClientId| {
+            redirects.get(&target).copied().unwrap_or(target)
+        };
+
+        for group in self
+            .actions
+            .chunk_by(|a, b| a.priority == b.priority && a.action == b.action)
+        {
+            match group[0].action {
+                NightAction::Block => {
+                    for action in group {
+                        if let Some(target) = action.target
+                            && !blocked.contains(&action.actor)
+                        {
+                            tracing::info!("{:?} blocked {:?}", action.actor, target);
+
+                            blocked.insert(target);
+                            report.outcomes.push(NightOutcome::Blocked {
+                                actor: action.actor,
+                                target,
+                            });
+                        }
+                    }
+                }
+                NightAction::Redirect => {
+                    for action in group {
+                        if let Some(target) = action.target
+                            && !blocked.contains(&action.actor)
+                        {
+                            tracing::info!(
+                                "actions against {:?} redirected to {:?}",
+                                target,
+                                action.actor
+                            );
+
+                            redirects.insert(target, action.actor);
+                            report.outcomes.push(NightOutcome::Redirected {
+                                actor: action.actor,
+                                from: target,
+                            });
+                        }
+                    }
+                }
+                NightAction::Protect => {
+                    for action in group {
+                        if let Some(target) = action.target
+                            && !blocked.contains(&action.actor)
+                        {
+                            let target = redirect(&redirects, target);
+
+                            tracing::info!("{:?} protected {:?}", action.actor, target);
+
+                            protected.insert(target);
+                            report.outcomes.push(NightOutcome::Protected {
+                                actor: action.actor,
+                                target,
+                            });
+                        }
+                    }
+                }
+                NightAction::Kill => {
+                    let mut kill_groups = group
+                        .iter()
+                        .map(QueuedAction::kill_group)
+                        .collect::<Vec<_>>();
+                    // Sort for determinism.
+                    kill_groups.sort();
+                    kill_groups.dedup();
+
+                    for kill_group in kill_groups {
+                        let (allegiance, _) = kill_group;
+                        // Blocked killers neither vote nor count towards the majority.
+                        let killers = group
+                            .iter()
+                            .filter(|action| action.kill_group() == kill_group)
+                            .filter(|action| !blocked.contains(&action.actor))
+                            .collect::<Vec<_>>();
+
+                        let num_votes_for_player =
+                            killers.iter().fold(HashMap::new(), |mut acc, action| {
+                                if let Some(target) = action.target {
+                                    *acc.entry(target).or_insert(0) += 1;
+                                }
+                                acc
+                            });
+
+                        let Some((voted_player, _)) = num_votes_for_player
+                            .into_iter()
+                            .find(|(_, count)| count * 2 > killers.len())
+                        else {
+                            report
+                                .outcomes
+                                .push(NightOutcome::FailedKill { allegiance });
+                            continue;
+                        };
+
+                        let target = redirect(&redirects, voted_player);
+
+                        if protected.contains(&target) {
+                            tracing::info!(
+                                "{:?} was protected from a {:?} kill in the night",
+                                target,
+                                allegiance
+                            );
+
+                            report
+                                .outcomes
+                                .push(NightOutcome::Saved { allegiance, target });
+                        } else if killed.insert(target) {
+                            tracing::info!(
+                                "{:?} was killed by the {:?} in the night",
+                                target,
+                                allegiance
+                            );
+
+                            report
+                                .outcomes
+                                .push(NightOutcome::Killed { allegiance, target });
+                        }
+                    }
+                }
+                NightAction::Investigate => {
+                    for action in group {
+                        if let Some(target) = action.target
+                            && !blocked.contains(&action.actor)
+                        {
+                            let target = redirect(&redirects, target);
+                            let allegiance = allegiance_of(target);
+
+                            tracing::info!(
+                                "{:?} was investigated by {:?} and discovered to be {:?}",
+                                target,
+                                action.actor,
+                                allegiance
+                            );
+
+                            report.outcomes.push(NightOutcome::Investigated {
+                                actor: action.actor,
+                                target,
+                                allegiance,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        report
+    }
+}
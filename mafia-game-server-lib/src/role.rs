@@ -0,0 +1,255 @@
+//! Behavior for the special roles a player can hold.
+//!
+//! The engine never matches on [`SpecialRole`] directly, instead it looks up the [`Role`]
+//! registered for it in a [`RoleRegistry`]. House roles can be added by implementing [`Role`] with
+//! a [`SpecialRole::Custom`] identifier, and registering it in
+//! [`GameConfig::roles`](crate::game::GameConfig::roles).
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+use mafia_game_lib::SpecialRole;
+
+use crate::win::WinContext;
+
+/// Action a role performs on their vote target during the night.
+///
+/// Actions are resolved in priority order, see the `night` module for how they interact.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum NightAction {
+    /// Cancels the target's own action for the night.
+    Block,
+    /// Sends every later action aimed at the target to the actor instead, e.g. a bodyguard.
+    Redirect,
+    /// Protects the target from being killed.
+    Protect,
+    /// Votes alongside every other killer of the same allegiance on a player to kill. The target
+    /// dies if a majority of those living, unblocked killers agree on them. Neutral killers act
+    /// alone.
+    Kill,
+    /// Learns the allegiance of the target.
+    Investigate,
+}
+
+impl NightAction {
+    /// Priority this action resolves at unless a role overrides it. Lower priorities resolve
+    /// first.
+    pub fn default_priority(self) -> u32 {
+        match self {
+            NightAction::Block => 100,
+            NightAction::Redirect => 200,
+            NightAction::Protect => 300,
+            NightAction::Kill => 400,
+            NightAction::Investigate => 500,
+        }
+    }
+}
+
+/// Behavior of a special role.
+pub trait Role: Debug + Send + Sync {
+    /// Identifier of this role, as shown to clients. House roles use [`SpecialRole::Custom`].
+    fn special_role(&self) -> SpecialRole;
+
+    /// Which side a holder of this role is on.
+    fn allegiance(&self) -> Allegiance;
+
+    /// Action a holder of this role performs on their night vote target, if any.
+    fn night_action(&self) -> Option<NightAction>;
+
+    /// Priority a holder's night action resolves at, lower priorities resolve first.
+    fn night_priority(&self) -> u32 {
+        self.night_action()
+            .map_or(u32::MAX, NightAction::default_priority)
+    }
+
+    /// Whether a holder of this role votes during the night.
+    fn votes_at_night(&self) -> bool {
+        self.night_action().is_some()
+    }
+
+    /// Whether a living holder of this role can see which players hold `other`. Players can always
+    /// see their own role.
+    fn can_see_role(&self, _other: &dyn Role) -> bool {
+        false
+    }
+
+    /// Whether a living holder of this role can see night votes cast by holders of `other`.
+    /// Players can always see their own vote.
+    fn can_see_night_vote(&self, _other: &dyn Role) -> bool {
+        false
+    }
+
+    /// Whether `player`, who holds this role, met their own win condition. Only checked for
+    /// [`Allegiance::Neutral`] roles, other roles win with their faction.
+    fn has_won(&self, _player: ClientId, _ctx: &WinContext) -> bool {
+        false
+    }
+}
+
+/// Kills one player each night alongside the rest of the Mafia.
+#[derive(Copy, Clone, Debug)]
+pub struct Mafia;
+
+impl Role for Mafia {
+    fn special_role(&self) -> SpecialRole {
+        SpecialRole::Mafia
+    }
+
+    fn allegiance(&self) -> Allegiance {
+        Allegiance::Mafia
+    }
+
+    fn night_action(&self) -> Option<NightAction> {
+        Some(NightAction::Kill)
+    }
+
+    fn can_see_role(&self, other: &dyn Role) -> bool {
+        other.allegiance() == Allegiance::Mafia
+    }
+
+    fn can_see_night_vote(&self, other: &dyn Role) -> bool {
+        other.allegiance() == Allegiance::Mafia
+    }
+}
+
+/// Protects one player from Mafia death each night.
+#[derive(Copy, Clone, Debug)]
+pub struct Doctor;
+
+impl Role for Doctor {
+    fn special_role(&self) -> SpecialRole {
+        SpecialRole::Doctor
+    }
+
+    fn allegiance(&self) -> Allegiance {
+        Allegiance::Villagers
+    }
+
+    fn night_action(&self) -> Option<NightAction> {
+        Some(NightAction::Protect)
+    }
+}
+
+/// Investigates the allegiance of one player each night.
+#[derive(Copy, Clone, Debug)]
+pub struct Detective;
+
+impl Role for Detective {
+    fn special_role(&self) -> SpecialRole {
+        SpecialRole::Detective
+    }
+
+    fn allegiance(&self) -> Allegiance {
+        Allegiance::Villagers
+    }
+
+    fn night_action(&self) -> Option<NightAction> {
+        Some(NightAction::Investigate)
+    }
+}
+
+/// Neutral role that wins if lynched.
+#[derive(Copy, Clone, Debug)]
+pub struct Jester;
+
+impl Role for Jester {
+    fn special_role(&self) -> SpecialRole {
+        SpecialRole::Jester
+    }
+
+    fn allegiance(&self) -> Allegiance {
+        Allegiance::Neutral
+    }
+
+    fn night_action(&self) -> Option<NightAction> {
+        None
+    }
+
+    fn has_won(&self, player: ClientId, ctx: &WinContext) -> bool {
+        ctx.lynched == Some(player)
+    }
+}
+
+/// Neutral role that kills one player each night, and wins if they are the last one standing.
+#[derive(Copy, Clone, Debug)]
+pub struct SerialKiller;
+
+impl Role for SerialKiller {
+    fn special_role(&self) -> SpecialRole {
+        SpecialRole::SerialKiller
+    }
+
+    fn allegiance(&self) -> Allegiance {
+        Allegiance::Neutral
+    }
+
+    fn night_action(&self) -> Option<NightAction> {
+        Some(NightAction::Kill)
+    }
+
+    fn has_won(&self, player: ClientId, ctx: &WinContext) -> bool {
+        matches!(ctx.alive, [last] if last.id == player)
+    }
+}
+
+/// Maps each [`SpecialRole`] to the [`Role`] implementing it.
+///
+/// The default registry contains every built-in role.
+#[derive(Clone, Debug)]
+pub struct RoleRegistry(HashMap<SpecialRole, Arc<dyn Role>>);
+
+impl RoleRegistry {
+    /// Returns a registry without any roles.
+    pub fn empty() -> Self {
+        RoleRegistry(HashMap::new())
+    }
+
+    /// Registers `role`, replacing any role previously registered for the same [`SpecialRole`].
+    pub fn register<R: Role + 'static>(&mut self, role: R) -> &mut Self {
+        self.0.insert(role.special_role(), Arc::new(role));
+        self
+    }
+
+    pub fn get(&self, role: &SpecialRole) -> Option<&dyn Role> {
+        self.0.get(role).map(|v| v.as_ref())
+    }
+
+    /// Returns the identifier of every registered role, sorted.
+    pub fn ids(&self) -> Vec<SpecialRole> {
+        let mut ids = self.0.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+
+        ids
+    }
+
+    /// Returns a registry with only the roles in `ids`, or the first of `ids` not registered here.
+    pub fn select(&self, ids: &[SpecialRole]) -> Result<RoleRegistry, SpecialRole> {
+        ids.iter()
+            .map(|id| {
+                self.0
+                    .get(id)
+                    .map(|role| (id.clone(), Arc::clone(role)))
+                    .ok_or_else(|| id.clone())
+            })
+            .collect::<Result<_, _>>()
+            .map(RoleRegistry)
+    }
+}
+
+impl Default for RoleRegistry {
+    fn default() -> Self {
+        let mut registry = RoleRegistry::empty();
+
+        registry
+            .register(Mafia)
+            .register(Doctor)
+            .register(Detective)
+            .register(Jester)
+            .register(SerialKiller);
+
+        registry
+    }
+}
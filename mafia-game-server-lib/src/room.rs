@@ -0,0 +1,313 @@
+//! Rooms group clients together, each room plays its own game and has its own chat.
+//!
+//! Every client is in exactly one room. Clients start in the [`LOBBY`], which always exists, and
+//! can move between rooms when they are not playing in a game.
+//!
+//! Each room has a host, who starts and ends its games. The client that created a room is its
+//! host, the lobby's host is the first client in it. When the host leaves, the member with the
+//! lowest ID takes over.
+//!
+//! If the server sets a minimum number of ready players, games also start on their own: once
+//! enough players in a room are ready a countdown starts, and the game starts when it runs out.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+use mafia_game_lib::Cycle;
+use mafia_game_lib::Entity;
+use mafia_game_lib::Event;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::GameInfo;
+use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::RoleReveal;
+use mafia_game_lib::RoomInfo;
+use serde::Deserialize;
+use serde::Serialize;
+use tap::Tap;
+
+use crate::client::ClientSet;
+use crate::error::MafiaGameError;
+use crate::game::Game;
+use crate::game::GameConfig;
+use crate::game::is_alive;
+
+/// Name of the room clients are put in when they connect.
+pub const LOBBY: &str = "lobby";
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Room {
+    pub(crate) name: Arc<str>,
+    pub(crate) members: ClientSet,
+    /// Client allowed to start and end games in this room. Only `None` while the room is empty.
+    pub(crate) host: Option<ClientId>,
+    /// Settings for games started in this room.
+    pub(crate) config: GameConfig,
+    pub(crate) active_game: Option<Game>,
+    /// Time the next game starts on its own, set while enough players are ready.
+    pub(crate) countdown_end: Option<SystemTime>,
+}
+
+impl Room {
+    pub(crate) fn new(name: Arc<str>, config: GameConfig) -> Self {
+        Room {
+            name,
+            members: ClientSet::new(),
+            host: None,
+            config,
+            active_game: None,
+            countdown_end: None,
+        }
+    }
+
+    pub(crate) fn get_info(&self) -> RoomInfo {
+        RoomInfo {
+            name: Arc::clone(&self.name),
+            num_clients: self.members.count(),
+            in_game: self.in_active_game(),
+        }
+    }
+
+    pub(crate) fn get_active_game(&self) -> Result<&Game, MafiaGameError> {
+        let Some(game) = self.active_game.as_ref() else {
+            return Err(MafiaGameError::NoGameInProgress);
+        };
+
+        if game.is_over() {
+            return Err(MafiaGameError::NoGameInProgress);
+        }
+
+        Ok(game)
+    }
+
+    pub(crate) fn get_active_game_mut(&mut self) -> Result<&mut Game, MafiaGameError> {
+        let Some(game) = self.active_game.as_mut() else {
+            return Err(MafiaGameError::NoGameInProgress);
+        };
+
+        if game.is_over() {
+            return Err(MafiaGameError::NoGameInProgress);
+        }
+
+        Ok(game)
+    }
+
+    pub(crate) fn in_active_game(&self) -> bool {
+        self.get_active_game().is_ok()
+    }
+
+    /// Returns `true` if `client_id` is a player in this room's active game, dead or alive.
+    pub(crate) fn is_playing(&self, client_id: ClientId) -> bool {
+        self.get_active_game()
+            .is_ok_and(|game| game.get_player_status(client_id).is_some())
+    }
+
+    fn get_clients_for_channel(&self, actor: Option<ClientId>, channel: EventChannel) -> ClientSet {
+        let all_clients = self.members.clone();
+
+        match channel {
+            EventChannel::Public => all_clients,
+            EventChannel::Mafia => {
+                if let Some(game) = self.active_game.as_ref() {
+                    all_clients.tap_mut(|s| {
+                        s.difference_with(&game.get_players(|status, _, allegiance| {
+                            status == PlayerStatus::Alive && allegiance != Allegiance::Mafia
+                        }));
+                    })
+                } else {
+                    ClientSet::new()
+                }
+            }
+            EventChannel::Spectator => {
+                if let Some(game) = self.active_game.as_ref() {
+                    all_clients.tap_mut(|s| {
+                        s.difference_with(&game.get_players(is_alive));
+                    })
+                } else {
+                    all_clients
+                }
+            }
+        }
+        .tap_mut(|s| {
+            // Sender can always see their own messages.
+            if let Some(client_id) = actor {
+                s.insert(client_id);
+            }
+        })
+    }
+
+    /// Returns a set of clients in this room eligible to see the given event.
+    pub(crate) fn get_event_visibility(&self, event: &Event) -> ClientSet {
+        match event {
+            // These events should have their contents tailed to the recipient, hence should not be
+            // called in this function.
+            Event::SetServerInfo(_) | Event::SetGame(_) => {
+                unreachable!("should not be called with `get_event_visibility`")
+            }
+            Event::EndGame => self.members.clone(),
+            Event::ClientConnected(info) => self.members.clone().tap_mut(|s| {
+                s.remove(info.id);
+            }),
+            Event::ClientDisconnected(client_id) => self.members.clone().tap_mut(|s| {
+                s.remove(*client_id);
+            }),
+            Event::HostChanged(_) => self.members.clone(),
+            Event::LobbyStatusChanged {
+                client: _,
+                status: _,
+            } => self.members.clone(),
+            Event::ReadyChanged {
+                client: _,
+                ready: _,
+            } => self.members.clone(),
+            Event::CountdownStarted {
+                start_time_unix_ts_secs: _,
+                duration_secs: _,
+            } => self.members.clone(),
+            Event::CountdownCancelled => self.members.clone(),
+            Event::PlayerReplaced {
+                player: _,
+                replacement: _,
+            } => self.members.clone(),
+            Event::MessageReceived(message) => match message.from {
+                Entity::Client(client_id) => {
+                    self.get_clients_for_channel(Some(client_id), message.channel)
+                }
+                Entity::System => self.members.clone(),
+            },
+            Event::VoteIssued {
+                voter,
+                target: _,
+                channel,
+            } => self.get_clients_for_channel(Some(*voter), *channel),
+            Event::PlayerNominated {
+                voter: _,
+                accused: _,
+            } => self.members.clone(),
+            Event::TrialStarted { accused: _ } => self.members.clone(),
+            Event::TrialVerdict {
+                accused: _,
+                verdict: _,
+                votes: _,
+            } => self.members.clone(),
+            Event::FailedVote { cycle: _, channel } => self.get_clients_for_channel(None, *channel),
+            Event::PlayerKilled {
+                player: _,
+                cycle: _,
+                death_message: _,
+                reveal: _,
+                cause: _,
+            } => self.members.clone(),
+            Event::SetCycle {
+                start_time_unix_ts_secs: _,
+                duration_secs: _,
+                cycle: _,
+                day_num: _,
+            } => self.members.clone(),
+            Event::PlayerInvestigated {
+                actor,
+                target: _,
+                allegiance: _,
+            } => self.get_clients_for_channel(Some(*actor), EventChannel::Spectator),
+            Event::GameWon {
+                player_to_role: _,
+                winners: _,
+            } => self.members.clone(),
+            Event::GameDrawn {
+                player_to_role: _,
+                reason: _,
+            } => self.members.clone(),
+        }
+    }
+
+    pub(crate) fn get_game_info_for(&self, client: ClientId) -> Option<GameInfo> {
+        let game = self.active_game.as_ref()?;
+
+        let mut game_info = GameInfo {
+            cycle_start_time_unix_ts_secs: game.get_cycle_start_unix_ts_secs(),
+            cycle_duration_secs: game.get_cycle_duration().as_secs(),
+            current_cycle: game.get_cycle(),
+            day_num: game.get_day_num(),
+            player_status: game.get_player_statuses().clone(),
+            winner: game.get_winner().cloned(),
+            draw: game.get_draw(),
+            player_to_role: HashMap::new(),
+            votes: HashMap::new(),
+            accused: game.get_accused(),
+            death_reveals: game.get_death_reveals(),
+        };
+
+        let status = game.get_player_status(client);
+        let role = game.get_player_role_impl(client);
+        let cycle = game.get_cycle();
+
+        match (status, role, cycle) {
+            // Spectator or dead person can see everything.
+            (None | Some(PlayerStatus::Dead), _, _) => {
+                game_info.votes = game.get_votes().clone();
+            }
+            // Everyone can see votes during the day.
+            (Some(PlayerStatus::Alive), _, Cycle::Day | Cycle::Defense | Cycle::Verdict) => {
+                game_info.votes = game.get_votes().clone();
+            }
+            // Special roles can see their own votes in the night, plus whichever other votes
+            // their role allows (e.g. Mafia can see every other mafia's vote).
+            (Some(PlayerStatus::Alive), Some(role), Cycle::Night) => {
+                game_info.votes = game
+                    .get_votes()
+                    .iter()
+                    .filter_map(|(&k, &v)| {
+                        if k == client
+                            || game
+                                .get_player_role_impl(k)
+                                .is_some_and(|other| role.can_see_night_vote(other))
+                        {
+                            Some((k, v))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+            }
+            // Villagers without roles cannot see any votes in the night.
+            (Some(PlayerStatus::Alive), None, Cycle::Night) => {}
+        }
+
+        match (status, role) {
+            // Spectator or dead person can see everything.
+            (None | Some(PlayerStatus::Dead), _) => {
+                game_info.player_to_role = game.get_player_roles().clone();
+            }
+            // Special roles can see their own role, plus whichever other roles their role allows.
+            (Some(PlayerStatus::Alive), Some(role)) => {
+                game_info.player_to_role = game
+                    .get_player_roles()
+                    .iter()
+                    .filter_map(|(&k, v)| {
+                        if k == client || role.can_see_role(game.get_role_impl(v)) {
+                            Some((k, v.clone()))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+            }
+            (Some(PlayerStatus::Alive), None) => {}
+        }
+
+        // Roles revealed on death are visible to everyone.
+        for (&client_id, reveal) in &game_info.death_reveals {
+            if let RoleReveal::Role {
+                allegiance: _,
+                role: Some(role),
+            } = reveal
+            {
+                game_info.player_to_role.insert(client_id, role.clone());
+            }
+        }
+
+        Some(game_info)
+    }
+}
@@ -1,17 +1,39 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::TryRecvError;
+use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 
+use crate::client::ClientSet;
 use crate::client::ClientState;
+use crate::clock::MockClock;
 use crate::error::MafiaGameError;
+use crate::event_log::EventCursor;
+use crate::event_log::EventLog;
+use crate::event_log::MAX_RETAINED_EVENTS;
+use mafia_game_lib::ClientId;
 use mafia_game_lib::Entity;
 use mafia_game_lib::Event;
 use mafia_game_lib::EventChannel;
 use mafia_game_lib::Message;
+use mafia_game_lib::SequencedEvent;
+
+/// Takes the unread events of `client_id`, without their sequence numbers.
+fn take_events(client_state: &ClientState, client_id: ClientId) -> Box<[Arc<Event>]> {
+    client_state
+        .take_events(client_id)
+        .unwrap()
+        .iter()
+        .map(|event| Arc::clone(&event.event))
+        .collect()
+}
 
 #[test]
 pub fn test_client_registration() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, client1_session_token) = client_state.connect_client("hello").unwrap();
     let (client2_id, client2_session_token) = client_state.connect_client("world").unwrap();
@@ -107,7 +129,11 @@ pub fn test_client_registration() {
         );
     }
 
-    client_state.purge_disconnected_clients(Duration::from_secs(10));
+    client_state.purge_disconnected_clients(
+        Duration::from_secs(10),
+        Duration::ZERO,
+        &ClientSet::new(),
+    );
 
     {
         let clients = client_state.list_clients();
@@ -122,7 +148,11 @@ pub fn test_client_registration() {
     }
 
     assert!(client_state.disconnect_client(client1_id).is_ok());
-    client_state.purge_disconnected_clients(Duration::from_secs(10));
+    client_state.purge_disconnected_clients(
+        Duration::from_secs(10),
+        Duration::ZERO,
+        &ClientSet::new(),
+    );
 
     {
         let clients = client_state.list_clients();
@@ -135,7 +165,11 @@ pub fn test_client_registration() {
 
     assert!(client_state.disconnect_client(client1_id).is_err());
     assert!(client_state.disconnect_client(client2_id).is_ok());
-    client_state.purge_disconnected_clients(Duration::from_secs(10));
+    client_state.purge_disconnected_clients(
+        Duration::from_secs(10),
+        Duration::ZERO,
+        &ClientSet::new(),
+    );
 
     {
         let clients = client_state.list_clients();
@@ -144,9 +178,115 @@ pub fn test_client_registration() {
     }
 }
 
+#[test]
+fn test_client_id_allocation() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    // Well past the number of clients a game can reasonably hold.
+    let clients = (0..200)
+        .map(|i| {
+            client_state
+                .connect_client(&format!("client-{}", i))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(client_state.all_client_ids().count(), 200);
+
+    // Purged clients free up their IDs for new clients.
+    for (client_id, _) in [clients[10], clients[150]] {
+        client_state.disconnect_client(client_id).unwrap();
+    }
+    client_state.purge_disconnected_clients(
+        Duration::from_secs(10),
+        Duration::ZERO,
+        &ClientSet::new(),
+    );
+
+    // Their slots are handed out again, but under new IDs.
+    let (reused1, _) = client_state.connect_client("steven").unwrap();
+    let (reused2, _) = client_state.connect_client("connie").unwrap();
+    let mut reused = [reused1, reused2].map(ClientId::slot);
+    reused.sort();
+    assert_eq!(reused, [clients[10].0.slot(), clients[150].0.slot()]);
+    assert!(
+        !clients
+            .iter()
+            .any(|(client_id, _)| [reused1, reused2].contains(client_id))
+    );
+
+    // Stale IDs do not refer to the clients that took their slots.
+    assert!(matches!(
+        client_state.get_client(clients[10].0),
+        Err(MafiaGameError::InvalidClientId(_))
+    ));
+    assert!(matches!(
+        client_state.disconnect_client(clients[150].0),
+        Err(MafiaGameError::InvalidClientId(_))
+    ));
+    assert!(!client_state.all_client_ids().contains(clients[10].0));
+
+    let (new_id, _) = client_state.connect_client("lars").unwrap();
+    assert_eq!(new_id, ClientId::new(200, 0));
+
+    // Restored clients keep their IDs, and slots of purged clients stay free with their
+    // generations intact.
+    client_state.disconnect_client(clients[20].0).unwrap();
+    client_state.purge_disconnected_clients(
+        Duration::from_secs(10),
+        Duration::ZERO,
+        &ClientSet::new(),
+    );
+
+    let mut client_state = ClientState::restore(client_state.snapshot(), clock.clone());
+    assert_eq!(client_state.all_client_ids().count(), 200);
+    assert!(client_state.get_client(reused1).is_ok());
+
+    let (reused, _) = client_state.connect_client("sadie").unwrap();
+    assert_eq!(reused, ClientId::new(clients[20].0.slot(), 1));
+
+    let (new_id, _) = client_state.connect_client("onion").unwrap();
+    assert_eq!(new_id, ClientId::new(201, 0));
+}
+
+#[test]
+fn test_client_inactivity() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, client1_session_token) = client_state.connect_client("hello").unwrap();
+    let (client2_id, _) = client_state.connect_client("world").unwrap();
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(
+        client_state.auth_client(client1_session_token).unwrap(),
+        client1_id
+    );
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(
+        client_state.purge_disconnected_clients(
+            Duration::from_secs(10),
+            Duration::ZERO,
+            &ClientSet::new()
+        ),
+        vec![client2_id]
+    );
+
+    {
+        let clients = client_state.list_clients();
+
+        assert_eq!(
+            *clients,
+            HashMap::from_iter([(Arc::from("hello"), client1_id)])
+        );
+    }
+}
+
 #[test]
 fn test_messages() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, _) = client_state.connect_client("hello").unwrap();
     let (client2_id, _) = client_state.connect_client("world").unwrap();
@@ -170,7 +310,7 @@ fn test_messages() {
     );
 
     assert_eq!(
-        client_state.take_events(client1_id),
+        take_events(&client_state, client1_id),
         [Message {
             channel: EventChannel::Public,
             contents: Box::from("hello world"),
@@ -182,7 +322,7 @@ fn test_messages() {
     );
 
     assert_eq!(
-        client_state.take_events(client2_id),
+        take_events(&client_state, client2_id),
         [
             Message {
                 channel: EventChannel::Public,
@@ -200,8 +340,8 @@ fn test_messages() {
         .collect()
     );
 
-    assert_eq!(client_state.take_events(client1_id), Box::from([]));
-    assert_eq!(client_state.take_events(client2_id), Box::from([]));
+    assert_eq!(take_events(&client_state, client1_id), Box::from([]));
+    assert_eq!(take_events(&client_state, client2_id), Box::from([]));
 
     client_state.send_event(
         [client1_id, client2_id].into_iter().collect(),
@@ -222,7 +362,7 @@ fn test_messages() {
     );
 
     assert_eq!(
-        client_state.take_events(client1_id),
+        take_events(&client_state, client1_id),
         [
             Message {
                 channel: EventChannel::Public,
@@ -241,7 +381,7 @@ fn test_messages() {
     );
 
     assert_eq!(
-        client_state.take_events(client2_id),
+        take_events(&client_state, client2_id),
         [Message {
             channel: EventChannel::Public,
             contents: Box::from("foobar"),
@@ -252,3 +392,301 @@ fn test_messages() {
         .collect()
     );
 }
+
+#[test]
+fn test_event_resume() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("hello").unwrap();
+
+    let message = |contents: &str| Message {
+        channel: EventChannel::Public,
+        contents: Box::from(contents),
+        from: Entity::System,
+    };
+
+    client_state.send_event(client1_id.into(), message("one"));
+    client_state.send_event(client1_id.into(), message("two"));
+
+    let events = client_state.take_events(client1_id).unwrap();
+    assert_eq!(
+        events.iter().map(|event| event.seq).collect::<Vec<_>>(),
+        [1, 2]
+    );
+
+    // Events sent while disconnected are kept.
+    client_state.disconnect_client(client1_id).unwrap();
+    client_state.send_event(client1_id.into(), message("three"));
+    clock.advance(Duration::from_secs(5));
+    client_state.purge_disconnected_clients(
+        Duration::from_secs(10),
+        Duration::from_secs(10),
+        &ClientSet::new(),
+    );
+
+    let (tmp_id, _) = client_state.connect_client("hello").unwrap();
+    assert_eq!(tmp_id, client1_id);
+
+    let events = client_state.take_events_since(client1_id, 1).unwrap();
+    assert_eq!(
+        events.iter().map(|event| event.seq).collect::<Vec<_>>(),
+        [2, 3]
+    );
+    assert_eq!(*events[1].event, Event::MessageReceived(message("three")));
+
+    // Taken events are not taken again, but can be resumed from.
+    assert_eq!(take_events(&client_state, client1_id), Box::from([]));
+    assert_eq!(
+        client_state.take_events_since(client1_id, 3).unwrap().len(),
+        0
+    );
+    assert!(matches!(
+        client_state.take_events_since(client1_id, 4),
+        Err(MafiaGameError::EventsUnavailable(4))
+    ));
+
+    // Only the latest events taken are retained.
+    for _ in 0..MAX_RETAINED_EVENTS {
+        client_state.send_event(client1_id.into(), message("spam"));
+    }
+
+    assert_eq!(
+        take_events(&client_state, client1_id).len(),
+        MAX_RETAINED_EVENTS
+    );
+
+    assert!(matches!(
+        client_state.take_events_since(client1_id, 2),
+        Err(MafiaGameError::EventsUnavailable(2))
+    ));
+    assert_eq!(
+        client_state.take_events_since(client1_id, 3).unwrap().len(),
+        MAX_RETAINED_EVENTS
+    );
+}
+
+#[test]
+fn test_shared_event_log() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("hello").unwrap();
+    let (client2_id, _) = client_state.connect_client("world").unwrap();
+
+    let message = |contents: &str| Message {
+        channel: EventChannel::Public,
+        contents: Box::from(contents),
+        from: Entity::System,
+    };
+
+    client_state.send_event(
+        ClientSet::from_iter([client1_id, client2_id]),
+        message("one"),
+    );
+    client_state.send_event(client2_id.into(), message("two"));
+    client_state.send_event(client1_id.into(), message("three"));
+
+    // Each client numbers the events it gets on its own.
+    let seqs =
+        |events: Box<[SequencedEvent]>| events.iter().map(|event| event.seq).collect::<Vec<_>>();
+    assert_eq!(seqs(client_state.take_events(client1_id).unwrap()), [1, 2]);
+    assert_eq!(seqs(client_state.take_events(client2_id).unwrap()), [1, 2]);
+
+    assert_eq!(
+        *client_state.take_events_since(client1_id, 1).unwrap()[0].event,
+        Event::MessageReceived(message("three"))
+    );
+
+    // Clients that connect later do not see earlier events.
+    let (client3_id, _) = client_state.connect_client("steven").unwrap();
+    assert!(client_state.take_events(client3_id).unwrap().is_empty());
+    assert!(
+        client_state
+            .take_events_since(client3_id, 0)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_restore_cursor_ahead_of_log() {
+    let message = |contents: &str| {
+        Arc::new(Event::MessageReceived(Message {
+            channel: EventChannel::Public,
+            contents: Box::from(contents),
+            from: Entity::System,
+        }))
+    };
+    let contents = |events: &[SequencedEvent]| {
+        events
+            .iter()
+            .map(|event| match &*event.event {
+                Event::MessageReceived(message) => (event.seq, message.contents.to_string()),
+                _ => panic!("unexpected event"),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let client_id = ClientId(0);
+    let log = Arc::new(EventLog::new());
+    let cursor = EventCursor::new(&log, client_id);
+
+    log.push(client_id.into(), message("one"));
+
+    // An event is sent and taken between saving the log and saving the cursor.
+    let entries = log.snapshot();
+    log.push(client_id.into(), message("two"));
+    assert_eq!(
+        contents(&cursor.take_unread().unwrap()),
+        [(1, "one".to_string()), (2, "two".to_string())]
+    );
+    let state = cursor.snapshot();
+
+    let log = Arc::new(EventLog::restore(entries));
+    let cursor = EventCursor::restore(&log, client_id, state);
+
+    // The next event takes the position of the one missing from the saved log.
+    log.push(client_id.into(), message("three"));
+    assert_eq!(
+        contents(&cursor.take_unread().unwrap()),
+        [(3, "three".to_string())]
+    );
+}
+
+#[test]
+fn test_wait_events() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("hello").unwrap();
+    let cursor = client_state.get_cursor(client1_id).unwrap();
+
+    // Times out without events.
+    assert_eq!(
+        cursor.wait_unread(Duration::from_millis(10)).unwrap().len(),
+        0
+    );
+
+    // Wakes up as soon as an event is sent.
+    let waiter = thread::spawn({
+        let cursor = Arc::clone(&cursor);
+
+        move || {
+            let start = Instant::now();
+            let events = cursor.wait_unread(Duration::from_secs(30)).unwrap();
+
+            (events, start.elapsed())
+        }
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    client_state.send_event(
+        client1_id.into(),
+        Message {
+            channel: EventChannel::Public,
+            contents: Box::from("wake up"),
+            from: Entity::System,
+        },
+    );
+
+    let (events, elapsed) = waiter.join().unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(elapsed < Duration::from_secs(30));
+
+    // Keeps waiting through events sent to other clients.
+    let (client2_id, _) = client_state.connect_client("world").unwrap();
+    let waiter = thread::spawn({
+        let cursor = Arc::clone(&cursor);
+
+        move || cursor.wait_unread(Duration::from_secs(30)).unwrap()
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    for (to, contents) in [(client2_id, "not yet"), (client1_id, "now")] {
+        client_state.send_event(
+            to.into(),
+            Message {
+                channel: EventChannel::Public,
+                contents: Box::from(contents),
+                from: Entity::System,
+            },
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let events = waiter.join().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        *events[0].event,
+        Event::MessageReceived(Message {
+            channel: EventChannel::Public,
+            contents: Box::from("now"),
+            from: Entity::System,
+        })
+    );
+
+    // Wakes up when the client disconnects.
+    let waiter = thread::spawn({
+        let cursor = Arc::clone(&cursor);
+
+        move || cursor.wait_unread(Duration::from_secs(30)).unwrap()
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    client_state.disconnect_client(client1_id).unwrap();
+
+    assert_eq!(waiter.join().unwrap().len(), 0);
+}
+
+#[test]
+fn test_subscribe() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("hello").unwrap();
+    let (client2_id, _) = client_state.connect_client("world").unwrap();
+
+    let message = |contents: &str| Message {
+        channel: EventChannel::Public,
+        contents: Box::from(contents),
+        from: Entity::System,
+    };
+
+    // Events not taken yet are sent first.
+    client_state.send_event(client1_id.into(), message("before"));
+
+    let events = client_state.subscribe(client1_id).unwrap();
+    client_state.send_event(
+        ClientSet::from_iter([client1_id, client2_id]),
+        message("after"),
+    );
+
+    let received = std::iter::from_fn(|| events.try_recv().ok()).collect::<Vec<_>>();
+    assert_eq!(
+        received.iter().map(|event| event.seq).collect::<Vec<_>>(),
+        [1, 2]
+    );
+    assert_eq!(*received[1].event, Event::MessageReceived(message("after")));
+
+    // Only recipients of the event get it, and subscribed events count as taken.
+    assert_eq!(take_events(&client_state, client1_id).len(), 0);
+    assert_eq!(take_events(&client_state, client2_id).len(), 1);
+
+    // Events not received by a subscription are left unread.
+    client_state.send_event(client1_id.into(), message("dropped"));
+    drop(events);
+    assert_eq!(take_events(&client_state, client1_id).len(), 1);
+
+    // Disconnecting ends the subscription, even once the client reconnects.
+    let events = client_state.subscribe(client1_id).unwrap();
+    client_state.disconnect_client(client1_id).unwrap();
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(30)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+
+    client_state.connect_client("hello").unwrap();
+    client_state.send_event(client1_id.into(), message("reconnected"));
+    assert_eq!(events.try_recv(), Err(TryRecvError::Disconnected));
+}
@@ -0,0 +1,286 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use mafia_game_lib::SpecialRole;
+
+use crate::MafiaGameError;
+use crate::MafiaGameServerConfig;
+use crate::config::PRESETS;
+use crate::config::load_game_config;
+use crate::config::load_server_config;
+use crate::config::preset;
+use crate::game::AfkPolicy;
+use crate::game::RevealPolicy;
+
+/// Writes `contents` to a file in the temp dir unique to this test process.
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mafia-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+
+    path
+}
+
+#[test]
+fn test_presets() {
+    for name in PRESETS {
+        let config = preset(name).unwrap();
+        // Presets are named after the number of players they are meant for.
+        let num_players = name.rsplit('-').next().unwrap().parse().unwrap();
+
+        config.validate(Some(num_players)).unwrap();
+        // AFK handling is opt-in.
+        assert!(config.afk.is_none());
+    }
+
+    assert!(preset("battle-royale-100").is_none());
+}
+
+#[test]
+fn test_validate_reports_every_problem() {
+    let mut config = preset("classic-7").unwrap();
+    config.max_days = 0;
+    config.afk = Some(AfkPolicy {
+        skip_after_missed_cycles: 0,
+        modkill_after_missed_cycles: 3,
+    });
+
+    let problems = config.validate(Some(3)).unwrap_err();
+    assert_eq!(problems.len(), 4, "{:?}", problems);
+    assert!(matches!(problems[0], MafiaGameError::InvalidGameConfig(_)));
+    assert!(matches!(problems[1], MafiaGameError::InvalidGameConfig(_)));
+    assert!(matches!(problems[2], MafiaGameError::NotEnoughPlayers(_)));
+    assert!(matches!(problems[3], MafiaGameError::NotEnoughPlayers(_)));
+
+    // Player counts are not checked without a number of players.
+    assert_eq!(config.validate(None).unwrap_err().len(), 2);
+}
+
+#[test]
+fn test_validate_max_players() {
+    let mut config = preset("classic-7").unwrap();
+    config.max_players = Some(10);
+
+    config.validate(Some(10)).unwrap();
+    assert!(matches!(
+        config.validate(Some(11)).unwrap_err()[..],
+        [MafiaGameError::TooManyPlayers(_)]
+    ));
+
+    // The cap must leave room for the game's roles.
+    config.max_players = Some(4);
+    assert!(matches!(
+        config.validate(None).unwrap_err()[..],
+        [MafiaGameError::InvalidGameConfig(_)]
+    ));
+}
+
+#[test]
+fn test_load_server_config_toml() {
+    let path = config_file(
+        "server.toml",
+        r#"
+max_client_inactive_time = "2m 30s"
+min_ready_players = 7
+lobby_game_config = "classic-7"
+"#,
+    );
+    let config = load_server_config(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.max_client_inactive_time, Duration::from_secs(150));
+    assert_eq!(config.min_ready_players, Some(7));
+    assert_eq!(
+        config.lobby_game_config.num_special_roles[&SpecialRole::Mafia],
+        2
+    );
+    // Missing fields keep their defaults.
+    assert_eq!(
+        config.max_resume_time,
+        MafiaGameServerConfig::default().max_resume_time
+    );
+}
+
+#[test]
+fn test_load_server_config_json() {
+    let path = config_file(
+        "server.json",
+        r#"{
+            "snapshot_interval": "1m",
+            "lobby_game_config": {
+                "start_cycle": "Night",
+                "time_for_day": "90s",
+                "end_day_after_all_votes": false,
+                "time_for_night": "45s",
+                "end_night_after_all_votes": true,
+                "num_special_roles": { "Mafia": 1 },
+                "vote_grace_period": "0s",
+                "reveal_on_death": "Role",
+                "trial": {
+                    "time_for_defense": "20s",
+                    "time_for_verdict": "15s",
+                    "max_trials_per_day": 1
+                },
+                "max_days": 10
+            }
+        }"#,
+    );
+    let config = load_server_config(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.snapshot_interval, Duration::from_secs(60));
+    assert_eq!(
+        config.lobby_game_config.time_for_day,
+        Duration::from_secs(90)
+    );
+    assert_eq!(
+        config.lobby_game_config.trial.unwrap().time_for_verdict,
+        Duration::from_secs(15)
+    );
+    assert!(config.lobby_game_config.afk.is_none());
+}
+
+#[test]
+fn test_load_invalid_config() {
+    let path = config_file("unknown-preset.toml", r#"lobby_game_config = "classic-8""#);
+    let err = load_server_config(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        matches!(&err, MafiaGameError::Config(msg) if msg.contains("classic-8")),
+        "{:?}",
+        err
+    );
+
+    let path = config_file("typo.toml", r#"max_client_inactiv_time = "5m""#);
+    assert!(matches!(
+        load_server_config(&path),
+        Err(MafiaGameError::Config(_))
+    ));
+    std::fs::remove_file(&path).unwrap();
+
+    // Every problem is reported, including games that cannot start with `min_ready_players`.
+    let path = config_file(
+        "invalid.toml",
+        r#"
+min_ready_players = 3
+lobby_game_config = "mafia-heavy-12"
+"#,
+    );
+    let Err(MafiaGameError::Config(msg)) = load_server_config(&path) else {
+        panic!("expected config error");
+    };
+    std::fs::remove_file(&path).unwrap();
+    assert!(msg.contains("need at least 9 players"), "{}", msg);
+    assert!(msg.contains("6 special roles"), "{}", msg);
+
+    let path = config_file(
+        "game.toml",
+        r#"
+start_cycle = "Day"
+time_for_day = "2m"
+end_day_after_all_votes = true
+time_for_night = "1m"
+end_night_after_all_votes = true
+num_special_roles = { Doctor = 1 }
+vote_grace_period = "2s"
+reveal_on_death = "Nothing"
+max_days = 0
+"#,
+    );
+    let Err(MafiaGameError::Config(msg)) = load_game_config(&path) else {
+        panic!("expected config error");
+    };
+    std::fs::remove_file(&path).unwrap();
+    assert!(msg.contains("max_days"), "{}", msg);
+    assert!(msg.contains("at least 1 mafia"), "{}", msg);
+}
+
+#[test]
+fn test_load_game_config_defaults() {
+    // Optional fields can be left out.
+    let path = config_file(
+        "minimal.toml",
+        r#"
+start_cycle = "Day"
+time_for_day = "2m"
+end_day_after_all_votes = true
+time_for_night = "1m"
+end_night_after_all_votes = true
+num_special_roles = { Mafia = 1 }
+max_days = 10
+"#,
+    );
+    let config = load_game_config(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(config.vote_grace_period, Duration::ZERO);
+    assert_eq!(config.reveal_on_death, RevealPolicy::Nothing);
+    assert!(config.trial.is_none());
+
+    // Every unknown role is reported, not just the first.
+    let path = config_file(
+        "house-roles.toml",
+        r#"
+start_cycle = "Day"
+time_for_day = "2m"
+end_day_after_all_votes = true
+time_for_night = "1m"
+end_night_after_all_votes = true
+num_special_roles = { Mafia = 1, Mayor = 1, Bodyguard = 1 }
+max_days = 10
+"#,
+    );
+    let Err(MafiaGameError::Config(msg)) = load_game_config(&path) else {
+        panic!("expected config error");
+    };
+    std::fs::remove_file(&path).unwrap();
+    assert!(msg.contains("Mayor"), "{}", msg);
+    assert!(msg.contains("Bodyguard"), "{}", msg);
+}
+
+#[test]
+fn test_load_game_config_unknown_fields() {
+    // Misspelled optional fields are rejected rather than silently left at their default.
+    for (name, typo, field) in [
+        (
+            "reveal.toml",
+            r#"reveal_on_deth = "Role""#,
+            "reveal_on_deth",
+        ),
+        (
+            "trial.toml",
+            r#"trail = { time_for_defense = "20s" }"#,
+            "trail",
+        ),
+        (
+            "trial-field.toml",
+            r#"trial = { time_for_defense = "20s", time_for_verdict = "15s", max_trials = 1 }"#,
+            "max_trials",
+        ),
+        (
+            "afk-field.toml",
+            "afk = { skip_after_missed_cycles = 1, modkill_after_missed = 3 }",
+            "modkill_after_missed",
+        ),
+    ] {
+        let path = config_file(
+            name,
+            &format!(
+                r#"
+start_cycle = "Day"
+time_for_day = "2m"
+end_day_after_all_votes = true
+time_for_night = "1m"
+end_night_after_all_votes = true
+num_special_roles = {{ Mafia = 1 }}
+max_days = 10
+{}
+"#,
+                typo
+            ),
+        );
+        let Err(MafiaGameError::Config(msg)) = load_game_config(&path) else {
+            panic!("expected config error for {}", typo);
+        };
+        std::fs::remove_file(&path).unwrap();
+        assert!(msg.contains(field), "{}", msg);
+    }
+}
@@ -1,4 +1,6 @@
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 use mafia_game_lib::Event;
@@ -7,18 +9,31 @@ use rand::rngs::mock::StepRng;
 
 use crate::Game;
 use crate::client::ClientState;
+use crate::clock::MockClock;
 use crate::consts::DAY_DEATH_MESSAGES;
+use crate::consts::MODKILL_DEATH_MESSAGE;
 use crate::consts::NIGHT_DEATH_MESSAGES;
 use crate::error::MafiaGameError;
+use crate::game::AfkPolicy;
 use crate::game::GameConfig;
+use crate::game::RevealPolicy;
+use crate::game::TrialConfig;
 use crate::game::is_alive;
+use crate::role::RoleRegistry;
 use mafia_game_lib::Allegiance;
 use mafia_game_lib::Cycle;
+use mafia_game_lib::DeathCause;
+use mafia_game_lib::DrawReason;
+use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::RoleReveal;
 use mafia_game_lib::SpecialRole;
+use mafia_game_lib::Verdict;
+use mafia_game_lib::Winners;
 
 #[test]
 fn test_game_validation() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     client_state.connect_client("garnet").unwrap();
     client_state.connect_client("amethyst").unwrap();
@@ -33,10 +48,17 @@ fn test_game_validation() {
                 time_for_night: Duration::from_secs(0),
                 end_night_after_all_votes: true,
                 num_special_roles: HashMap::new(),
-                vote_grace_period: Duration::from_secs(0)
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
             },
-            &client_state,
-            StepRng::new(1, 1)
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
         ),
         Err(MafiaGameError::InvalidGameConfig(_))
     ));
@@ -50,10 +72,17 @@ fn test_game_validation() {
                 time_for_night: Duration::from_secs(0),
                 end_night_after_all_votes: true,
                 num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 2)]),
-                vote_grace_period: Duration::from_secs(0)
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
             },
-            &client_state,
-            StepRng::new(1, 1)
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
         ),
         Err(MafiaGameError::NotEnoughPlayers(_))
     ));
@@ -70,15 +99,22 @@ fn test_game_validation() {
                     (SpecialRole::Mafia, 1),
                     (SpecialRole::Detective, 3)
                 ]),
-                vote_grace_period: Duration::from_secs(0)
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
             },
-            &client_state,
-            StepRng::new(1, 1)
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
         ),
         Err(MafiaGameError::NotEnoughPlayers(_))
     ));
 
-    assert!(matches!(
+    assert!(
         Game::start(
             GameConfig {
                 start_cycle: Cycle::Day,
@@ -87,15 +123,22 @@ fn test_game_validation() {
                 time_for_night: Duration::from_secs(0),
                 end_night_after_all_votes: true,
                 num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
-                vote_grace_period: Duration::from_secs(0)
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
             },
-            &client_state,
-            StepRng::new(1, 1)
-        ),
-        Ok(_)
-    ));
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
+        )
+        .is_ok()
+    );
 
-    assert!(matches!(
+    assert!(
         Game::start(
             GameConfig {
                 start_cycle: Cycle::Day,
@@ -107,15 +150,22 @@ fn test_game_validation() {
                     (SpecialRole::Mafia, 1),
                     (SpecialRole::Detective, 1)
                 ]),
-                vote_grace_period: Duration::from_secs(0)
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
             },
-            &client_state,
-            StepRng::new(1, 1)
-        ),
-        Ok(_)
-    ));
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
+        )
+        .is_ok()
+    );
 
-    assert!(matches!(
+    assert!(
         Game::start(
             GameConfig {
                 start_cycle: Cycle::Day,
@@ -128,18 +178,26 @@ fn test_game_validation() {
                     (SpecialRole::Detective, 1),
                     (SpecialRole::Doctor, 1)
                 ]),
-                vote_grace_period: Duration::from_secs(0)
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
             },
-            &client_state,
-            StepRng::new(1, 1)
-        ),
-        Ok(_)
-    ));
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
+        )
+        .is_ok()
+    );
 }
 
 #[test_log::test]
 fn test_game_single_cycle_day() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, _) = client_state.connect_client("garnet").unwrap();
     let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
@@ -154,9 +212,16 @@ fn test_game_single_cycle_day() {
             end_night_after_all_votes: true,
             num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
             vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
         },
-        &client_state,
+        &client_state.all_client_ids(),
         StepRng::new(1, 1),
+        clock.clone(),
     )
     .unwrap();
 
@@ -178,21 +243,27 @@ fn test_game_single_cycle_day() {
             Event::PlayerKilled {
                 player: client3_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::GameWon {
                 player_to_role: HashMap::from_iter([(client3_id, SpecialRole::Mafia)]),
-                side: Allegiance::Villagers
+                winners: Winners::from(Allegiance::Villagers)
             }
         ]
     );
 
-    assert_eq!(game.get_winner(), Some(Allegiance::Villagers));
+    assert_eq!(
+        game.get_winner(),
+        Some(&Winners::from(Allegiance::Villagers))
+    );
 }
 
 #[test_log::test]
 fn test_game_single_cycle_night() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, _) = client_state.connect_client("garnet").unwrap();
     let (_client2_id, _) = client_state.connect_client("amethyst").unwrap();
@@ -207,9 +278,16 @@ fn test_game_single_cycle_night() {
             end_night_after_all_votes: true,
             num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
             vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
         },
-        &client_state,
+        &client_state.all_client_ids(),
         StepRng::new(1, 1),
+        clock.clone(),
     )
     .unwrap();
 
@@ -226,21 +304,24 @@ fn test_game_single_cycle_night() {
             Event::PlayerKilled {
                 player: client1_id,
                 cycle: Cycle::Night,
-                death_message: Box::from(NIGHT_DEATH_MESSAGES[0])
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::GameWon {
                 player_to_role: HashMap::from_iter([(client3_id, SpecialRole::Mafia)]),
-                side: Allegiance::Mafia
+                winners: Winners::from(Allegiance::Mafia)
             }
         ]
     );
 
-    assert_eq!(game.get_winner(), Some(Allegiance::Mafia));
+    assert_eq!(game.get_winner(), Some(&Winners::from(Allegiance::Mafia)));
 }
 
 #[test_log::test]
 fn test_game_vote_rejections_day() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, _) = client_state.connect_client("garnet").unwrap();
     let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
@@ -263,9 +344,16 @@ fn test_game_vote_rejections_day() {
                 (SpecialRole::Doctor, 1),
             ]),
             vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
         },
-        &client_state,
+        &client_state.all_client_ids(),
         StepRng::new(1, 1),
+        clock.clone(),
     )
     .unwrap();
 
@@ -345,7 +433,9 @@ fn test_game_vote_rejections_day() {
             Event::PlayerKilled {
                 player: client4_id,
                 cycle: Cycle::Night,
-                death_message: Box::from(NIGHT_DEATH_MESSAGES[0])
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::PlayerInvestigated {
                 actor: client3_id,
@@ -386,7 +476,9 @@ fn test_game_vote_rejections_day() {
             Event::PlayerKilled {
                 player: client7_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::SetCycle {
                 cycle: Cycle::Night,
@@ -429,7 +521,9 @@ fn test_game_vote_rejections_day() {
             Event::PlayerKilled {
                 player: client5_id,
                 cycle: Cycle::Night,
-                death_message: Box::from(NIGHT_DEATH_MESSAGES[0])
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::PlayerInvestigated {
                 actor: client3_id,
@@ -474,7 +568,9 @@ fn test_game_vote_rejections_day() {
             Event::PlayerKilled {
                 player: client1_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::GameWon {
                 player_to_role: HashMap::from_iter([
@@ -483,14 +579,17 @@ fn test_game_vote_rejections_day() {
                     (client2_id, SpecialRole::Doctor),
                     (client3_id, SpecialRole::Detective)
                 ]),
-                side: Allegiance::Villagers
+                winners: Winners::from(Allegiance::Villagers)
             }
         ]
     );
 
     // -- VILLAGERS WIN --
     // All votes fail.
-    assert_eq!(game.get_winner(), Some(Allegiance::Villagers));
+    assert_eq!(
+        game.get_winner(),
+        Some(&Winners::from(Allegiance::Villagers))
+    );
 
     for &client_id in client_state.list_clients().values() {
         assert!(matches!(
@@ -502,7 +601,8 @@ fn test_game_vote_rejections_day() {
 
 #[test_log::test]
 fn test_game_e2e_mafia_win() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, _) = client_state.connect_client("garnet").unwrap();
     let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
@@ -525,9 +625,16 @@ fn test_game_e2e_mafia_win() {
                 (SpecialRole::Doctor, 1),
             ]),
             vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
         },
-        &client_state,
+        &client_state.all_client_ids(),
         StepRng::new(1, 1),
+        clock.clone(),
     )
     .unwrap();
 
@@ -577,7 +684,9 @@ fn test_game_e2e_mafia_win() {
             Event::PlayerKilled {
                 player: client4_id,
                 cycle: Cycle::Night,
-                death_message: Box::from(NIGHT_DEATH_MESSAGES[0])
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::SetCycle {
                 cycle: Cycle::Day,
@@ -599,7 +708,9 @@ fn test_game_e2e_mafia_win() {
             Event::PlayerKilled {
                 player: client5_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::SetCycle {
                 cycle: Cycle::Night,
@@ -622,7 +733,9 @@ fn test_game_e2e_mafia_win() {
             Event::PlayerKilled {
                 player: client6_id,
                 cycle: Cycle::Night,
-                death_message: Box::from(NIGHT_DEATH_MESSAGES[0])
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::GameWon {
                 player_to_role: HashMap::from_iter([
@@ -631,18 +744,19 @@ fn test_game_e2e_mafia_win() {
                     (client2_id, SpecialRole::Doctor),
                     (client3_id, SpecialRole::Detective)
                 ]),
-                side: Allegiance::Mafia
+                winners: Winners::from(Allegiance::Mafia)
             }
         ]
     );
 
     // -- MAFIA WIN --
-    assert_eq!(game.get_winner(), Some(Allegiance::Mafia));
+    assert_eq!(game.get_winner(), Some(&Winners::from(Allegiance::Mafia)));
 }
 
 #[test_log::test]
 fn test_game_e2e_villagers_win() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, _) = client_state.connect_client("garnet").unwrap();
     let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
@@ -665,9 +779,16 @@ fn test_game_e2e_villagers_win() {
                 (SpecialRole::Doctor, 1),
             ]),
             vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
         },
-        &client_state,
+        &client_state.all_client_ids(),
         StepRng::new(1, 1),
+        clock.clone(),
     )
     .unwrap();
 
@@ -717,7 +838,9 @@ fn test_game_e2e_villagers_win() {
             Event::PlayerKilled {
                 player: client4_id,
                 cycle: Cycle::Night,
-                death_message: Box::from(NIGHT_DEATH_MESSAGES[0])
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::SetCycle {
                 cycle: Cycle::Day,
@@ -739,7 +862,9 @@ fn test_game_e2e_villagers_win() {
             Event::PlayerKilled {
                 player: client7_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::SetCycle {
                 cycle: Cycle::Night,
@@ -761,7 +886,9 @@ fn test_game_e2e_villagers_win() {
             Event::PlayerKilled {
                 player: client6_id,
                 cycle: Cycle::Night,
-                death_message: Box::from(NIGHT_DEATH_MESSAGES[0])
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::SetCycle {
                 cycle: Cycle::Day,
@@ -783,7 +910,9 @@ fn test_game_e2e_villagers_win() {
             Event::PlayerKilled {
                 player: client1_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::GameWon {
                 player_to_role: HashMap::from_iter([
@@ -792,18 +921,22 @@ fn test_game_e2e_villagers_win() {
                     (client2_id, SpecialRole::Doctor),
                     (client3_id, SpecialRole::Detective)
                 ]),
-                side: Allegiance::Villagers
+                winners: Winners::from(Allegiance::Villagers)
             }
         ]
     );
 
     // -- VILLAGERS WIN --
-    assert_eq!(game.get_winner(), Some(Allegiance::Villagers));
+    assert_eq!(
+        game.get_winner(),
+        Some(&Winners::from(Allegiance::Villagers))
+    );
 }
 
 #[test_log::test]
 fn test_game_e2e_doctor_investigator() {
-    let mut client_state = ClientState::new();
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
 
     let (client1_id, _) = client_state.connect_client("garnet").unwrap();
     let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
@@ -826,9 +959,16 @@ fn test_game_e2e_doctor_investigator() {
                 (SpecialRole::Doctor, 1),
             ]),
             vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
         },
-        &client_state,
+        &client_state.all_client_ids(),
         StepRng::new(1, 1),
+        clock.clone(),
     )
     .unwrap();
 
@@ -902,7 +1042,9 @@ fn test_game_e2e_doctor_investigator() {
             Event::PlayerKilled {
                 player: client7_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::SetCycle {
                 cycle: Cycle::Night,
@@ -950,7 +1092,9 @@ fn test_game_e2e_doctor_investigator() {
             Event::PlayerKilled {
                 player: client1_id,
                 cycle: Cycle::Day,
-                death_message: Box::from(DAY_DEATH_MESSAGES[0])
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
             },
             Event::GameWon {
                 player_to_role: HashMap::from_iter([
@@ -959,12 +1103,792 @@ fn test_game_e2e_doctor_investigator() {
                     (client2_id, SpecialRole::Doctor),
                     (client3_id, SpecialRole::Detective)
                 ]),
-                side: Allegiance::Villagers
+                winners: Winners::from(Allegiance::Villagers)
             }
         ]
     );
 
     // -- VILLAGERS WIN --
     assert_eq!(game.get_players(is_alive).count(), 5);
-    assert_eq!(game.get_winner(), Some(Allegiance::Villagers));
+    assert_eq!(
+        game.get_winner(),
+        Some(&Winners::from(Allegiance::Villagers))
+    );
+}
+
+#[test_log::test]
+fn test_game_reveal_on_death() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (_client3_id, _) = client_state.connect_client("pearl").unwrap();
+    let (_client4_id, _) = client_state.connect_client("steven").unwrap();
+    let (client5_id, _) = client_state.connect_client("connie").unwrap();
+
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Day,
+            time_for_day: Duration::from_secs(10),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(10),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([
+                (SpecialRole::Mafia, 1),
+                (SpecialRole::Doctor, 1),
+            ]),
+            vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Role,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        *game.get_player_roles(),
+        HashMap::from_iter([
+            (client5_id, SpecialRole::Mafia),
+            (client1_id, SpecialRole::Doctor)
+        ]),
+    );
+
+    // -- DAY 1 --
+    for client_id in &game.get_players(is_alive) {
+        game.cast_vote(client_id, Some(client1_id)).unwrap();
+    }
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::PlayerKilled {
+                player: client1_id,
+                cycle: Cycle::Day,
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: Some(RoleReveal::Role {
+                    allegiance: Allegiance::Villagers,
+                    role: Some(SpecialRole::Doctor)
+                }),
+                cause: DeathCause::Killed,
+            },
+            Event::SetCycle {
+                cycle: Cycle::Night,
+                day_num: 1,
+                start_time_unix_ts_secs: 0,
+                duration_secs: 10
+            }
+        ]
+    );
+
+    // -- NIGHT 1 --
+    game.cast_vote(client5_id, Some(client2_id)).unwrap();
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::PlayerKilled {
+                player: client2_id,
+                cycle: Cycle::Night,
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: Some(RoleReveal::Role {
+                    allegiance: Allegiance::Villagers,
+                    role: None
+                }),
+                cause: DeathCause::Killed,
+            },
+            Event::SetCycle {
+                cycle: Cycle::Day,
+                day_num: 2,
+                start_time_unix_ts_secs: 0,
+                duration_secs: 10
+            }
+        ]
+    );
+
+    assert_eq!(
+        game.get_death_reveals(),
+        HashMap::from_iter([
+            (
+                client1_id,
+                RoleReveal::Role {
+                    allegiance: Allegiance::Villagers,
+                    role: Some(SpecialRole::Doctor)
+                }
+            ),
+            (
+                client2_id,
+                RoleReveal::Role {
+                    allegiance: Allegiance::Villagers,
+                    role: None
+                }
+            )
+        ])
+    );
+
+    // Dead players cannot be substituted.
+    let (client6_id, _) = client_state.connect_client("lars").unwrap();
+    assert!(matches!(
+        game.substitute(client1_id, client6_id),
+        Err(MafiaGameError::InvalidSubstitution(_))
+    ));
+    assert_eq!(game.get_player_status(client6_id), None);
+}
+
+#[test]
+fn test_game_trial() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (client3_id, _) = client_state.connect_client("pearl").unwrap();
+    let (client4_id, _) = client_state.connect_client("steven").unwrap();
+    let (client5_id, _) = client_state.connect_client("connie").unwrap();
+
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Day,
+            time_for_day: Duration::from_secs(10),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(10),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+            vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: Some(TrialConfig {
+                time_for_defense: Duration::from_secs(5),
+                time_for_verdict: Duration::from_secs(10),
+                max_trials_per_day: 2,
+            }),
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        *game.get_player_roles(),
+        HashMap::from_iter([(client5_id, SpecialRole::Mafia)]),
+    );
+
+    // -- DAY 1, first trial --
+    clock.advance(Duration::from_secs(3));
+
+    for client_id in [client2_id, client3_id, client4_id] {
+        game.cast_vote(client_id, Some(client1_id)).unwrap();
+    }
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::TrialStarted {
+                accused: client1_id
+            },
+            Event::SetCycle {
+                cycle: Cycle::Defense,
+                day_num: 1,
+                start_time_unix_ts_secs: 3,
+                duration_secs: 5
+            }
+        ]
+    );
+    assert_eq!(game.get_accused(), Some(client1_id));
+
+    assert!(matches!(
+        game.cast_vote(client2_id, Some(client5_id)),
+        Err(MafiaGameError::InvalidVote(_))
+    ));
+    assert!(matches!(
+        game.cast_verdict(client2_id, Verdict::Guilty),
+        Err(MafiaGameError::InvalidVote(_))
+    ));
+
+    assert_eq!(game.poll_end_cycle(), vec![]);
+    clock.advance(Duration::from_secs(6));
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![Event::SetCycle {
+            cycle: Cycle::Verdict,
+            day_num: 1,
+            start_time_unix_ts_secs: 9,
+            duration_secs: 10
+        }]
+    );
+
+    assert!(matches!(
+        game.cast_verdict(client1_id, Verdict::Innocent),
+        Err(MafiaGameError::InvalidVote(_))
+    ));
+
+    game.cast_verdict(client2_id, Verdict::Guilty).unwrap();
+    game.cast_verdict(client3_id, Verdict::Innocent).unwrap();
+    game.cast_verdict(client4_id, Verdict::Innocent).unwrap();
+    assert_eq!(game.poll_end_cycle(), vec![]);
+    game.cast_verdict(client5_id, Verdict::Guilty).unwrap();
+
+    // The day resumes with the time it had left before the trial.
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::TrialVerdict {
+                accused: client1_id,
+                verdict: Verdict::Innocent,
+                votes: HashMap::from_iter([
+                    (client2_id, Verdict::Guilty),
+                    (client3_id, Verdict::Innocent),
+                    (client4_id, Verdict::Innocent),
+                    (client5_id, Verdict::Guilty),
+                ]),
+            },
+            Event::SetCycle {
+                cycle: Cycle::Day,
+                day_num: 1,
+                start_time_unix_ts_secs: 6,
+                duration_secs: 10
+            }
+        ]
+    );
+    assert_eq!(game.get_accused(), None);
+    assert!(game.get_votes().is_empty());
+
+    // -- DAY 1, second trial --
+    for client_id in [client1_id, client2_id, client3_id] {
+        game.cast_vote(client_id, Some(client5_id)).unwrap();
+    }
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::TrialStarted {
+                accused: client5_id
+            },
+            Event::SetCycle {
+                cycle: Cycle::Defense,
+                day_num: 1,
+                start_time_unix_ts_secs: 9,
+                duration_secs: 5
+            }
+        ]
+    );
+    clock.advance(Duration::from_secs(6));
+    game.poll_end_cycle();
+
+    for client_id in [client1_id, client2_id, client3_id, client4_id] {
+        game.cast_verdict(client_id, Verdict::Guilty).unwrap();
+    }
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::TrialVerdict {
+                accused: client5_id,
+                verdict: Verdict::Guilty,
+                votes: HashMap::from_iter([
+                    (client1_id, Verdict::Guilty),
+                    (client2_id, Verdict::Guilty),
+                    (client3_id, Verdict::Guilty),
+                    (client4_id, Verdict::Guilty),
+                ]),
+            },
+            Event::PlayerKilled {
+                player: client5_id,
+                cycle: Cycle::Verdict,
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
+            },
+            Event::GameWon {
+                player_to_role: HashMap::from_iter([(client5_id, SpecialRole::Mafia)]),
+                winners: Winners::from(Allegiance::Villagers),
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_game_trial_limit() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (client3_id, _) = client_state.connect_client("pearl").unwrap();
+    let (client4_id, _) = client_state.connect_client("steven").unwrap();
+    let (client5_id, _) = client_state.connect_client("connie").unwrap();
+
+    let config = GameConfig {
+        start_cycle: Cycle::Day,
+        time_for_day: Duration::from_secs(10),
+        end_day_after_all_votes: true,
+        time_for_night: Duration::from_secs(10),
+        end_night_after_all_votes: true,
+        num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+        vote_grace_period: Duration::from_secs(0),
+        roles: RoleRegistry::default(),
+        reveal_on_death: RevealPolicy::Nothing,
+        trial: Some(TrialConfig {
+            time_for_defense: Duration::from_secs(5),
+            time_for_verdict: Duration::from_secs(10),
+            max_trials_per_day: 0,
+        }),
+        max_days: 100,
+        max_players: None,
+        afk: None,
+    };
+
+    // A limit of 0 would still let the first trial happen, so it is rejected.
+    assert!(matches!(
+        Game::start(
+            config.clone(),
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
+        ),
+        Err(MafiaGameError::InvalidGameConfig(_))
+    ));
+
+    let mut game = Game::start(
+        GameConfig {
+            trial: Some(TrialConfig {
+                max_trials_per_day: 1,
+                ..config.trial.clone().unwrap()
+            }),
+            ..config
+        },
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    // -- DAY 1, only trial --
+    clock.advance(Duration::from_secs(3));
+
+    for client_id in [client2_id, client3_id, client4_id] {
+        game.cast_vote(client_id, Some(client1_id)).unwrap();
+    }
+
+    game.poll_end_cycle();
+    assert_eq!(game.get_accused(), Some(client1_id));
+
+    clock.advance(Duration::from_secs(6));
+    game.poll_end_cycle();
+
+    for client_id in [client2_id, client3_id, client4_id, client5_id] {
+        game.cast_verdict(client_id, Verdict::Innocent).unwrap();
+    }
+
+    // An innocent verdict on the last trial of the day ends the day without a lynch.
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::TrialVerdict {
+                accused: client1_id,
+                verdict: Verdict::Innocent,
+                votes: HashMap::from_iter([
+                    (client2_id, Verdict::Innocent),
+                    (client3_id, Verdict::Innocent),
+                    (client4_id, Verdict::Innocent),
+                    (client5_id, Verdict::Innocent),
+                ]),
+            },
+            Event::FailedVote {
+                cycle: Cycle::Verdict,
+                channel: EventChannel::Public,
+            },
+            Event::SetCycle {
+                cycle: Cycle::Night,
+                day_num: 1,
+                start_time_unix_ts_secs: 9,
+                duration_secs: 10
+            }
+        ]
+    );
+    assert_eq!(game.get_accused(), None);
+}
+
+#[test]
+fn test_game_neutral_winners() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (client3_id, _) = client_state.connect_client("pearl").unwrap();
+    let (client4_id, _) = client_state.connect_client("steven").unwrap();
+    let (client5_id, _) = client_state.connect_client("connie").unwrap();
+
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Day,
+            time_for_day: Duration::from_secs(10),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(10),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([
+                (SpecialRole::Mafia, 1),
+                (SpecialRole::Jester, 1),
+                (SpecialRole::SerialKiller, 1),
+            ]),
+            vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        *game.get_player_roles(),
+        HashMap::from_iter([
+            (client5_id, SpecialRole::Mafia),
+            (client1_id, SpecialRole::Jester),
+            (client2_id, SpecialRole::SerialKiller),
+        ]),
+    );
+
+    // -- DAY 1 --
+    for client_id in &game.get_players(is_alive) {
+        game.cast_vote(client_id, Some(client1_id)).unwrap();
+    }
+
+    // The Jester wins, but the game goes on.
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::PlayerKilled {
+                player: client1_id,
+                cycle: Cycle::Day,
+                death_message: Box::from(DAY_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
+            },
+            Event::SetCycle {
+                cycle: Cycle::Night,
+                day_num: 1,
+                start_time_unix_ts_secs: 0,
+                duration_secs: 10
+            }
+        ]
+    );
+
+    // -- NIGHT 1 --
+    // The Mafia and Serial Killer kill each other.
+    game.cast_vote(client5_id, Some(client2_id)).unwrap();
+    game.cast_vote(client2_id, Some(client5_id)).unwrap();
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::PlayerKilled {
+                player: client2_id,
+                cycle: Cycle::Night,
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
+            },
+            Event::PlayerKilled {
+                player: client5_id,
+                cycle: Cycle::Night,
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
+            },
+            Event::GameWon {
+                player_to_role: HashMap::from_iter([
+                    (client5_id, SpecialRole::Mafia),
+                    (client1_id, SpecialRole::Jester),
+                    (client2_id, SpecialRole::SerialKiller),
+                ]),
+                winners: Winners {
+                    factions: BTreeSet::from([Allegiance::Villagers]),
+                    players: BTreeSet::from([client1_id]),
+                }
+            }
+        ]
+    );
+
+    assert_eq!(
+        game.get_player_status(client3_id),
+        Some(PlayerStatus::Alive)
+    );
+    assert_eq!(
+        game.get_player_status(client4_id),
+        Some(PlayerStatus::Alive)
+    );
+}
+
+#[test]
+fn test_game_max_days_draw() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (_client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (_client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (_client3_id, _) = client_state.connect_client("pearl").unwrap();
+    let (_client4_id, _) = client_state.connect_client("steven").unwrap();
+    let (client5_id, _) = client_state.connect_client("connie").unwrap();
+
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Day,
+            time_for_day: Duration::from_secs(10),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(10),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+            vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 1,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    // -- DAY 1 --
+    for client_id in &game.get_players(is_alive) {
+        game.cast_vote(client_id, None).unwrap();
+    }
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::FailedVote {
+                cycle: Cycle::Day,
+                channel: EventChannel::Public
+            },
+            Event::SetCycle {
+                cycle: Cycle::Night,
+                day_num: 1,
+                start_time_unix_ts_secs: 0,
+                duration_secs: 10
+            }
+        ]
+    );
+
+    // -- NIGHT 1 --
+    game.cast_vote(client5_id, None).unwrap();
+
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::FailedVote {
+                cycle: Cycle::Night,
+                channel: EventChannel::Mafia
+            },
+            Event::GameDrawn {
+                player_to_role: HashMap::from_iter([(client5_id, SpecialRole::Mafia)]),
+                reason: DrawReason::MaxDays,
+            }
+        ]
+    );
+
+    assert_eq!(game.get_winner(), None);
+    assert_eq!(game.get_draw(), Some(DrawReason::MaxDays));
+    assert!(matches!(
+        game.cast_vote(client5_id, None),
+        Err(MafiaGameError::InvalidVote(_))
+    ));
+}
+
+#[test]
+fn test_game_cycle_timeout() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (_client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (_client3_id, _) = client_state.connect_client("pearl").unwrap();
+
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Day,
+            time_for_day: Duration::from_secs(10),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(10),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+            vote_grace_period: Duration::from_secs(2),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    assert!(matches!(
+        game.cast_vote(client1_id, None),
+        Err(MafiaGameError::InvalidVote(_))
+    ));
+
+    clock.advance(Duration::from_secs(2));
+    game.cast_vote(client1_id, None).unwrap();
+
+    clock.advance(Duration::from_secs(8));
+    assert_eq!(game.poll_end_cycle(), vec![]);
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::FailedVote {
+                cycle: Cycle::Day,
+                channel: EventChannel::Public
+            },
+            Event::SetCycle {
+                cycle: Cycle::Night,
+                day_num: 1,
+                start_time_unix_ts_secs: 11,
+                duration_secs: 10
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_game_afk_modkill() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (_client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (_client3_id, _) = client_state.connect_client("pearl").unwrap();
+    let (_client4_id, _) = client_state.connect_client("steven").unwrap();
+    let (client5_id, _) = client_state.connect_client("connie").unwrap();
+
+    let config = GameConfig {
+        start_cycle: Cycle::Day,
+        time_for_day: Duration::from_secs(10),
+        end_day_after_all_votes: true,
+        time_for_night: Duration::from_secs(10),
+        end_night_after_all_votes: true,
+        num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+        vote_grace_period: Duration::from_secs(0),
+        roles: RoleRegistry::default(),
+        reveal_on_death: RevealPolicy::Nothing,
+        trial: None,
+        max_days: 100,
+        max_players: None,
+        afk: Some(AfkPolicy {
+            skip_after_missed_cycles: 1,
+            modkill_after_missed_cycles: 2,
+        }),
+    };
+
+    assert!(matches!(
+        Game::start(
+            GameConfig {
+                afk: Some(AfkPolicy {
+                    skip_after_missed_cycles: 2,
+                    modkill_after_missed_cycles: 1,
+                }),
+                ..config.clone()
+            },
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
+        ),
+        Err(MafiaGameError::InvalidGameConfig(_))
+    ));
+
+    let mut game = Game::start(
+        config,
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    // -- DAY 1 --
+    // Garnet is idle and holds up the day until it runs out.
+    for client_id in &game.get_players(is_alive) {
+        if client_id != client1_id {
+            game.cast_vote(client_id, None).unwrap();
+        }
+    }
+    assert_eq!(game.poll_end_cycle(), vec![]);
+
+    clock.advance(Duration::from_secs(11));
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::FailedVote {
+                cycle: Cycle::Day,
+                channel: EventChannel::Public
+            },
+            Event::SetCycle {
+                cycle: Cycle::Night,
+                day_num: 1,
+                start_time_unix_ts_secs: 11,
+                duration_secs: 10
+            }
+        ]
+    );
+
+    // -- NIGHT 1 --
+    // Garnet has no night vote, so cannot miss the night.
+    game.cast_vote(client5_id, None).unwrap();
+    assert_eq!(game.poll_end_cycle().len(), 2);
+
+    // -- DAY 2 --
+    // Garnet's vote is skipped, and they are removed for missing a second day.
+    for client_id in &game.get_players(is_alive) {
+        if client_id != client1_id {
+            game.cast_vote(client_id, None).unwrap();
+        }
+    }
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::PlayerKilled {
+                player: client1_id,
+                cycle: Cycle::Day,
+                death_message: Box::from(MODKILL_DEATH_MESSAGE),
+                reveal: None,
+                cause: DeathCause::Modkilled,
+            },
+            Event::FailedVote {
+                cycle: Cycle::Day,
+                channel: EventChannel::Public
+            },
+            Event::SetCycle {
+                cycle: Cycle::Night,
+                day_num: 2,
+                start_time_unix_ts_secs: 11,
+                duration_secs: 10
+            }
+        ]
+    );
+    assert_eq!(game.get_player_status(client1_id), Some(PlayerStatus::Dead));
 }
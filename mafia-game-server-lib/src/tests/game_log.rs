@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mafia_game_lib::ClientId;
+use mafia_game_lib::Cycle;
+use mafia_game_lib::Event;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::SessionToken;
+use mafia_game_lib::SpecialRole;
+use mafia_game_lib::Verdict;
+use rand::rngs::mock::StepRng;
+
+use crate::Game;
+use crate::MafiaGameServer;
+use crate::MafiaGameServerConfig;
+use crate::client::ClientState;
+use crate::clock::MockClock;
+use crate::error::MafiaGameError;
+use crate::game::GameConfig;
+use crate::game::RevealPolicy;
+use crate::game::TrialConfig;
+use crate::game::is_alive;
+use crate::game_log::GameInput;
+use crate::game_log::GameLog;
+use crate::role::RoleRegistry;
+use crate::room::LOBBY;
+use crate::tests::role::Mayor;
+
+fn assert_same_game(a: &Game, b: &Game) {
+    assert_eq!(a.get_player_roles(), b.get_player_roles());
+    assert_eq!(a.get_player_statuses(), b.get_player_statuses());
+    assert_eq!(a.get_votes(), b.get_votes());
+    assert_eq!(a.get_cycle(), b.get_cycle());
+    assert_eq!(a.get_day_num(), b.get_day_num());
+    assert_eq!(
+        a.get_cycle_start_unix_ts_secs(),
+        b.get_cycle_start_unix_ts_secs()
+    );
+    assert_eq!(a.get_accused(), b.get_accused());
+    assert_eq!(a.get_winner(), b.get_winner());
+    assert_eq!(a.get_draw(), b.get_draw());
+    assert_eq!(a.get_log().entries().len(), b.get_log().entries().len());
+}
+
+#[test_log::test]
+fn test_game_log_replay() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    for name in ["garnet", "amethyst", "pearl", "steven", "connie"] {
+        client_state.connect_client(name).unwrap();
+    }
+
+    // Roles are drawn from a real RNG, the log must remember them.
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Day,
+            time_for_day: Duration::from_secs(60),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(30),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([
+                (SpecialRole::Mafia, 1),
+                (SpecialRole::Doctor, 1),
+            ]),
+            vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Role,
+            trial: Some(TrialConfig {
+                time_for_defense: Duration::from_secs(5),
+                time_for_verdict: Duration::from_secs(10),
+                max_trials_per_day: 1,
+            }),
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        rand::rng(),
+        clock.clone(),
+    )
+    .unwrap();
+
+    let mut players = game.get_players(is_alive).into_iter().collect::<Vec<_>>();
+    players.sort();
+    let accused = players[0];
+
+    let mut events = Vec::new();
+
+    // Everyone nominates the first player, who is put on trial and found guilty.
+    clock.advance(Duration::from_secs(3));
+    for &player in &players {
+        game.cast_vote(player, Some(accused)).unwrap();
+        events.push(game.vote_event(player, Some(accused)));
+    }
+    events.extend(game.poll_end_cycle());
+    assert_eq!(game.get_cycle(), Cycle::Defense);
+
+    clock.advance(Duration::from_secs(6));
+    events.extend(game.poll_end_cycle());
+    assert_eq!(game.get_cycle(), Cycle::Verdict);
+
+    for &player in &players[1..] {
+        game.cast_verdict(player, Verdict::Guilty).unwrap();
+    }
+    events.extend(game.poll_end_cycle());
+    assert_eq!(
+        game.get_player_status(accused),
+        Some(PlayerStatus::Dead),
+        "accused should have been lynched"
+    );
+
+    // Polls that change nothing are not logged.
+    let num_entries = game.get_log().entries().len();
+    assert!(game.poll_end_cycle().is_empty());
+    assert_eq!(game.get_log().entries().len(), num_entries);
+
+    // The night runs out without votes.
+    if !game.is_over() {
+        clock.advance(Duration::from_secs(31));
+        events.extend(game.poll_end_cycle());
+    }
+
+    assert!(matches!(
+        game.get_log().entries()[0].input,
+        GameInput::Start { .. }
+    ));
+
+    let (replayed, replayed_events) = game
+        .get_log()
+        .replay_game(&RoleRegistry::default())
+        .unwrap();
+    assert_same_game(&game, &replayed);
+    assert_eq!(replayed_events, events);
+
+    // Logs survive serialization.
+    let log: GameLog =
+        serde_json::from_str(&serde_json::to_string(game.get_log()).unwrap()).unwrap();
+    assert_eq!(log.replay(&RoleRegistry::default()).unwrap(), events);
+}
+
+#[test]
+fn test_game_log_replay_house_role() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    for name in ["garnet", "amethyst", "pearl", "steven", "connie"] {
+        client_state.connect_client(name).unwrap();
+    }
+
+    let mut roles = RoleRegistry::default();
+    roles.register(Mayor);
+
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Night,
+            time_for_day: Duration::from_secs(60),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(30),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([
+                (SpecialRole::Mafia, 1),
+                (SpecialRole::from("Mayor"), 1),
+            ]),
+            vote_grace_period: Duration::from_secs(0),
+            roles: roles.clone(),
+            reveal_on_death: RevealPolicy::Role,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        rand::rng(),
+        clock.clone(),
+    )
+    .unwrap();
+
+    // The mafia kills someone, the mayor has no night vote so the night ends right away.
+    let (&mafia, _) = game
+        .get_player_roles()
+        .iter()
+        .find(|(_, role)| **role == SpecialRole::Mafia)
+        .unwrap();
+    let target = game
+        .get_players(is_alive)
+        .into_iter()
+        .find(|&player| player != mafia)
+        .unwrap();
+
+    clock.advance(Duration::from_secs(1));
+    game.cast_vote(mafia, Some(target)).unwrap();
+    let mut events = vec![game.vote_event(mafia, Some(target))];
+    events.extend(game.poll_end_cycle());
+    assert_eq!(game.get_cycle(), Cycle::Day);
+
+    let log: GameLog =
+        serde_json::from_str(&serde_json::to_string(game.get_log()).unwrap()).unwrap();
+
+    // House roles have to be given back to replay the game.
+    assert!(matches!(
+        log.replay(&RoleRegistry::default()),
+        Err(MafiaGameError::InvalidGameLog(_))
+    ));
+
+    let (replayed, replayed_events) = log.replay_game(&roles).unwrap();
+    assert_same_game(&game, &replayed);
+    assert_eq!(replayed_events, events);
+}
+
+#[test]
+fn test_game_log_replay_server_events() {
+    let admin = SessionToken::new();
+    let server = MafiaGameServer::new(
+        MafiaGameServerConfig {
+            randomize_death_message: true,
+            admin_token: Some(admin),
+            lobby_game_config: GameConfig {
+                start_cycle: Cycle::Day,
+                time_for_day: Duration::from_secs(60),
+                end_day_after_all_votes: true,
+                time_for_night: Duration::from_secs(30),
+                end_night_after_all_votes: true,
+                num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
+            },
+            ..Default::default()
+        },
+        Arc::new(MockClock::default()),
+    );
+
+    let players =
+        ["garnet", "amethyst", "pearl", "connie"].map(|name| server.connect_client(name).unwrap());
+    let (_, steven) = server.connect_client("steven").unwrap();
+    server
+        .set_lobby_status(steven, LobbyStatus::Spectator)
+        .unwrap();
+
+    server
+        .start_game(players[0].1, LOBBY, StepRng::new(1, 1))
+        .unwrap();
+    server.take_events(steven).unwrap();
+
+    let roles = server.0.read().unwrap().rooms[LOBBY]
+        .active_game
+        .as_ref()
+        .unwrap()
+        .get_player_roles()
+        .clone();
+    let (mafia, villagers): (Vec<&(ClientId, SessionToken)>, Vec<_>) = players
+        .iter()
+        .partition(|(client_id, _)| roles.get(client_id) == Some(&SpecialRole::Mafia));
+
+    // The villagers lynch one of their own, then one of them leaves during the night and the
+    // mafia kills another.
+    for (_, token) in &players {
+        server.cast_vote(*token, Some(villagers[0].0)).unwrap();
+    }
+    server.disconnect_client(villagers[1].1).unwrap();
+    server.cast_vote(mafia[0].1, Some(villagers[2].0)).unwrap();
+
+    // Steven spectates, so he sees every event about the game, with the same death messages.
+    let events = server
+        .take_events(steven)
+        .unwrap()
+        .iter()
+        .map(|event| (*event.event).clone())
+        .collect::<Vec<_>>();
+    assert!(
+        events
+            .iter()
+            .any(|event| matches!(event, Event::ClientDisconnected(_))),
+        "{:?}",
+        events
+    );
+
+    let log = server.game_log(admin, LOBBY).unwrap();
+    assert!(
+        log.entries()
+            .iter()
+            .any(|entry| matches!(entry.input, GameInput::RandomizeDeathMessages { .. }))
+    );
+    assert_eq!(log.replay(&RoleRegistry::default()).unwrap(), events);
+}
+
+#[test]
+fn test_game_log_invalid() {
+    assert!(matches!(
+        GameLog::default().replay(&RoleRegistry::default()),
+        Err(MafiaGameError::InvalidGameLog(_))
+    ));
+}
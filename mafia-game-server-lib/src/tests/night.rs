@@ -0,0 +1,277 @@
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+
+use crate::night::NightOutcome;
+use crate::night::NightQueue;
+use crate::night::QueuedAction;
+use crate::role::NightAction;
+
+fn action(
+    actor: u64,
+    allegiance: Allegiance,
+    action: NightAction,
+    target: Option<u64>,
+) -> QueuedAction {
+    QueuedAction {
+        actor: ClientId(actor),
+        allegiance,
+        action,
+        target: target.map(ClientId),
+        priority: action.default_priority(),
+    }
+}
+
+fn allegiance_of(client_id: ClientId) -> Allegiance {
+    if client_id.0 < 2 {
+        Allegiance::Mafia
+    } else {
+        Allegiance::Villagers
+    }
+}
+
+#[test_log::test]
+fn test_night_kill_and_protect() {
+    let mut queue = NightQueue::new();
+
+    // Resolution order does not depend on queue order.
+    queue
+        .push(action(
+            2,
+            Allegiance::Villagers,
+            NightAction::Investigate,
+            Some(1),
+        ))
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(1, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(
+            3,
+            Allegiance::Villagers,
+            NightAction::Protect,
+            Some(5),
+        ));
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![
+            NightOutcome::Protected {
+                actor: ClientId(3),
+                target: ClientId(5)
+            },
+            NightOutcome::Killed {
+                allegiance: Allegiance::Mafia,
+                target: ClientId(4)
+            },
+            NightOutcome::Investigated {
+                actor: ClientId(2),
+                target: ClientId(1),
+                allegiance: Allegiance::Mafia
+            },
+        ]
+    );
+
+    let mut queue = NightQueue::new();
+
+    queue
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(1, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(
+            3,
+            Allegiance::Villagers,
+            NightAction::Protect,
+            Some(4),
+        ));
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![
+            NightOutcome::Protected {
+                actor: ClientId(3),
+                target: ClientId(4)
+            },
+            NightOutcome::Saved {
+                allegiance: Allegiance::Mafia,
+                target: ClientId(4)
+            },
+        ]
+    );
+
+    // Killers that do not reach a majority fail.
+    let mut queue = NightQueue::new();
+
+    queue
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(1, Allegiance::Mafia, NightAction::Kill, None));
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![NightOutcome::FailedKill {
+            allegiance: Allegiance::Mafia
+        }]
+    );
+}
+
+#[test_log::test]
+fn test_night_block() {
+    let mut queue = NightQueue::new();
+
+    // A blocked killer does not count towards the majority, so the other killer decides alone.
+    queue
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(1, Allegiance::Mafia, NightAction::Kill, Some(5)))
+        .push(action(
+            2,
+            Allegiance::Villagers,
+            NightAction::Block,
+            Some(1),
+        ))
+        .push(action(
+            3,
+            Allegiance::Villagers,
+            NightAction::Investigate,
+            Some(0),
+        ));
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![
+            NightOutcome::Blocked {
+                actor: ClientId(2),
+                target: ClientId(1)
+            },
+            NightOutcome::Killed {
+                allegiance: Allegiance::Mafia,
+                target: ClientId(4)
+            },
+            NightOutcome::Investigated {
+                actor: ClientId(3),
+                target: ClientId(0),
+                allegiance: Allegiance::Mafia
+            },
+        ]
+    );
+
+    // Blocking every killer stops the kill.
+    let mut queue = NightQueue::new();
+
+    queue
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(
+            2,
+            Allegiance::Villagers,
+            NightAction::Block,
+            Some(0),
+        ));
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![
+            NightOutcome::Blocked {
+                actor: ClientId(2),
+                target: ClientId(0)
+            },
+            NightOutcome::FailedKill {
+                allegiance: Allegiance::Mafia
+            },
+        ]
+    );
+
+    // A blocked blocker does nothing, ties are broken by actor.
+    let mut queue = NightQueue::new();
+
+    queue
+        .push(action(
+            3,
+            Allegiance::Villagers,
+            NightAction::Block,
+            Some(0),
+        ))
+        .push(action(1, Allegiance::Mafia, NightAction::Block, Some(3)))
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)));
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![
+            NightOutcome::Blocked {
+                actor: ClientId(1),
+                target: ClientId(3)
+            },
+            NightOutcome::Killed {
+                allegiance: Allegiance::Mafia,
+                target: ClientId(4)
+            },
+        ]
+    );
+}
+
+#[test_log::test]
+fn test_night_redirect() {
+    let mut queue = NightQueue::new();
+
+    // Bodyguard takes the kill aimed at their target, which the doctor did not protect.
+    queue
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(
+            3,
+            Allegiance::Villagers,
+            NightAction::Protect,
+            Some(4),
+        ))
+        .push(action(
+            2,
+            Allegiance::Villagers,
+            NightAction::Redirect,
+            Some(4),
+        ));
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![
+            NightOutcome::Redirected {
+                actor: ClientId(2),
+                from: ClientId(4)
+            },
+            NightOutcome::Protected {
+                actor: ClientId(3),
+                target: ClientId(2)
+            },
+            NightOutcome::Saved {
+                allegiance: Allegiance::Mafia,
+                target: ClientId(2)
+            },
+        ]
+    );
+
+    // Custom priorities can reorder resolution, e.g. redirect after protection.
+    let mut queue = NightQueue::new();
+
+    queue
+        .push(action(0, Allegiance::Mafia, NightAction::Kill, Some(4)))
+        .push(action(
+            3,
+            Allegiance::Villagers,
+            NightAction::Protect,
+            Some(4),
+        ))
+        .push(QueuedAction {
+            priority: NightAction::Protect.default_priority() + 1,
+            ..action(2, Allegiance::Villagers, NightAction::Redirect, Some(4))
+        });
+
+    assert_eq!(
+        queue.resolve(allegiance_of).outcomes,
+        vec![
+            NightOutcome::Protected {
+                actor: ClientId(3),
+                target: ClientId(4)
+            },
+            NightOutcome::Redirected {
+                actor: ClientId(2),
+                from: ClientId(4)
+            },
+            NightOutcome::Killed {
+                allegiance: Allegiance::Mafia,
+                target: ClientId(2)
+            },
+        ]
+    );
+}
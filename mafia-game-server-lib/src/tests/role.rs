@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::Cycle;
+use mafia_game_lib::DeathCause;
+use mafia_game_lib::Event;
+use mafia_game_lib::SpecialRole;
+use rand::rngs::mock::StepRng;
+
+use crate::Game;
+use crate::client::ClientState;
+use crate::clock::MockClock;
+use crate::consts::NIGHT_DEATH_MESSAGES;
+use crate::error::MafiaGameError;
+use crate::game::GameConfig;
+use crate::game::RevealPolicy;
+use crate::role::NightAction;
+use crate::role::Role;
+use crate::role::RoleRegistry;
+
+/// House role on the villagers' side that does nothing at night.
+#[derive(Debug)]
+pub(crate) struct Mayor;
+
+impl Role for Mayor {
+    fn special_role(&self) -> SpecialRole {
+        SpecialRole::from("Mayor")
+    }
+
+    fn allegiance(&self) -> Allegiance {
+        Allegiance::Villagers
+    }
+
+    fn night_action(&self) -> Option<NightAction> {
+        None
+    }
+}
+
+#[test]
+fn test_unregistered_role() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    client_state.connect_client("garnet").unwrap();
+    client_state.connect_client("amethyst").unwrap();
+    client_state.connect_client("pearl").unwrap();
+
+    assert!(matches!(
+        Game::start(
+            GameConfig {
+                start_cycle: Cycle::Day,
+                time_for_day: Duration::from_secs(0),
+                end_day_after_all_votes: true,
+                time_for_night: Duration::from_secs(0),
+                end_night_after_all_votes: true,
+                num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::empty(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
+            },
+            &client_state.all_client_ids(),
+            StepRng::new(1, 1),
+            clock.clone(),
+        ),
+        Err(MafiaGameError::InvalidGameConfig(_))
+    ));
+}
+
+#[test_log::test]
+fn test_house_role() {
+    let clock = Arc::new(MockClock::default());
+    let mut client_state = ClientState::new(clock.clone());
+
+    let (client1_id, _) = client_state.connect_client("garnet").unwrap();
+    let (client2_id, _) = client_state.connect_client("amethyst").unwrap();
+    let (client3_id, _) = client_state.connect_client("pearl").unwrap();
+    let (client4_id, _) = client_state.connect_client("steven").unwrap();
+    let (client5_id, _) = client_state.connect_client("connie").unwrap();
+
+    let mut roles = RoleRegistry::default();
+    roles.register(Mayor);
+
+    let mut game = Game::start(
+        GameConfig {
+            start_cycle: Cycle::Night,
+            time_for_day: Duration::from_secs(10),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(10),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([
+                (SpecialRole::Mafia, 1),
+                (SpecialRole::Doctor, 1),
+                (SpecialRole::from("Mayor"), 1),
+            ]),
+            vote_grace_period: Duration::from_secs(0),
+            roles,
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        &client_state.all_client_ids(),
+        StepRng::new(1, 1),
+        clock.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        *game.get_player_roles(),
+        HashMap::from_iter([
+            (client5_id, SpecialRole::Mafia),
+            (client1_id, SpecialRole::Doctor),
+            (client2_id, SpecialRole::from("Mayor")),
+        ]),
+    );
+    assert_eq!(
+        game.get_player_allegiance(client2_id),
+        Allegiance::Villagers
+    );
+    assert_eq!(game.get_player_allegiance(client5_id), Allegiance::Mafia);
+
+    // -- NIGHT 1 --
+    // The house role does not vote at night, while the real doctor still does.
+    assert!(matches!(
+        game.cast_vote(client2_id, Some(client1_id)),
+        Err(MafiaGameError::InvalidVote(_))
+    ));
+
+    game.cast_vote(client1_id, Some(client4_id)).unwrap();
+    game.cast_vote(client5_id, Some(client3_id)).unwrap();
+
+    // Night ends without waiting on the house role.
+    assert_eq!(
+        game.poll_end_cycle(),
+        vec![
+            Event::PlayerKilled {
+                player: client3_id,
+                cycle: Cycle::Night,
+                death_message: Box::from(NIGHT_DEATH_MESSAGES[0]),
+                reveal: None,
+                cause: DeathCause::Killed,
+            },
+            Event::SetCycle {
+                cycle: Cycle::Day,
+                day_num: 2,
+                start_time_unix_ts_secs: 0,
+                duration_secs: 10
+            }
+        ]
+    );
+}
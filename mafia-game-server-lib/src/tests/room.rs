@@ -0,0 +1,695 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::TryRecvError;
+use std::time::Duration;
+
+use mafia_game_lib::Entity;
+use mafia_game_lib::Event;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::LobbyStatus;
+use mafia_game_lib::Message;
+use mafia_game_lib::PlayerStatus;
+use mafia_game_lib::RoomInfo;
+use mafia_game_lib::SessionToken;
+use mafia_game_lib::SpecialRole;
+use rand::rngs::mock::StepRng;
+
+use crate::MafiaGameError;
+use crate::MafiaGameServer;
+use crate::MafiaGameServerConfig;
+use crate::clock::MockClock;
+use crate::event_log::MAX_LOG_ENTRIES;
+use crate::event_log::MAX_RETAINED_EVENTS;
+use crate::game::GameConfig;
+use crate::game::RevealPolicy;
+use crate::role::RoleRegistry;
+use crate::room::LOBBY;
+
+fn game_config() -> GameConfig {
+    GameConfig {
+        start_cycle: mafia_game_lib::Cycle::Day,
+        time_for_day: Duration::from_secs(10),
+        end_day_after_all_votes: true,
+        time_for_night: Duration::from_secs(10),
+        end_night_after_all_votes: true,
+        num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+        vote_grace_period: Duration::from_secs(0),
+        roles: RoleRegistry::default(),
+        reveal_on_death: RevealPolicy::Nothing,
+        trial: None,
+        max_days: 100,
+        max_players: None,
+        afk: None,
+    }
+}
+
+fn server_config(admin_token: Option<SessionToken>) -> MafiaGameServerConfig {
+    MafiaGameServerConfig {
+        max_client_inactive_time: Duration::from_secs(300),
+        max_resume_time: Duration::from_secs(60),
+        randomize_death_message: false,
+        lobby_game_config: game_config(),
+        snapshot_path: None,
+        snapshot_interval: Duration::from_secs(30),
+        admin_token,
+        min_ready_players: None,
+        start_countdown: Duration::from_secs(10),
+    }
+}
+
+/// Returns a new server along with its admin token.
+fn new_server() -> (MafiaGameServer, SessionToken) {
+    let admin = SessionToken::new();
+    let server = MafiaGameServer::new(server_config(Some(admin)), Arc::new(MockClock::default()));
+
+    (server, admin)
+}
+
+/// Returns the contents of chat messages in `token`'s inbox.
+fn take_messages(server: &MafiaGameServer, token: SessionToken) -> Vec<Box<str>> {
+    server
+        .take_events(token)
+        .unwrap()
+        .iter()
+        .filter_map(|event| match &*event.event {
+            Event::MessageReceived(message) => Some(message.contents.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_rooms() {
+    let (server, admin) = new_server();
+
+    let (_, garnet) = server.connect_client("garnet").unwrap();
+    let (amethyst_id, amethyst) = server.connect_client("amethyst").unwrap();
+    let (_, pearl) = server.connect_client("pearl").unwrap();
+
+    assert_eq!(
+        server.list_rooms(),
+        [RoomInfo {
+            name: Arc::from(LOBBY),
+            num_clients: 3,
+            in_game: false,
+        }]
+    );
+
+    server
+        .create_room(amethyst, "beach", game_config())
+        .unwrap();
+    assert!(matches!(
+        server.create_room(pearl, "beach", game_config()),
+        Err(MafiaGameError::RoomExists(_))
+    ));
+    assert!(matches!(
+        server.create_room(pearl, " ", game_config()),
+        Err(MafiaGameError::InvalidRoomName(_))
+    ));
+    assert!(matches!(
+        server.join_room(pearl, "temple"),
+        Err(MafiaGameError::NoSuchRoom(_))
+    ));
+
+    assert_eq!(&*server.client_room(amethyst).unwrap(), "beach");
+    assert_eq!(
+        server.list_rooms(),
+        [
+            RoomInfo {
+                name: Arc::from("beach"),
+                num_clients: 1,
+                in_game: false,
+            },
+            RoomInfo {
+                name: Arc::from(LOBBY),
+                num_clients: 2,
+                in_game: false,
+            },
+        ]
+    );
+
+    // Lobby clients see amethyst leave, amethyst is sent the state of the new room.
+    let garnet_events = server.take_events(garnet).unwrap();
+    assert_eq!(
+        *garnet_events.last().unwrap().event,
+        Event::ClientDisconnected(amethyst_id)
+    );
+    let amethyst_events = server.take_events(amethyst).unwrap();
+    let Event::SetServerInfo(server_info) = &*amethyst_events.last().unwrap().event else {
+        panic!("expected server info, got {:?}", amethyst_events);
+    };
+    assert_eq!(&*server_info.room, "beach");
+    assert_eq!(
+        server_info.connected_clients.keys().collect::<Vec<_>>(),
+        [&amethyst_id]
+    );
+    server.take_events(pearl).unwrap();
+
+    // Chat is scoped to the room.
+    server.send_message(garnet, "hi lobby".into()).unwrap();
+    server.send_message(amethyst, "hi beach".into()).unwrap();
+
+    assert_eq!(take_messages(&server, pearl), [Box::from("hi lobby")]);
+    assert_eq!(take_messages(&server, amethyst), [Box::from("hi beach")]);
+
+    // Admin broadcasts reach every room.
+    server.broadcast_message(admin, "hi all".into()).unwrap();
+
+    assert_eq!(take_messages(&server, pearl), [Box::from("hi all")]);
+    assert_eq!(take_messages(&server, amethyst), [Box::from("hi all")]);
+
+    // Rooms are removed once everyone left.
+    server.leave_room(amethyst).unwrap();
+    assert_eq!(server.list_rooms().len(), 1);
+    assert_eq!(&*server.client_room(amethyst).unwrap(), LOBBY);
+}
+
+#[test]
+fn test_busy_room_keeps_quiet_room_events() {
+    let (server, _) = new_server();
+
+    let (_, garnet) = server.connect_client("garnet").unwrap();
+    let (_, pearl) = server.connect_client("pearl").unwrap();
+    let (_, amethyst) = server.connect_client("amethyst").unwrap();
+
+    server
+        .create_room(amethyst, "beach", game_config())
+        .unwrap();
+    server.take_events(garnet).unwrap();
+    server.take_events(pearl).unwrap();
+
+    let last_seq = server
+        .take_events_since(garnet, 0)
+        .unwrap()
+        .last()
+        .unwrap()
+        .seq;
+
+    server.send_message(pearl, "before".into()).unwrap();
+
+    // The busy room sends far more events than a client keeps while garnet sits idle.
+    for _ in 0..10 * MAX_RETAINED_EVENTS {
+        server.send_message(amethyst, "spam".into()).unwrap();
+        server.take_events(amethyst).unwrap();
+    }
+
+    server.send_message(pearl, "after".into()).unwrap();
+
+    // Garnet gets every event sent to it, numbered without gaps.
+    let events = server.take_events(garnet).unwrap();
+    assert_eq!(
+        events.iter().map(|event| event.seq).collect::<Vec<_>>(),
+        [last_seq + 1, last_seq + 2]
+    );
+
+    // And can still resume from before the busy room got busy.
+    let events = server.take_events_since(garnet, last_seq).unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .filter_map(|event| match &*event.event {
+                Event::MessageReceived(message) => Some(&*message.contents),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        ["before", "after"]
+    );
+}
+
+#[test]
+fn test_idle_client_resyncs_after_log_cap() {
+    let (server, _) = new_server();
+
+    let (_, garnet) = server.connect_client("garnet").unwrap();
+    let (_, amethyst) = server.connect_client("amethyst").unwrap();
+
+    server
+        .create_room(amethyst, "beach", game_config())
+        .unwrap();
+    server.take_events(garnet).unwrap();
+
+    let subscription = server.subscribe(garnet).unwrap();
+    server.send_message(garnet, "lost".into()).unwrap();
+
+    // The log only holds so many entries, even if garnet never read them.
+    for _ in 0..MAX_LOG_ENTRIES {
+        server.send_message(amethyst, "spam".into()).unwrap();
+        server.take_events(amethyst).unwrap();
+    }
+
+    assert_eq!(subscription.try_recv(), Err(TryRecvError::Disconnected));
+    assert!(subscription.fell_behind());
+    assert!(matches!(
+        server.take_events(garnet),
+        Err(MafiaGameError::EventsUnavailable(_))
+    ));
+
+    // Garnet is sent the state of its room to catch up from, then gets events as usual.
+    server.send_message(garnet, "after".into()).unwrap();
+
+    let events = server.take_events(garnet).unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        &*events[0].event,
+        Event::SetServerInfo(info) if &*info.room == LOBBY
+    ));
+    assert!(matches!(
+        &*events[1].event,
+        Event::MessageReceived(message) if &*message.contents == "after"
+    ));
+}
+
+#[test]
+fn test_room_games() {
+    let (server, _) = new_server();
+
+    let lobby = ["garnet", "amethyst", "pearl"].map(|name| server.connect_client(name).unwrap());
+    let beach = ["steven", "connie", "lars"].map(|name| server.connect_client(name).unwrap());
+
+    server
+        .create_room(beach[0].1, "beach", game_config())
+        .unwrap();
+    for (_, token) in &beach[1..] {
+        server.join_room(*token, "beach").unwrap();
+    }
+
+    server
+        .start_game(lobby[0].1, LOBBY, StepRng::new(1, 1))
+        .unwrap();
+    server
+        .start_game(beach[0].1, "beach", StepRng::new(1, 1))
+        .unwrap();
+    assert!(matches!(
+        server.start_game(beach[0].1, "beach", StepRng::new(1, 1)),
+        Err(MafiaGameError::GameInProgress)
+    ));
+
+    {
+        let slf = server.0.read().unwrap();
+
+        for (room, clients) in [(LOBBY, &lobby), ("beach", &beach)] {
+            let mut players = slf.rooms[room]
+                .active_game
+                .as_ref()
+                .unwrap()
+                .get_player_statuses()
+                .keys()
+                .copied()
+                .collect::<Vec<_>>();
+            players.sort();
+
+            assert_eq!(players, clients.map(|(id, _)| id));
+        }
+    }
+
+    // Players cannot leave while the game is going.
+    assert!(matches!(
+        server.leave_room(beach[0].1),
+        Err(MafiaGameError::PlayerInGame)
+    ));
+
+    // Late joiners spectate.
+    let (_, yellow) = server.connect_client("yellow").unwrap();
+    server.join_room(yellow, "beach").unwrap();
+    server.send_message(yellow, "am I late?".into()).unwrap();
+
+    let spectator_message = Event::MessageReceived(Message {
+        channel: EventChannel::Spectator,
+        contents: Box::from("am I late?"),
+        from: Entity::Client(server.auth_client(yellow).unwrap()),
+    });
+    assert!(
+        !server
+            .take_events(lobby[0].1)
+            .unwrap()
+            .iter()
+            .any(|event| *event.event == spectator_message)
+    );
+
+    server.end_game(beach[0].1, "beach").unwrap();
+    assert!(!server.in_active_game("beach").unwrap());
+    assert!(server.in_active_game(LOBBY).unwrap());
+
+    server.leave_room(beach[0].1).unwrap();
+}
+
+#[test]
+fn test_room_hosts() {
+    let (server, admin) = new_server();
+
+    let (garnet_id, garnet) = server.connect_client("garnet").unwrap();
+    let (amethyst_id, amethyst) = server.connect_client("amethyst").unwrap();
+    let (pearl_id, pearl) = server.connect_client("pearl").unwrap();
+
+    // The first client in a room is its host, only the host can run the game.
+    assert!(matches!(
+        server.start_game(amethyst, LOBBY, StepRng::new(1, 1)),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+    assert!(matches!(
+        server.transfer_host(amethyst, amethyst_id),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+    assert!(matches!(
+        server.force_disconnect_client(amethyst, garnet_id),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+    assert!(matches!(
+        server.broadcast_message(garnet, "hi all".into()),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+
+    // Hosts can hand over to another client.
+    server.transfer_host(garnet, amethyst_id).unwrap();
+    assert_eq!(
+        *server.take_events(pearl).unwrap().last().unwrap().event,
+        Event::HostChanged(amethyst_id)
+    );
+    assert!(matches!(
+        server.end_game(garnet, LOBBY),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+
+    // Creating a room makes the creator its host, the lobby passes to the next client.
+    server
+        .create_room(amethyst, "beach", game_config())
+        .unwrap();
+    assert!(
+        server
+            .take_events(pearl)
+            .unwrap()
+            .iter()
+            .any(|event| *event.event == Event::HostChanged(garnet_id))
+    );
+    server.join_room(pearl, "beach").unwrap();
+    assert!(matches!(
+        server.start_game(pearl, "beach", StepRng::new(1, 1)),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+
+    assert!(matches!(
+        server.set_room_config(pearl, "beach", game_config()),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+    server
+        .set_room_config(amethyst, "beach", game_config())
+        .unwrap();
+
+    // The admin can do anything.
+    server.transfer_host(admin, pearl_id).unwrap();
+    assert!(matches!(
+        server.end_game(admin, "beach"),
+        Err(MafiaGameError::NoGameInProgress)
+    ));
+    server.force_disconnect_client(admin, garnet_id).unwrap();
+}
+
+#[test]
+fn test_room_spectators() {
+    let (server, _) = new_server();
+
+    let clients =
+        ["garnet", "amethyst", "pearl", "steven"].map(|name| server.connect_client(name).unwrap());
+    let (pearl_id, pearl) = clients[2];
+
+    server
+        .set_lobby_status(pearl, LobbyStatus::Spectator)
+        .unwrap();
+    assert_eq!(
+        *server
+            .take_events(clients[0].1)
+            .unwrap()
+            .last()
+            .unwrap()
+            .event,
+        Event::LobbyStatusChanged {
+            client: pearl_id,
+            status: LobbyStatus::Spectator,
+        }
+    );
+
+    server
+        .start_game(clients[0].1, LOBBY, StepRng::new(1, 1))
+        .unwrap();
+
+    {
+        let slf = server.0.read().unwrap();
+        let game = slf.rooms[LOBBY].active_game.as_ref().unwrap();
+
+        assert_eq!(game.get_player_statuses().len(), 3);
+        assert_eq!(game.get_player_status(pearl_id), None);
+    }
+
+    // Players are stuck with their choice until the game is over, spectators can join in later.
+    assert!(matches!(
+        server.set_lobby_status(clients[0].1, LobbyStatus::Spectator),
+        Err(MafiaGameError::PlayerInGame)
+    ));
+    server.set_lobby_status(pearl, LobbyStatus::Player).unwrap();
+    server
+        .set_lobby_status(pearl, LobbyStatus::Spectator)
+        .unwrap();
+
+    // Spectators chat on the spectator channel.
+    server.send_message(pearl, "go garnet!".into()).unwrap();
+    let spectator_message = Event::MessageReceived(Message {
+        channel: EventChannel::Spectator,
+        contents: Box::from("go garnet!"),
+        from: Entity::Client(pearl_id),
+    });
+    assert!(
+        server
+            .take_events(pearl)
+            .unwrap()
+            .iter()
+            .any(|event| *event.event == spectator_message)
+    );
+}
+
+#[test]
+fn test_room_ready_countdown() {
+    let clock = Arc::new(MockClock::default());
+    let server = MafiaGameServer::new(
+        MafiaGameServerConfig {
+            min_ready_players: Some(3),
+            ..server_config(None)
+        },
+        clock.clone(),
+    );
+
+    let clients =
+        ["garnet", "amethyst", "pearl", "steven"].map(|name| server.connect_client(name).unwrap());
+    let (steven_id, steven) = clients[3];
+
+    // Steven is never ready, the others are enough to start.
+    for (_, token) in &clients[..3] {
+        server.set_ready(*token, true).unwrap();
+    }
+    clock.advance(Duration::from_secs(3));
+    server.do_tick();
+    assert_eq!(
+        *server.take_events(steven).unwrap().last().unwrap().event,
+        Event::CountdownStarted {
+            start_time_unix_ts_secs: 3,
+            duration_secs: 10,
+        }
+    );
+
+    // Unreadying cancels the countdown.
+    server.set_ready(clients[0].1, false).unwrap();
+    server.do_tick();
+    assert_eq!(
+        *server.take_events(steven).unwrap().last().unwrap().event,
+        Event::CountdownCancelled
+    );
+
+    server.set_ready(clients[0].1, true).unwrap();
+    server.do_tick();
+    clock.advance(Duration::from_secs(5));
+    server.do_tick();
+    assert!(!server.in_active_game(LOBBY).unwrap());
+
+    clock.advance(Duration::from_secs(5));
+    server.do_tick();
+    assert!(server.in_active_game(LOBBY).unwrap());
+
+    {
+        let slf = server.0.read().unwrap();
+        let game = slf.rooms[LOBBY].active_game.as_ref().unwrap();
+
+        assert_eq!(game.get_player_statuses().len(), 3);
+        assert_eq!(game.get_player_status(steven_id), None);
+    }
+
+    // Everyone has to ready up again for the next game.
+    let events = server.take_events(steven).unwrap();
+    for (client_id, _) in &clients[..3] {
+        assert!(events.iter().any(|event| *event.event
+            == Event::ReadyChanged {
+                client: *client_id,
+                ready: false,
+            }));
+    }
+    assert!(matches!(
+        server.set_ready(steven, true),
+        Err(MafiaGameError::GameInProgress)
+    ));
+}
+
+#[test]
+fn test_room_substitution() {
+    let (server, admin) = new_server();
+
+    let clients =
+        ["garnet", "amethyst", "pearl", "steven"].map(|name| server.connect_client(name).unwrap());
+    let (garnet_id, garnet) = clients[0];
+    let (pearl_id, pearl) = clients[2];
+    let (steven_id, steven) = clients[3];
+
+    server
+        .set_lobby_status(steven, LobbyStatus::Spectator)
+        .unwrap();
+    server
+        .start_game(garnet, LOBBY, StepRng::new(1, 1))
+        .unwrap();
+    server.cast_vote(pearl, Some(garnet_id)).unwrap();
+    server.cast_vote(garnet, Some(pearl_id)).unwrap();
+
+    let pearl_role = {
+        let slf = server.0.read().unwrap();
+        slf.rooms[LOBBY]
+            .active_game
+            .as_ref()
+            .unwrap()
+            .get_player_role(pearl_id)
+    };
+
+    assert!(matches!(
+        server.substitute_player(pearl, pearl_id, steven_id),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+    assert!(matches!(
+        server.substitute_player(garnet, pearl_id, clients[1].0),
+        Err(MafiaGameError::InvalidSubstitution(_))
+    ));
+
+    // Only spectators can be substituted in, not clients waiting to play the next game.
+    let (connie_id, _) = server.connect_client("connie").unwrap();
+    assert!(matches!(
+        server.substitute_player(garnet, pearl_id, connie_id),
+        Err(MafiaGameError::InvalidSubstitution(_))
+    ));
+    server.take_events(steven).unwrap();
+    server.take_events(pearl).unwrap();
+
+    server
+        .substitute_player(garnet, pearl_id, steven_id)
+        .unwrap();
+
+    {
+        let slf = server.0.read().unwrap();
+        let game = slf.rooms[LOBBY].active_game.as_ref().unwrap();
+
+        assert_eq!(game.get_player_status(pearl_id), None);
+        assert_eq!(game.get_player_status(steven_id), Some(PlayerStatus::Alive));
+        assert_eq!(game.get_player_role(steven_id), pearl_role);
+        assert_eq!(game.get_votes()[&steven_id], Some(garnet_id));
+        assert_eq!(game.get_votes()[&garnet_id], Some(steven_id));
+
+        // Pearl and Steven swap lobby statuses.
+        let lobby_status = |client_id| {
+            slf.clients
+                .get_client(client_id)
+                .unwrap()
+                .get_info()
+                .lobby_status
+        };
+        assert_eq!(lobby_status(pearl_id), LobbyStatus::Spectator);
+        assert_eq!(lobby_status(steven_id), LobbyStatus::Player);
+    }
+
+    // Steven is told about the game from his new point of view.
+    let events = server.take_events(steven).unwrap();
+    assert_eq!(
+        events[..3]
+            .iter()
+            .map(|event| (*event.event).clone())
+            .collect::<Vec<_>>(),
+        [
+            Event::PlayerReplaced {
+                player: pearl_id,
+                replacement: steven_id,
+            },
+            Event::LobbyStatusChanged {
+                client: pearl_id,
+                status: LobbyStatus::Spectator,
+            },
+            Event::LobbyStatusChanged {
+                client: steven_id,
+                status: LobbyStatus::Player,
+            },
+        ]
+    );
+    let Event::SetGame(game_info) = &*events[3].event else {
+        panic!("expected game info, got {:?}", events);
+    };
+    assert_eq!(
+        game_info.player_status.get(&steven_id),
+        Some(&PlayerStatus::Alive)
+    );
+
+    // So is Pearl, who no longer plays.
+    let events = server.take_events(pearl).unwrap();
+    let Event::SetGame(game_info) = &*events.last().unwrap().event else {
+        panic!("expected game info, got {:?}", events);
+    };
+    assert_eq!(game_info.player_status.get(&pearl_id), None);
+
+    // Pearl is free to go, and the substitution is replayed from the game log, which only admins
+    // can read.
+    server.leave_room(pearl).unwrap();
+    assert!(matches!(
+        server.game_log(garnet, LOBBY),
+        Err(MafiaGameError::PermissionDenied)
+    ));
+    server
+        .game_log(admin, LOBBY)
+        .unwrap()
+        .replay(&RoleRegistry::default())
+        .unwrap();
+}
+
+#[test]
+fn test_room_stale_client_ids() {
+    let clock = Arc::new(MockClock::default());
+    let server = MafiaGameServer::new(server_config(None), clock.clone());
+
+    let clients =
+        ["garnet", "amethyst", "pearl", "steven"].map(|name| server.connect_client(name).unwrap());
+    let (_, garnet) = clients[0];
+    let (amethyst_id, amethyst) = clients[1];
+
+    // Amethyst leaves and is purged, Connie takes their slot.
+    server.disconnect_client(amethyst).unwrap();
+    clock.advance(Duration::from_secs(61));
+    server.do_tick();
+
+    let (connie_id, _) = server.connect_client("connie").unwrap();
+    assert_eq!(connie_id.slot(), amethyst_id.slot());
+    assert_ne!(connie_id, amethyst_id);
+
+    // Amethyst's ID does not refer to Connie.
+    assert!(matches!(
+        server.transfer_host(garnet, amethyst_id),
+        Err(MafiaGameError::InvalidClientId(_))
+    ));
+
+    server
+        .start_game(garnet, LOBBY, StepRng::new(1, 1))
+        .unwrap();
+    assert!(matches!(
+        server.cast_vote(garnet, Some(amethyst_id)),
+        Err(MafiaGameError::InvalidClientId(_))
+    ));
+    server.cast_vote(garnet, Some(connie_id)).unwrap();
+}
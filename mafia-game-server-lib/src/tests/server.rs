@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 use insta::assert_json_snapshot;
@@ -6,31 +7,27 @@ use rand::rngs::mock::StepRng;
 
 use crate::MafiaGameServer;
 use crate::MafiaGameServerConfig;
+use crate::clock::MockClock;
 use crate::game::GameConfig;
+use crate::game::RevealPolicy;
+use crate::role::RoleRegistry;
+use crate::room::LOBBY;
 use mafia_game_lib::Allegiance;
 use mafia_game_lib::Cycle;
+use mafia_game_lib::RoleReveal;
+use mafia_game_lib::SessionToken;
 use mafia_game_lib::SpecialRole;
+use mafia_game_lib::Winners;
 
 #[test_log::test]
 fn test_server_messages() {
-    let server = MafiaGameServer::new(MafiaGameServerConfig {
-        max_client_inactive_time: Duration::from_secs(300),
-        randomize_death_message: false,
-    });
-
-    let (client0_id, client0_token) = server.connect_client("garnet").unwrap();
-    let (client1_id, client1_token) = server.connect_client("amethyst").unwrap();
-    let (client2_id, client2_token) = server.connect_client("pearl").unwrap();
-    let (client3_id, client3_token) = server.connect_client("steven").unwrap();
-    let (_client4_id, client4_token) = server.connect_client("connie").unwrap();
-    let (_client5_id, client5_token) = server.connect_client("pink").unwrap();
-    let (client6_id, client6_token) = server.connect_client("blue").unwrap();
-
-    server.broadcast_message(Box::from("game is starting!"));
-
-    server
-        .start_game(
-            GameConfig {
+    let admin = SessionToken::new();
+    let server = MafiaGameServer::new(
+        MafiaGameServerConfig {
+            max_client_inactive_time: Duration::from_secs(300),
+            max_resume_time: Duration::from_secs(60),
+            randomize_death_message: false,
+            lobby_game_config: GameConfig {
                 start_cycle: Cycle::Day,
                 time_for_day: Duration::from_secs(10),
                 end_day_after_all_votes: true,
@@ -42,19 +39,43 @@ fn test_server_messages() {
                     (SpecialRole::Doctor, 1),
                 ]),
                 vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Nothing,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
             },
-            StepRng::new(1, 1),
-        )
+            snapshot_path: None,
+            snapshot_interval: Duration::from_secs(30),
+            admin_token: Some(admin),
+            min_ready_players: None,
+            start_countdown: Duration::from_secs(10),
+        },
+        Arc::new(MockClock::default()),
+    );
+
+    let (client0_id, client0_token) = server.connect_client("garnet").unwrap();
+    let (client1_id, client1_token) = server.connect_client("amethyst").unwrap();
+    let (client2_id, client2_token) = server.connect_client("pearl").unwrap();
+    let (client3_id, client3_token) = server.connect_client("steven").unwrap();
+    let (_client4_id, client4_token) = server.connect_client("connie").unwrap();
+    let (_client5_id, client5_token) = server.connect_client("pink").unwrap();
+    let (client6_id, client6_token) = server.connect_client("blue").unwrap();
+
+    server
+        .broadcast_message(admin, Box::from("game is starting!"))
+        .unwrap();
+
+    server
+        .start_game(client0_token, LOBBY, StepRng::new(1, 1))
         .unwrap();
 
     // Joined after the game started.
     let (_client7_id, client7_token) = server.connect_client("yellow").unwrap();
 
     assert_eq!(
-        *server
-            .0
-            .read()
-            .unwrap()
+        *server.0.read().unwrap().rooms[LOBBY]
             .active_game
             .as_ref()
             .unwrap()
@@ -184,7 +205,9 @@ fn test_server_messages() {
     server.cast_vote(client7_token, None).unwrap_err();
 
     // -- VILLAGERS WIN --
-    server.broadcast_message(Box::from("villagers won"));
+    server
+        .broadcast_message(admin, Box::from("villagers won"))
+        .unwrap();
 
     server.send_message(client0_token, "shit".into()).unwrap();
     server.send_message(client6_token, "gg".into()).unwrap();
@@ -196,15 +219,12 @@ fn test_server_messages() {
         .unwrap();
 
     assert_eq!(
-        server
-            .0
-            .read()
-            .unwrap()
+        server.0.read().unwrap().rooms[LOBBY]
             .active_game
             .as_ref()
             .unwrap()
             .get_winner(),
-        Some(Allegiance::Villagers)
+        Some(&Winners::from(Allegiance::Villagers))
     );
 
     insta::with_settings!({sort_maps => true}, {
@@ -219,3 +239,89 @@ fn test_server_messages() {
         assert_json_snapshot!(server.take_events(client8_token).unwrap());
     });
 }
+
+#[test_log::test]
+fn test_server_reveal_on_death() {
+    let admin = SessionToken::new();
+    let server = MafiaGameServer::new(
+        MafiaGameServerConfig {
+            max_client_inactive_time: Duration::from_secs(300),
+            max_resume_time: Duration::from_secs(60),
+            randomize_death_message: false,
+            lobby_game_config: GameConfig {
+                start_cycle: Cycle::Day,
+                time_for_day: Duration::from_secs(10),
+                end_day_after_all_votes: true,
+                time_for_night: Duration::from_secs(10),
+                end_night_after_all_votes: true,
+                num_special_roles: HashMap::from_iter([
+                    (SpecialRole::Mafia, 1),
+                    (SpecialRole::Doctor, 1),
+                ]),
+                vote_grace_period: Duration::from_secs(0),
+                roles: RoleRegistry::default(),
+                reveal_on_death: RevealPolicy::Role,
+                trial: None,
+                max_days: 100,
+                max_players: None,
+                afk: None,
+            },
+            snapshot_path: None,
+            snapshot_interval: Duration::from_secs(30),
+            admin_token: Some(admin),
+            min_ready_players: None,
+            start_countdown: Duration::from_secs(10),
+        },
+        Arc::new(MockClock::default()),
+    );
+
+    let (client0_id, client0_token) = server.connect_client("garnet").unwrap();
+    let (client1_id, client1_token) = server.connect_client("amethyst").unwrap();
+    let (_client2_id, client2_token) = server.connect_client("pearl").unwrap();
+    let (_client3_id, client3_token) = server.connect_client("steven").unwrap();
+    let (client4_id, client4_token) = server.connect_client("connie").unwrap();
+
+    server
+        .start_game(client0_token, LOBBY, StepRng::new(1, 1))
+        .unwrap();
+
+    assert_eq!(
+        *server.0.read().unwrap().rooms[LOBBY]
+            .active_game
+            .as_ref()
+            .unwrap()
+            .get_player_roles(),
+        HashMap::from_iter([
+            (client4_id, SpecialRole::Mafia),
+            (client0_id, SpecialRole::Doctor)
+        ]),
+    );
+
+    // -- DAY 1 --
+    server.cast_vote(client0_token, None).unwrap();
+    server.cast_vote(client1_token, Some(client0_id)).unwrap();
+    server.cast_vote(client2_token, Some(client0_id)).unwrap();
+    server.cast_vote(client3_token, Some(client0_id)).unwrap();
+    server.cast_vote(client4_token, Some(client0_id)).unwrap();
+
+    // -- NIGHT 1 --
+    // Living villagers can see the role of the dead doctor.
+    let game_info = server.0.read().unwrap().rooms[LOBBY]
+        .get_game_info_for(client1_id)
+        .unwrap();
+
+    assert_eq!(
+        game_info.player_to_role,
+        HashMap::from_iter([(client0_id, SpecialRole::Doctor)])
+    );
+    assert_eq!(
+        game_info.death_reveals,
+        HashMap::from_iter([(
+            client0_id,
+            RoleReveal::Role {
+                allegiance: Allegiance::Villagers,
+                role: Some(SpecialRole::Doctor)
+            }
+        )])
+    );
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mafia_game_lib::Cycle;
+use mafia_game_lib::Event;
+use mafia_game_lib::SpecialRole;
+use rand::rngs::mock::StepRng;
+
+use crate::MafiaGameError;
+use crate::MafiaGameServer;
+use crate::MafiaGameServerConfig;
+use crate::clock::MockClock;
+use crate::game::GameConfig;
+use crate::game::RevealPolicy;
+use crate::role::RoleRegistry;
+use crate::room::LOBBY;
+
+fn config(snapshot_path: Option<PathBuf>) -> MafiaGameServerConfig {
+    MafiaGameServerConfig {
+        max_client_inactive_time: Duration::from_secs(300),
+        max_resume_time: Duration::from_secs(60),
+        randomize_death_message: false,
+        lobby_game_config: GameConfig {
+            start_cycle: Cycle::Day,
+            time_for_day: Duration::from_secs(10),
+            end_day_after_all_votes: true,
+            time_for_night: Duration::from_secs(10),
+            end_night_after_all_votes: true,
+            num_special_roles: HashMap::from_iter([(SpecialRole::Mafia, 1)]),
+            vote_grace_period: Duration::from_secs(0),
+            roles: RoleRegistry::default(),
+            reveal_on_death: RevealPolicy::Nothing,
+            trial: None,
+            max_days: 100,
+            max_players: None,
+            afk: None,
+        },
+        snapshot_path,
+        snapshot_interval: Duration::from_secs(30),
+        admin_token: None,
+        min_ready_players: None,
+        start_countdown: Duration::from_secs(10),
+    }
+}
+
+/// Returns a path in the temp dir unique to this test process.
+fn snapshot_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mafia-{}-{}.json", name, std::process::id()))
+}
+
+#[test]
+fn test_save_restore() {
+    let clock = Arc::new(MockClock::default());
+    let server = MafiaGameServer::new(config(None), clock.clone());
+
+    let clients = ["garnet", "amethyst", "pearl"].map(|name| server.connect_client(name).unwrap());
+
+    server
+        .start_game(clients[0].1, LOBBY, StepRng::new(1, 1))
+        .unwrap();
+    server.cast_vote(clients[0].1, Some(clients[2].0)).unwrap();
+
+    let last_seqs = clients.map(|(_, token)| {
+        server
+            .take_events(token)
+            .unwrap()
+            .last()
+            .map_or(0, |event| event.seq)
+    });
+
+    let (roles, votes) = {
+        let slf = server.0.read().unwrap();
+        let game = slf.rooms[LOBBY].active_game.as_ref().unwrap();
+
+        (game.get_player_roles().clone(), game.get_votes().clone())
+    };
+
+    let path = snapshot_path("save-restore");
+    server.save(&path).unwrap();
+    drop(server);
+
+    let server = MafiaGameServer::restore(&path, config(None), clock.clone()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    {
+        let slf = server.0.read().unwrap();
+        let game = slf.rooms[LOBBY].active_game.as_ref().unwrap();
+
+        assert_eq!(game.get_player_roles(), &roles);
+        assert_eq!(game.get_votes(), &votes);
+        assert_eq!(game.get_cycle(), Cycle::Day);
+        assert_eq!(game.get_day_num(), 1);
+    }
+
+    // Clients come back disconnected, and keep their session token when resuming.
+    assert!(matches!(
+        server.auth_client(clients[0].1),
+        Err(MafiaGameError::ClientDisconnected(_))
+    ));
+
+    for (((client_id, token), last_seq), name) in clients
+        .iter()
+        .zip(last_seqs)
+        .zip(["garnet", "amethyst", "pearl"])
+    {
+        assert_eq!(server.resume_client(*token).unwrap(), *client_id);
+
+        // Event numbers carry on where they left off.
+        let events = server.take_events_since(*token, last_seq).unwrap();
+        assert_eq!(events.first().unwrap().seq, last_seq + 1);
+
+        let Event::SetServerInfo(server_info) = &*events.last().unwrap().event else {
+            panic!("expected server info for {}, got {:?}", name, events);
+        };
+        assert!(server_info.active_game.is_some());
+    }
+
+    // The game continues where it left off.
+    server.cast_vote(clients[1].1, None).unwrap();
+    server.cast_vote(clients[2].1, None).unwrap();
+
+    let slf = server.0.read().unwrap();
+    let game = slf.rooms[LOBBY].active_game.as_ref().unwrap();
+    assert_eq!(game.get_cycle(), Cycle::Night);
+}
+
+#[test]
+fn test_ticker_saves_on_shutdown() {
+    let path = snapshot_path("ticker");
+    let server = MafiaGameServer::new(config(Some(path.clone())), Arc::new(MockClock::default()));
+
+    let (_, token) = server.connect_client("garnet").unwrap();
+
+    let (shutdown, ticker) = server.start_server_ticker(Duration::from_millis(10));
+    shutdown.do_shutdown();
+    ticker.join().unwrap();
+
+    let server =
+        MafiaGameServer::restore(&path, config(None), Arc::new(MockClock::default())).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    server.resume_client(token).unwrap();
+    assert_eq!(&*server.client_room(token).unwrap(), LOBBY);
+}
@@ -0,0 +1,134 @@
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+
+use crate::role::Jester;
+use crate::role::Role;
+use crate::role::SerialKiller;
+use crate::win::LivingPlayer;
+use crate::win::WinContext;
+use crate::win::faction_has_won;
+use crate::win::is_stalemate;
+
+fn player(id: u64, allegiance: Allegiance, is_killer: bool) -> LivingPlayer {
+    LivingPlayer {
+        id: ClientId(id),
+        allegiance,
+        is_killer,
+    }
+}
+
+#[test]
+fn test_faction_has_won() {
+    let alive = [
+        player(0, Allegiance::Villagers, false),
+        player(1, Allegiance::Villagers, false),
+        player(2, Allegiance::Mafia, true),
+    ];
+    let ctx = WinContext {
+        alive: &alive,
+        lynched: None,
+    };
+
+    assert!(!faction_has_won(Allegiance::Villagers, &ctx));
+    assert!(!faction_has_won(Allegiance::Mafia, &ctx));
+    assert!(!faction_has_won(Allegiance::Neutral, &ctx));
+
+    let ctx = WinContext {
+        alive: &alive[1..],
+        lynched: Some(ClientId(0)),
+    };
+
+    assert!(!faction_has_won(Allegiance::Villagers, &ctx));
+    assert!(faction_has_won(Allegiance::Mafia, &ctx));
+
+    // A neutral killer left alive blocks both factions from winning.
+    let alive = [
+        player(0, Allegiance::Villagers, false),
+        player(1, Allegiance::Neutral, true),
+    ];
+    let ctx = WinContext {
+        alive: &alive,
+        lynched: None,
+    };
+
+    assert!(!faction_has_won(Allegiance::Villagers, &ctx));
+    assert!(!faction_has_won(Allegiance::Mafia, &ctx));
+
+    // Other neutral players do not.
+    let alive = [
+        player(0, Allegiance::Villagers, false),
+        player(1, Allegiance::Neutral, false),
+    ];
+    let ctx = WinContext {
+        alive: &alive,
+        lynched: None,
+    };
+
+    assert!(faction_has_won(Allegiance::Villagers, &ctx));
+}
+
+#[test]
+fn test_neutral_has_won() {
+    let alive = [
+        player(0, Allegiance::Villagers, false),
+        player(1, Allegiance::Neutral, true),
+    ];
+    let ctx = WinContext {
+        alive: &alive,
+        lynched: Some(ClientId(2)),
+    };
+
+    assert!(Jester.has_won(ClientId(2), &ctx));
+    assert!(!Jester.has_won(ClientId(3), &ctx));
+    assert!(!SerialKiller.has_won(ClientId(1), &ctx));
+
+    let ctx = WinContext {
+        alive: &alive[1..],
+        lynched: None,
+    };
+
+    assert!(SerialKiller.has_won(ClientId(1), &ctx));
+}
+
+#[test]
+fn test_is_stalemate() {
+    let alive = [
+        player(0, Allegiance::Villagers, false),
+        player(1, Allegiance::Neutral, false),
+        player(2, Allegiance::Neutral, false),
+    ];
+    let ctx = WinContext {
+        alive: &alive,
+        lynched: None,
+    };
+
+    assert!(!is_stalemate(&ctx));
+
+    let ctx = WinContext {
+        alive: &alive[1..],
+        lynched: None,
+    };
+
+    assert!(is_stalemate(&ctx));
+    assert!(!faction_has_won(Allegiance::Villagers, &ctx));
+
+    let alive = [
+        player(0, Allegiance::Neutral, false),
+        player(1, Allegiance::Neutral, true),
+    ];
+    let ctx = WinContext {
+        alive: &alive,
+        lynched: None,
+    };
+
+    assert!(!is_stalemate(&ctx));
+
+    // No one left alive to win.
+    let ctx = WinContext {
+        alive: &[],
+        lynched: None,
+    };
+
+    assert!(is_stalemate(&ctx));
+    assert!(!faction_has_won(Allegiance::Villagers, &ctx));
+}
@@ -0,0 +1,74 @@
+//! Win conditions, checked at the end of every cycle.
+//!
+//! Players in the [`Allegiance::Mafia`] and [`Allegiance::Villagers`] factions win together when
+//! [`faction_has_won`] holds for their faction. [`Allegiance::Neutral`] players win on their own
+//! through [`Role::has_won`](crate::role::Role::has_won). A neutral player that won stays a winner
+//! for the rest of the game.
+//!
+//! The game ends once a faction has won, or once every living player has won. Otherwise it ends
+//! in a draw if [`is_stalemate`] holds.
+
+use mafia_game_lib::Allegiance;
+use mafia_game_lib::ClientId;
+
+/// A living player, as seen by win conditions.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct LivingPlayer {
+    pub id: ClientId,
+    pub allegiance: Allegiance,
+    /// Whether the player can kill at night.
+    pub is_killer: bool,
+}
+
+/// State of the game at the end of a cycle.
+#[derive(Copy, Clone, Debug)]
+pub struct WinContext<'a> {
+    pub alive: &'a [LivingPlayer],
+    /// Player lynched in the cycle that just ended, if any.
+    pub lynched: Option<ClientId>,
+}
+
+/// Returns `true` if the given faction met its win condition. Always `false` for
+/// [`Allegiance::Neutral`].
+///
+/// * Villagers win once no Mafia and no neutral killers are left alive, as long as at least one
+///   villager is.
+/// * Mafia win once they make up at least half of the living players and no neutral killers are
+///   left alive.
+pub fn faction_has_won(faction: Allegiance, ctx: &WinContext) -> bool {
+    let neutral_killer_alive = ctx
+        .alive
+        .iter()
+        .any(|player| player.allegiance == Allegiance::Neutral && player.is_killer);
+
+    match faction {
+        Allegiance::Villagers => {
+            !neutral_killer_alive
+                && ctx
+                    .alive
+                    .iter()
+                    .all(|player| player.allegiance != Allegiance::Mafia)
+                && ctx
+                    .alive
+                    .iter()
+                    .any(|player| player.allegiance == Allegiance::Villagers)
+        }
+        Allegiance::Mafia => {
+            let num_mafia_alive = ctx
+                .alive
+                .iter()
+                .filter(|player| player.allegiance == Allegiance::Mafia)
+                .count();
+
+            !neutral_killer_alive && num_mafia_alive > 0 && num_mafia_alive * 2 >= ctx.alive.len()
+        }
+        Allegiance::Neutral => false,
+    }
+}
+
+/// Returns `true` if no one can be killed anymore: either no one is left alive, or no living player
+/// can kill at night and too few are alive for a day vote to lynch anyone.
+pub fn is_stalemate(ctx: &WinContext) -> bool {
+    ctx.alive.is_empty()
+        || (ctx.alive.len() <= 2 && !ctx.alive.iter().any(|player| player.is_killer))
+}
@@ -0,0 +1,309 @@
+//! Serves a single client over a TCP connection.
+
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use mafia_game_lib::ClientId;
+use mafia_game_lib::Request;
+use mafia_game_lib::Response;
+use mafia_game_lib::ServerMessage;
+use mafia_game_lib::SessionToken;
+use mafia_game_server_lib::MafiaGameError;
+use mafia_game_server_lib::MafiaGameServer;
+use mafia_game_server_lib::game::GameConfig;
+
+/// How long the event pusher waits for events before checking if it should stop.
+const EVENT_WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Client connected over this connection.
+struct Session {
+    session_token: SessionToken,
+    /// Sequence number of the last event the client got before reconnecting.
+    resume_from: Option<u64>,
+    stop: Arc<AtomicBool>,
+    /// Pushes events to the client, started once the client received its session.
+    pusher: Option<JoinHandle<()>>,
+}
+
+struct Connection {
+    server: MafiaGameServer,
+    /// Settings for rooms created over this connection.
+    game_config: Arc<GameConfig>,
+    writer: Arc<Mutex<TcpStream>>,
+    session: Option<Session>,
+}
+
+/// Serves requests from `stream` until the client closes the connection. The client is
+/// disconnected from the server when the connection closes.
+pub(crate) fn handle_connection(
+    server: MafiaGameServer,
+    game_config: Arc<GameConfig>,
+    stream: TcpStream,
+) -> io::Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+
+    let mut connection = Connection {
+        server,
+        game_config,
+        writer: Arc::new(Mutex::new(stream)),
+        session: None,
+    };
+
+    let result = connection.serve(reader);
+    connection.end_session();
+
+    result
+}
+
+/// Writes `message` to `writer` as a single line.
+fn send(writer: &Mutex<TcpStream>, message: &ServerMessage) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+
+    writer.lock().unwrap().write_all(&line)
+}
+
+impl Connection {
+    fn serve<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => self.handle_request(request),
+                Err(e) => Response::Error(format!("invalid request: {}", e)),
+            };
+
+            send(&self.writer, &ServerMessage::Response(response))?;
+
+            // Only push events once the client knows it is connected.
+            if let Some(session) = &mut self.session
+                && session.pusher.is_none()
+            {
+                session.pusher = Some(spawn_event_pusher(
+                    self.server.clone(),
+                    session.session_token,
+                    session.resume_from,
+                    Arc::clone(&self.writer),
+                    Arc::clone(&session.stop),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        match &request {
+            Request::Connect { name, resume_from } => {
+                return self.start_session(*resume_from, |server| server.connect_client(name));
+            }
+            Request::Resume {
+                session_token,
+                resume_from,
+            } => {
+                return self.start_session(*resume_from, |server| {
+                    server
+                        .resume_client(*session_token)
+                        .map(|id| (id, *session_token))
+                });
+            }
+            _ => {}
+        }
+
+        let Some(session_token) = self.session.as_ref().map(|session| session.session_token) else {
+            return Response::Error("must connect first".to_string());
+        };
+
+        let result = match request {
+            Request::Connect {
+                name: _,
+                resume_from: _,
+            }
+            | Request::Resume {
+                session_token: _,
+                resume_from: _,
+            } => unreachable!("handled above"),
+            Request::Disconnect => {
+                self.end_session();
+
+                Ok(())
+            }
+            Request::SendMessage { contents } => self.server.send_message(session_token, contents),
+            Request::CastVote { target } => self.server.cast_vote(session_token, target),
+            Request::CastVerdict { verdict } => self.server.cast_verdict(session_token, verdict),
+            Request::StartGame => self
+                .server
+                .client_room(session_token)
+                .and_then(|room| self.server.start_game(session_token, &room, rand::rng())),
+            Request::EndGame => self
+                .server
+                .client_room(session_token)
+                .and_then(|room| self.server.end_game(session_token, &room)),
+            Request::TransferHost { to } => self.server.transfer_host(session_token, to),
+            Request::SetLobbyStatus { status } => {
+                self.server.set_lobby_status(session_token, status)
+            }
+            Request::SetReady { ready } => self.server.set_ready(session_token, ready),
+            Request::SubstitutePlayer {
+                player,
+                replacement,
+            } => self
+                .server
+                .substitute_player(session_token, player, replacement),
+            Request::ListRooms => return Response::Rooms(self.server.list_rooms()),
+            Request::CreateRoom { name } => {
+                self.server
+                    .create_room(session_token, &name, GameConfig::clone(&self.game_config))
+            }
+            Request::JoinRoom { name } => self.server.join_room(session_token, &name),
+            Request::LeaveRoom => self.server.leave_room(session_token),
+        };
+
+        match result {
+            Ok(()) => Response::Ok,
+            Err(e) => Response::Error(e.to_string()),
+        }
+    }
+
+    /// Connects a client with `connect`, unless one is connected already.
+    fn start_session(
+        &mut self,
+        resume_from: Option<u64>,
+        connect: impl FnOnce(&MafiaGameServer) -> Result<(ClientId, SessionToken), MafiaGameError>,
+    ) -> Response {
+        if self.session.is_some() {
+            return Response::Error("already connected".to_string());
+        }
+
+        match connect(&self.server) {
+            Ok((id, session_token)) => {
+                self.session = Some(Session {
+                    session_token,
+                    resume_from,
+                    stop: Arc::new(AtomicBool::new(false)),
+                    pusher: None,
+                });
+
+                Response::Connected { id, session_token }
+            }
+            Err(e) => Response::Error(e.to_string()),
+        }
+    }
+
+    /// Stops pushing events and disconnects the client, if connected.
+    fn end_session(&mut self) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+
+        // Disconnecting first ends the event pusher's subscription if it is waiting for events.
+        if let Err(e) = self.server.disconnect_client(session.session_token) {
+            tracing::debug!("failed to disconnect client: {}", e);
+        }
+
+        session.stop.store(true, Ordering::Relaxed);
+
+        if let Some(pusher) = session.pusher {
+            pusher.join().expect("event pusher does not panic");
+        }
+    }
+}
+
+fn spawn_event_pusher(
+    server: MafiaGameServer,
+    session_token: SessionToken,
+    resume_from: Option<u64>,
+    writer: Arc<Mutex<TcpStream>>,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        // If the events are gone, the client still gets the server info sent on reconnect to
+        // catch up.
+        if let Some(seq) = resume_from {
+            let events = server.take_events_since(session_token, seq).or_else(|e| {
+                tracing::info!("failed to resume events: {}", e);
+                server.take_events(session_token)
+            });
+
+            match events {
+                Ok(events) => {
+                    for event in events {
+                        if let Err(e) = send(&writer, &ServerMessage::Event(event)) {
+                            tracing::info!("stopped pushing events: {}", e);
+                            return;
+                        }
+                    }
+                }
+                // The client is sent the state of its room to catch up from instead.
+                Err(MafiaGameError::EventsUnavailable(_)) => {}
+                Err(e) => {
+                    tracing::info!("stopped pushing events: {}", e);
+                    return;
+                }
+            }
+        }
+
+        loop {
+            let events = match server.subscribe(session_token) {
+                Ok(events) => events,
+                Err(e) => {
+                    tracing::info!("stopped pushing events: {}", e);
+                    return;
+                }
+            };
+
+            while !stop.load(Ordering::Relaxed) {
+                let event = match events.recv_timeout(EVENT_WAIT_TIMEOUT) {
+                    Ok(event) => event,
+                    // Authenticating keeps the client from being purged while the connection is
+                    // idle.
+                    Err(RecvTimeoutError::Timeout) => match server.auth_client(session_token) {
+                        Ok(_) => continue,
+                        Err(e) => {
+                            tracing::info!("stopped pushing events: {}", e);
+                            return;
+                        }
+                    },
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                if let Err(e) = send(&writer, &ServerMessage::Event(event)) {
+                    tracing::info!("stopped pushing events: {}", e);
+                    return;
+                }
+            }
+
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+
+            if !events.fell_behind() {
+                tracing::info!("stopped pushing events: client disconnected");
+                return;
+            }
+
+            // Taking events sends the client the state of its room to catch up from.
+            if let Err(e) = server.take_events(session_token)
+                && !matches!(e, MafiaGameError::EventsUnavailable(_))
+            {
+                tracing::info!("stopped pushing events: {}", e);
+                return;
+            }
+        }
+    })
+}
@@ -0,0 +1,128 @@
+//! TCP server for the Mafia game.
+//!
+//! Clients speak a JSON-lines protocol: each line sent to the server is a
+//! [`Request`](mafia_game_lib::Request), each line sent back is a
+//! [`ServerMessage`](mafia_game_lib::ServerMessage).
+//!
+//! Usage: `mafia-game-server [ADDR] [SNAPSHOT_PATH] [CONFIG_PATH]`. If a snapshot path is given,
+//! the server state is saved there periodically and restored from it on startup. The config is a
+//! TOML or JSON [`MafiaGameServerConfig`], see [`config`] for the format. Without one, the lobby
+//! plays the default preset and games start once enough players are ready.
+//!
+//! The server runs until it gets SIGINT or SIGTERM, then saves a final snapshot and exits.
+
+use std::io;
+use std::net::TcpListener;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use mafia_game_server_lib::MafiaGameServer;
+use mafia_game_server_lib::MafiaGameServerConfig;
+use mafia_game_server_lib::clock::SystemClock;
+use mafia_game_server_lib::config;
+use mafia_game_server_lib::game::GameConfig;
+
+mod connection;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:4567";
+const TICK_RATE: Duration = Duration::from_millis(100);
+/// Enough players for the default game's special roles plus a couple of villagers.
+const MIN_READY_PLAYERS: usize = 5;
+
+fn main() -> io::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    let snapshot_path = args.next().map(PathBuf::from);
+    let config_path = args.next().map(PathBuf::from);
+
+    let mut config = match config_path {
+        Some(path) => load_config(&path)?,
+        None => MafiaGameServerConfig {
+            min_ready_players: Some(MIN_READY_PLAYERS),
+            ..Default::default()
+        },
+    };
+    if snapshot_path.is_some() {
+        config.snapshot_path = snapshot_path;
+    }
+    let snapshot_path = config.snapshot_path.clone();
+    // Rooms created by clients play the same game as the lobby.
+    let game_config = Arc::new(config.lobby_game_config.clone());
+
+    let server = match snapshot_path.filter(|path| path.exists()) {
+        Some(path) => {
+            tracing::info!("restoring server state from {:?}", path);
+
+            MafiaGameServer::restore(&path, config, Arc::new(SystemClock))
+                .map_err(|e| io::Error::other(e.to_string()))?
+        }
+        None => MafiaGameServer::new(config, Arc::new(SystemClock)),
+    };
+    let (shutdown, ticker) = server.start_server_ticker(TICK_RATE);
+
+    let listener = TcpListener::bind(&addr)?;
+    tracing::info!("listening on {}", listener.local_addr()?);
+
+    // Connections are accepted on their own thread, so the main thread is free to wait for a
+    // signal to stop.
+    let (stop_sender, stop) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop_sender.send(());
+    })
+    .map_err(io::Error::other)?;
+
+    thread::spawn(move || accept_connections(listener, server, game_config));
+
+    stop.recv().expect("signal handler is never dropped");
+    tracing::info!("shutting down");
+
+    // Stopping the ticker saves the final snapshot, the accept thread dies with the process.
+    shutdown.do_shutdown();
+    ticker.join().expect("ticker thread does not panic");
+
+    Ok(())
+}
+
+fn accept_connections(
+    listener: TcpListener,
+    server: MafiaGameServer,
+    game_config: Arc<GameConfig>,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!("failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        let server = server.clone();
+        let game_config = Arc::clone(&game_config);
+
+        thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+
+            if let Err(e) = connection::handle_connection(server, game_config, stream) {
+                tracing::info!("connection from {:?} closed with error: {}", peer, e);
+            }
+        });
+    }
+}
+
+fn load_config(path: &Path) -> io::Result<MafiaGameServerConfig> {
+    tracing::info!("loading config from {:?}", path);
+
+    config::load_server_config(path).map_err(|e| io::Error::other(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    mod connection;
+}
@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use mafia_game_lib::ClientId;
+use mafia_game_lib::Entity;
+use mafia_game_lib::Event;
+use mafia_game_lib::EventChannel;
+use mafia_game_lib::Message;
+use mafia_game_lib::Request;
+use mafia_game_lib::Response;
+use mafia_game_lib::ServerMessage;
+use mafia_game_server_lib::MafiaGameServer;
+use mafia_game_server_lib::MafiaGameServerConfig;
+use mafia_game_server_lib::clock::SystemClock;
+use mafia_game_server_lib::config;
+
+use crate::connection::handle_connection;
+
+struct TestClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Events received while waiting for a response.
+    events: VecDeque<Arc<Event>>,
+    /// Sequence number of the last event received.
+    last_seq: u64,
+}
+
+impl TestClient {
+    fn connect(addr: SocketAddr) -> Self {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        TestClient {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            writer: stream,
+            events: VecDeque::new(),
+            last_seq: 0,
+        }
+    }
+
+    fn send_line(&mut self, line: &str) {
+        self.writer.write_all(line.as_bytes()).unwrap();
+        self.writer.write_all(b"\n").unwrap();
+    }
+
+    fn recv(&mut self) -> ServerMessage {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+
+        let message = serde_json::from_str(&line).unwrap();
+
+        if let ServerMessage::Event(event) = &message {
+            assert_eq!(event.seq, self.last_seq + 1, "events are numbered in order");
+            self.last_seq = event.seq;
+        }
+
+        message
+    }
+
+    fn request(&mut self, request: Request) -> Response {
+        self.send_line(&serde_json::to_string(&request).unwrap());
+
+        loop {
+            match self.recv() {
+                ServerMessage::Response(response) => return response,
+                ServerMessage::Event(event) => self.events.push_back(event.event),
+            }
+        }
+    }
+
+    fn next_event(&mut self) -> Arc<Event> {
+        if let Some(event) = self.events.pop_front() {
+            return event;
+        }
+
+        match self.recv() {
+            ServerMessage::Event(event) => event.event,
+            ServerMessage::Response(response) => panic!("unexpected response {:?}", response),
+        }
+    }
+}
+
+fn start_listener() -> SocketAddr {
+    let server = MafiaGameServer::new(
+        MafiaGameServerConfig {
+            randomize_death_message: false,
+            ..Default::default()
+        },
+        Arc::new(SystemClock),
+    );
+    let game_config = Arc::new(config::preset(config::DEFAULT_PRESET).unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let server = server.clone();
+            let game_config = Arc::clone(&game_config);
+
+            thread::spawn(move || handle_connection(server, game_config, stream.unwrap()));
+        }
+    });
+
+    addr
+}
+
+#[test]
+fn test_connection() {
+    let addr = start_listener();
+
+    let mut client0 = TestClient::connect(addr);
+
+    assert!(matches!(
+        client0.request(Request::SendMessage {
+            contents: Box::from("hello")
+        }),
+        Response::Error(_)
+    ));
+    client0.send_line("not json");
+    assert!(matches!(
+        client0.recv(),
+        ServerMessage::Response(Response::Error(_))
+    ));
+
+    let Response::Connected {
+        id: client0_id,
+        session_token: _,
+    } = client0.request(Request::Connect {
+        name: Box::from("garnet"),
+        resume_from: None,
+    })
+    else {
+        panic!("expected to connect");
+    };
+    assert_eq!(client0_id, ClientId(0));
+
+    assert!(matches!(*client0.next_event(), Event::SetServerInfo(_)));
+
+    assert!(matches!(
+        client0.request(Request::Connect {
+            name: Box::from("garnet"),
+            resume_from: None,
+        }),
+        Response::Error(_)
+    ));
+
+    assert_eq!(
+        client0.request(Request::SendMessage {
+            contents: Box::from("hello")
+        }),
+        Response::Ok
+    );
+    assert_eq!(
+        *client0.next_event(),
+        Event::MessageReceived(Message {
+            channel: EventChannel::Public,
+            contents: Box::from("hello"),
+            from: Entity::Client(client0_id),
+        })
+    );
+
+    // Not enough players for the default game.
+    assert!(matches!(
+        client0.request(Request::StartGame),
+        Response::Error(_)
+    ));
+
+    let mut client1 = TestClient::connect(addr);
+    assert!(matches!(
+        client1.request(Request::Connect {
+            name: Box::from("amethyst"),
+            resume_from: None,
+        }),
+        Response::Connected { .. }
+    ));
+    assert!(matches!(*client0.next_event(), Event::ClientConnected(_)));
+
+    assert_eq!(
+        client1.request(Request::CreateRoom {
+            name: Box::from("beach"),
+        }),
+        Response::Ok
+    );
+    assert_eq!(
+        *client0.next_event(),
+        Event::ClientDisconnected(ClientId(1))
+    );
+    let Response::Rooms(rooms) = client0.request(Request::ListRooms) else {
+        panic!("expected rooms");
+    };
+    assert_eq!(
+        rooms.iter().map(|room| &*room.name).collect::<Vec<_>>(),
+        ["beach", "lobby"]
+    );
+    assert_eq!(client1.request(Request::LeaveRoom), Response::Ok);
+    assert!(matches!(*client0.next_event(), Event::ClientConnected(_)));
+
+    // Closing the connection disconnects the client.
+    drop(client0);
+
+    loop {
+        if *client1.next_event() == Event::ClientDisconnected(client0_id) {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_connection_resume() {
+    let addr = start_listener();
+
+    let mut client0 = TestClient::connect(addr);
+    assert!(matches!(
+        client0.request(Request::Connect {
+            name: Box::from("garnet"),
+            resume_from: None,
+        }),
+        Response::Connected { .. }
+    ));
+    assert!(matches!(*client0.next_event(), Event::SetServerInfo(_)));
+
+    let mut client1 = TestClient::connect(addr);
+    assert!(matches!(
+        client1.request(Request::Connect {
+            name: Box::from("amethyst"),
+            resume_from: None,
+        }),
+        Response::Connected { .. }
+    ));
+    assert!(matches!(*client0.next_event(), Event::ClientConnected(_)));
+
+    // garnet's connection drops, and they miss a message.
+    let last_seq = client0.last_seq;
+    drop(client0);
+
+    loop {
+        if matches!(*client1.next_event(), Event::ClientDisconnected(_)) {
+            break;
+        }
+    }
+
+    assert_eq!(
+        client1.request(Request::SendMessage {
+            contents: Box::from("where did garnet go?")
+        }),
+        Response::Ok
+    );
+
+    let mut client0 = TestClient::connect(addr);
+    client0.last_seq = last_seq;
+    assert!(matches!(
+        client0.request(Request::Connect {
+            name: Box::from("garnet"),
+            resume_from: Some(last_seq),
+        }),
+        Response::Connected { .. }
+    ));
+
+    assert!(matches!(
+        &*client0.next_event(),
+        Event::MessageReceived(message) if &*message.contents == "where did garnet go?"
+    ));
+    assert!(matches!(*client0.next_event(), Event::SetServerInfo(_)));
+}